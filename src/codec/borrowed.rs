@@ -0,0 +1,554 @@
+//! Decoding Bencodex values which borrow their binaries and texts from the encoded bytes.
+//!
+//! [`decode_borrowed`] decodes a whole value at once like [`Decode::decode`], but without
+//! copying binaries and texts. [`LazyValue`] goes further and decodes nothing until asked,
+//! so a single subvalue of a large encoded value can be read without decoding the rest.
+//!
+//! [`Decode::decode`]: crate::Decode::decode
+
+use std::collections::BTreeMap;
+use std::result::Result;
+use std::str;
+
+use num_bigint::BigInt;
+
+use super::decode::{decode_impl, DecodeError};
+use super::types::{BencodexKey, BencodexValue};
+use crate::{BencodexPath, PathSegment, ValueKind};
+
+/// A dictionary key which borrows its bytes, the borrowed counterpart of [`BencodexKey`].
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+pub enum BorrowedKey<'a> {
+    Binary(&'a [u8]),
+    Text(&'a str),
+}
+
+impl<'a> BorrowedKey<'a> {
+    pub fn to_bencodex(&self) -> BencodexKey {
+        match self {
+            BorrowedKey::Binary(b) => BencodexKey::Binary(b.to_vec()),
+            BorrowedKey::Text(s) => BencodexKey::Text(s.to_string()),
+        }
+    }
+
+    fn matches(&self, key: &BencodexKey) -> bool {
+        match (self, key) {
+            (BorrowedKey::Binary(x), BencodexKey::Binary(y)) => x == y,
+            (BorrowedKey::Text(x), BencodexKey::Text(y)) => x == y,
+            _ => false,
+        }
+    }
+}
+
+/// A Bencodex value which borrows its binaries and texts from the encoded bytes, the
+/// borrowed counterpart of [`BencodexValue`].
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum BorrowedValue<'a> {
+    Boolean(bool),
+    Null,
+    Number(BigInt),
+    Binary(&'a [u8]),
+    Text(&'a str),
+    List(Vec<BorrowedValue<'a>>),
+    Dictionary(BTreeMap<BorrowedKey<'a>, BorrowedValue<'a>>),
+}
+
+impl<'a> BorrowedValue<'a> {
+    /// Copies the borrowed bytes to return an owned [`BencodexValue`].
+    pub fn to_bencodex(&self) -> BencodexValue {
+        match self {
+            BorrowedValue::Boolean(b) => BencodexValue::Boolean(*b),
+            BorrowedValue::Null => BencodexValue::Null,
+            BorrowedValue::Number(n) => BencodexValue::Number(n.clone()),
+            BorrowedValue::Binary(b) => BencodexValue::Binary(b.to_vec()),
+            BorrowedValue::Text(s) => BencodexValue::Text(s.to_string()),
+            BorrowedValue::List(list) => {
+                BencodexValue::List(list.iter().map(BorrowedValue::to_bencodex).collect())
+            }
+            BorrowedValue::Dictionary(dict) => BencodexValue::Dictionary(
+                dict.iter()
+                    .map(|(key, value)| (key.to_bencodex(), value.to_bencodex()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Reads the `<length>:` prefix of a binary or a text at `start`, and returns the length and
+/// the size of the prefix.
+fn read_length(bytes: &[u8], start: usize) -> Result<(usize, usize), DecodeError> {
+    let digits = bytes[start.min(bytes.len())..]
+        .iter()
+        .take_while(|byte| byte.is_ascii_digit())
+        .count();
+    let index = start + digits;
+    match bytes.get(index) {
+        None => return Err(DecodeError::InvalidBencodexValueError),
+        Some(b':') if digits > 0 => (),
+        Some(&token) => {
+            return Err(DecodeError::UnexpectedTokenError {
+                token,
+                point: index,
+            })
+        }
+    }
+
+    let length = str::from_utf8(&bytes[start..index])
+        .unwrap()
+        .parse::<usize>()
+        .map_err(|_| DecodeError::InvalidBencodexValueError)?;
+    if bytes.len() - (index + 1) < length {
+        return Err(DecodeError::InvalidBencodexValueError);
+    }
+
+    Ok((length, digits + 1))
+}
+
+fn read_binary(bytes: &[u8], start: usize) -> Result<(&[u8], usize), DecodeError> {
+    let (length, size) = read_length(bytes, start)?;
+    Ok((&bytes[start + size..start + size + length], size + length))
+}
+
+// start must be on 'u'
+fn read_text(bytes: &[u8], start: usize) -> Result<(&str, usize), DecodeError> {
+    let (text, size) = read_binary(bytes, start + 1)?;
+    let text = str::from_utf8(text).map_err(|_| DecodeError::InvalidBencodexValueError)?;
+    Ok((text, size + 1))
+}
+
+fn read_key(bytes: &[u8], start: usize) -> Result<(BorrowedKey<'_>, usize), DecodeError> {
+    match bytes.get(start) {
+        Some(b'u') => read_text(bytes, start).map(|(s, size)| (BorrowedKey::Text(s), size)),
+        Some(b'0'..=b'9') => {
+            read_binary(bytes, start).map(|(b, size)| (BorrowedKey::Binary(b), size))
+        }
+        _ => Err(DecodeError::InvalidBencodexValueError),
+    }
+}
+
+fn decode_borrowed_impl(
+    bytes: &[u8],
+    start: usize,
+) -> Result<(BorrowedValue<'_>, usize), DecodeError> {
+    match bytes.get(start) {
+        Some(b'l') => {
+            let mut size = 1;
+            let mut list = vec![];
+            while *bytes
+                .get(start + size)
+                .ok_or(DecodeError::InvalidBencodexValueError)?
+                != b'e'
+            {
+                let (value, value_size) = decode_borrowed_impl(bytes, start + size)?;
+                list.push(value);
+                size += value_size;
+            }
+
+            Ok((BorrowedValue::List(list), size + 1))
+        }
+        Some(b'd') => {
+            let mut size = 1;
+            let mut dict = BTreeMap::new();
+            while *bytes
+                .get(start + size)
+                .ok_or(DecodeError::InvalidBencodexValueError)?
+                != b'e'
+            {
+                let (key, key_size) = read_key(bytes, start + size)?;
+                size += key_size;
+                let (value, value_size) = decode_borrowed_impl(bytes, start + size)?;
+                if dict.insert(key, value).is_some() {
+                    return Err(DecodeError::InvalidBencodexValueError);
+                }
+                size += value_size;
+            }
+
+            Ok((BorrowedValue::Dictionary(dict), size + 1))
+        }
+        Some(b'u') => read_text(bytes, start).map(|(s, size)| (BorrowedValue::Text(s), size)),
+        Some(b'0'..=b'9') => {
+            read_binary(bytes, start).map(|(b, size)| (BorrowedValue::Binary(b), size))
+        }
+        _ => match decode_impl(bytes, start)? {
+            (BencodexValue::Boolean(b), size) => Ok((BorrowedValue::Boolean(b), size)),
+            (BencodexValue::Null, size) => Ok((BorrowedValue::Null, size)),
+            (BencodexValue::Number(n), size) => Ok((BorrowedValue::Number(n), size)),
+            _ => unreachable!("containers, binaries and texts are handled above"),
+        },
+    }
+}
+
+/// Returns the size of the encoded value at `start`, checking only its structure.
+fn skip(bytes: &[u8], start: usize) -> Result<usize, DecodeError> {
+    match bytes.get(start) {
+        Some(b'l') | Some(b'd') => {
+            let is_dict = bytes[start] == b'd';
+            let mut size = 1;
+            while *bytes
+                .get(start + size)
+                .ok_or(DecodeError::InvalidBencodexValueError)?
+                != b'e'
+            {
+                if is_dict {
+                    size += read_key(bytes, start + size)?.1;
+                }
+                size += skip(bytes, start + size)?;
+            }
+
+            Ok(size + 1)
+        }
+        Some(b'u') => read_length(bytes, start + 1).map(|(length, size)| 1 + size + length),
+        Some(b'0'..=b'9') => read_length(bytes, start).map(|(length, size)| size + length),
+        _ => decode_impl(bytes, start).map(|(_, size)| size),
+    }
+}
+
+/// Decodes a Bencodex value whose binaries and texts borrow from the given bytes.
+///
+/// Like [`Decode::decode`](crate::Decode::decode), bytes after the first value are ignored.
+///
+/// # Examples
+///
+/// ```
+/// use bencodex::codec::borrowed::{ decode_borrowed, BorrowedValue };
+///
+/// let bytes = b"l3:abcu3:defe";
+/// let value = decode_borrowed(bytes).unwrap();
+///
+/// assert_eq!(
+///     value,
+///     BorrowedValue::List(vec![BorrowedValue::Binary(b"abc"), BorrowedValue::Text("def")])
+/// );
+/// if let BorrowedValue::List(list) = &value {
+///     if let BorrowedValue::Binary(abc) = list[0] {
+///         assert_eq!(abc.as_ptr(), bytes[3..].as_ptr());
+///     }
+/// }
+/// ```
+pub fn decode_borrowed(bytes: &[u8]) -> Result<BorrowedValue<'_>, DecodeError> {
+    Ok(decode_borrowed_impl(bytes, 0)?.0)
+}
+
+/// An encoded Bencodex value which is decoded lazily, only as far as it is navigated.
+///
+/// Creating a `LazyValue` does not read anything. Navigating into a list or a dictionary
+/// reads only its own entries, skipping over the encodings of the subvalues which are not
+/// asked for, and decoding a subvalue reads only that subvalue. Structural errors are
+/// reported by the method which reads them.
+///
+/// # Examples
+///
+/// ```
+/// use bencodex::{ BencodexPath, BencodexValue };
+/// use bencodex::codec::borrowed::{ BorrowedValue, LazyValue };
+///
+/// let bytes = b"du3:txsl3:abci1eeu7:versioni2ee";
+/// let value = LazyValue::new(bytes);
+///
+/// let version = value.get(&"version".into()).unwrap().unwrap();
+/// assert_eq!(version.decode().unwrap(), BorrowedValue::Number(2.into()));
+///
+/// let path = BencodexPath::root().key("txs").index(0);
+/// let tx = value.get_path(&path).unwrap().unwrap();
+/// assert_eq!(tx.as_binary().unwrap(), Some(&b"abc"[..]));
+/// assert_eq!(value.get_path(&path.index(0)).unwrap(), None);
+/// ```
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct LazyValue<'a> {
+    bytes: &'a [u8],
+    start: usize,
+}
+
+impl<'a> LazyValue<'a> {
+    /// Creates a lazy value encoded at the beginning of the given bytes. Bytes after the
+    /// value are ignored.
+    pub fn new(bytes: &'a [u8]) -> LazyValue<'a> {
+        LazyValue { bytes, start: 0 }
+    }
+
+    /// Returns the kind of the value, reading only its first byte.
+    pub fn kind(&self) -> Result<ValueKind, DecodeError> {
+        match self.bytes.get(self.start) {
+            Some(b'n') => Ok(ValueKind::Null),
+            Some(b't') | Some(b'f') => Ok(ValueKind::Boolean),
+            Some(b'i') => Ok(ValueKind::Integer),
+            Some(b'0'..=b'9') => Ok(ValueKind::Binary),
+            Some(b'u') => Ok(ValueKind::Text),
+            Some(b'l') => Ok(ValueKind::List),
+            Some(b'd') => Ok(ValueKind::Dictionary),
+            Some(&token) => Err(DecodeError::UnexpectedTokenError {
+                token,
+                point: self.start,
+            }),
+            None => Err(DecodeError::InvalidBencodexValueError),
+        }
+    }
+
+    /// Returns the bytes of the input which encode the value, as they are. They are the
+    /// canonical encoding only if the input is encoded canonically.
+    pub fn encoded(&self) -> Result<&'a [u8], DecodeError> {
+        let size = skip(self.bytes, self.start)?;
+        Ok(&self.bytes[self.start..self.start + size])
+    }
+
+    /// Decodes the whole value.
+    pub fn decode(&self) -> Result<BorrowedValue<'a>, DecodeError> {
+        Ok(decode_borrowed_impl(self.bytes, self.start)?.0)
+    }
+
+    /// Returns the bytes if the value is a binary.
+    pub fn as_binary(&self) -> Result<Option<&'a [u8]>, DecodeError> {
+        match self.kind()? {
+            ValueKind::Binary => Ok(Some(read_binary(self.bytes, self.start)?.0)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the text if the value is a text.
+    pub fn as_text(&self) -> Result<Option<&'a str>, DecodeError> {
+        match self.kind()? {
+            ValueKind::Text => Ok(Some(read_text(self.bytes, self.start)?.0)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns an iterator over the elements if the value is a list.
+    pub fn items(&self) -> Result<Option<Items<'a>>, DecodeError> {
+        match self.kind()? {
+            ValueKind::List => Ok(Some(Items {
+                bytes: self.bytes,
+                index: self.start + 1,
+                done: false,
+            })),
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns an iterator over the entries if the value is a dictionary.
+    pub fn entries(&self) -> Result<Option<Entries<'a>>, DecodeError> {
+        match self.kind()? {
+            ValueKind::Dictionary => Ok(Some(Entries {
+                bytes: self.bytes,
+                index: self.start + 1,
+                done: false,
+            })),
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the element at the given index if the value is a list which has it.
+    pub fn index(&self, index: usize) -> Result<Option<LazyValue<'a>>, DecodeError> {
+        match self.items()? {
+            Some(mut items) => items.nth(index).transpose(),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the value of the given key if the value is a dictionary which has it.
+    pub fn get(&self, key: &BencodexKey) -> Result<Option<LazyValue<'a>>, DecodeError> {
+        if let Some(entries) = self.entries()? {
+            for entry in entries {
+                let (k, value) = entry?;
+                if k.matches(key) {
+                    return Ok(Some(value));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the subvalue at the given path, or [`None`] if there is no such subvalue.
+    pub fn get_path(&self, path: &BencodexPath) -> Result<Option<LazyValue<'a>>, DecodeError> {
+        let mut current = *self;
+        for segment in path {
+            let next = match segment {
+                PathSegment::Index(index) => current.index(*index)?,
+                PathSegment::Key(key) => current.get(key)?,
+            };
+            current = match next {
+                Some(next) => next,
+                None => return Ok(None),
+            };
+        }
+
+        Ok(Some(current))
+    }
+}
+
+/// An iterator over the elements of a list, returned by [`LazyValue::items`].
+#[derive(Debug, Clone)]
+pub struct Items<'a> {
+    bytes: &'a [u8],
+    index: usize,
+    done: bool,
+}
+
+impl<'a> Iterator for Items<'a> {
+    type Item = Result<LazyValue<'a>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.bytes.get(self.index) {
+            Some(b'e') => {
+                self.done = true;
+                None
+            }
+            _ => {
+                let item = LazyValue {
+                    bytes: self.bytes,
+                    start: self.index,
+                };
+                match skip(self.bytes, self.index) {
+                    Ok(size) => {
+                        self.index += size;
+                        Some(Ok(item))
+                    }
+                    Err(e) => {
+                        self.done = true;
+                        Some(Err(e))
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// An iterator over the entries of a dictionary, returned by [`LazyValue::entries`].
+#[derive(Debug, Clone)]
+pub struct Entries<'a> {
+    bytes: &'a [u8],
+    index: usize,
+    done: bool,
+}
+
+impl<'a> Entries<'a> {
+    fn read_entry(&mut self) -> Result<(BorrowedKey<'a>, LazyValue<'a>), DecodeError> {
+        let (key, key_size) = read_key(self.bytes, self.index)?;
+        let value = LazyValue {
+            bytes: self.bytes,
+            start: self.index + key_size,
+        };
+        self.index += key_size + skip(self.bytes, value.start)?;
+        Ok((key, value))
+    }
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = Result<(BorrowedKey<'a>, LazyValue<'a>), DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        if self.bytes.get(self.index) == Some(&b'e') {
+            self.done = true;
+            return None;
+        }
+
+        let entry = self.read_entry();
+        self.done = entry.is_err();
+        Some(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod decode_borrowed {
+        use super::super::*;
+        use crate::{BencodexDictionary, Encode};
+
+        #[test]
+        fn should_match_owned_decoding() {
+            let mut dict = BencodexDictionary::new();
+            dict.insert("text".into(), "한글".into());
+            dict.insert(vec![0u8, 1].into(), vec![0u8; 100].into());
+            dict.insert(
+                "list".into(),
+                vec![
+                    BencodexValue::Null,
+                    true.into(),
+                    (-123).into(),
+                    BencodexValue::Dictionary(BencodexDictionary::new()),
+                ]
+                .into(),
+            );
+            let value = BencodexValue::Dictionary(dict);
+            let mut bytes = vec![];
+            (&value).encode(&mut bytes).unwrap();
+
+            assert_eq!(value, decode_borrowed(&bytes).unwrap().to_bencodex());
+        }
+
+        #[test]
+        fn should_return_error_with_invalid_bytes() {
+            let invalid: Vec<&[u8]> = vec![
+                b"",
+                b"l",
+                b"5:abc",
+                b"u5:abc",
+                b"u2:\xff\xfe",
+                b"3abc",
+                b"di1ei2ee",
+                b"du1:ai1eu1:ai2ee",
+                b"x",
+            ];
+            for bytes in invalid {
+                assert!(decode_borrowed(bytes).is_err(), "{:?}", bytes);
+            }
+        }
+    }
+
+    mod lazy_value {
+        use super::super::*;
+
+        #[test]
+        fn should_navigate_without_decoding_others() {
+            // The first element is not valid UTF-8, but it is only skipped over.
+            let value = LazyValue::new(b"lu2:\xff\xffi1ee");
+            assert_eq!(
+                Some(BorrowedValue::Number(1.into())),
+                value.index(1).unwrap().map(|v| v.decode().unwrap())
+            );
+            assert_eq!(None, value.index(2).unwrap());
+            assert_eq!(ValueKind::List, value.kind().unwrap());
+            assert_eq!(b"lu2:\xff\xffi1ee", value.encoded().unwrap());
+            assert!(value.index(0).unwrap().unwrap().decode().is_err());
+        }
+
+        #[test]
+        fn should_iterate_entries() {
+            let value = LazyValue::new(b"d1:au1:bu1:cli1eee");
+            let entries: Vec<_> = value
+                .entries()
+                .unwrap()
+                .unwrap()
+                .map(|entry| entry.unwrap())
+                .collect();
+            assert_eq!(2, entries.len());
+            assert_eq!(BorrowedKey::Binary(b"a"), entries[0].0);
+            assert_eq!(Some("b"), entries[0].1.as_text().unwrap());
+            assert_eq!(BorrowedKey::Text("c"), entries[1].0);
+            assert_eq!(b"li1ee", entries[1].1.encoded().unwrap());
+            assert!(value.items().unwrap().is_none());
+            assert_eq!(None, value.get(&"a".into()).unwrap());
+        }
+
+        #[test]
+        fn should_return_error_with_truncated_bytes() {
+            let value = LazyValue::new(b"li1e3:ab");
+            let mut items = value.items().unwrap().unwrap();
+            assert!(items.next().unwrap().is_ok());
+            assert_eq!(
+                Some(Err(DecodeError::InvalidBencodexValueError)),
+                items.next()
+            );
+            assert_eq!(None, items.next());
+            assert!(value.encoded().is_err());
+        }
+    }
+}
@@ -93,7 +93,10 @@ impl Expect<u8> for u8 {
     }
 }
 
-fn decode_impl(vector: &[u8], start: usize) -> Result<(BencodexValue, usize), DecodeError> {
+pub(crate) fn decode_impl(
+    vector: &[u8],
+    start: usize,
+) -> Result<(BencodexValue, usize), DecodeError> {
     if start >= vector.len() {
         return Err(DecodeError::InvalidBencodexValueError);
     }
@@ -135,10 +138,9 @@ fn decode_dict_impl(vector: &[u8], start: usize) -> Result<(BencodexValue, usize
         index = start + tsize;
         let (value, size) = decode_impl(vector, index)?;
 
-        match map.insert(key, value) {
-            None => (),
-            Some(_) => todo!(),
-        };
+        if map.insert(key, value).is_some() {
+            return Err(DecodeError::InvalidBencodexValueError);
+        }
         tsize += size;
         index = start + tsize;
     }
@@ -196,10 +198,10 @@ fn decode_byte_string_impl(
         .should_not_be_none()?
         .expect(b':', index)?;
     tsize += 1;
-    let length_size = length.to_usize().unwrap();
-    if vector.len() < start + tsize + length_size {
-        return Err(DecodeError::InvalidBencodexValueError);
-    }
+    let length_size = match length.to_usize() {
+        Some(length) if length <= vector.len() - (start + tsize) => length,
+        _ => return Err(DecodeError::InvalidBencodexValueError),
+    };
     Ok((
         BencodexValue::Binary(vector[start + tsize..start + tsize + length_size].to_vec()),
         tsize + length_size,
@@ -239,10 +241,10 @@ fn decode_unicode_string_impl(
         .expect(b':', index)?;
     tsize += 1;
 
-    let length_size = length.to_usize().unwrap();
-    if vector.len() < start + tsize + length_size {
-        return Err(DecodeError::InvalidBencodexValueError);
-    }
+    let length_size = match length.to_usize() {
+        Some(length) if length <= vector.len() - (start + tsize) => length,
+        _ => return Err(DecodeError::InvalidBencodexValueError),
+    };
     let text = match str::from_utf8(&vector[start + tsize..start + tsize + length_size]) {
         Ok(v) => v,
         Err(_) => return Err(DecodeError::InvalidBencodexValueError),
@@ -323,6 +325,18 @@ impl Decode for Vec<u8> {
     }
 }
 
+impl Decode for &[u8] {
+    /// ```
+    /// use bencodex::{ Decode, BencodexValue };
+    ///
+    /// let buf: &[u8] = b"u3:foo";
+    /// assert_eq!(buf.decode().unwrap(), BencodexValue::Text("foo".to_string()));
+    /// ```
+    fn decode(self) -> Result<BencodexValue, DecodeError> {
+        Ok(decode_impl(self, 0)?.0)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     mod decode_impl {
@@ -425,6 +439,14 @@ mod tests {
                 decode_dict_impl(&vec![b'd', b'1', b':', b'a', b'k', b'e'], 0).unwrap_err()
             );
         }
+
+        #[test]
+        fn should_return_error_with_source_having_duplicate_key() {
+            assert_eq!(
+                DecodeError::InvalidBencodexValueError,
+                decode_dict_impl(b"du1:anu1:ane", 0).unwrap_err()
+            );
+        }
     }
 
     mod decode_list_impl {
@@ -490,6 +512,20 @@ mod tests {
                 decode_byte_string_impl(&vec![b'1', b'k', b'a'], 0).unwrap_err()
             );
         }
+
+        #[test]
+        fn should_return_error_with_too_large_length() {
+            let expected_error = DecodeError::InvalidBencodexValueError;
+            for source in [
+                &b"18446744073709551615:a"[..],
+                b"99999999999999999999999999:a",
+            ] {
+                assert_eq!(
+                    expected_error,
+                    decode_byte_string_impl(source, 0).unwrap_err()
+                );
+            }
+        }
     }
 
     mod decode_unicode_string_impl {
@@ -549,6 +585,20 @@ mod tests {
                 decode_unicode_string_impl(&vec![b'u', b'1', b':', 0x90], 0).unwrap_err()
             );
         }
+
+        #[test]
+        fn should_return_error_with_too_large_length() {
+            let expected_error = DecodeError::InvalidBencodexValueError;
+            for source in [
+                &b"u18446744073709551615:a"[..],
+                b"u99999999999999999999999999:a",
+            ] {
+                assert_eq!(
+                    expected_error,
+                    decode_unicode_string_impl(source, 0).unwrap_err()
+                );
+            }
+        }
     }
 
     mod decode_number_impl {
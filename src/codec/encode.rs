@@ -1,4 +1,5 @@
 use super::types::*;
+use crate::path::{BencodexPath, PathSegment};
 use itertools::Itertools;
 use num_bigint::BigInt;
 use std::cmp::Ordering;
@@ -29,22 +30,35 @@ pub trait Encode {
     fn encode(self, writer: &mut dyn io::Write) -> Result<(), std::io::Error>;
 }
 
-impl Encode for Vec<u8> {
+impl Encode for &[u8] {
     /// ```
     /// use bencodex::{ Encode };
     ///
     /// let mut buf = vec![];
-    /// b"hello".to_vec().encode(&mut buf);
+    /// b"hello".as_slice().encode(&mut buf);
     /// assert_eq!(buf, b"5:hello");
     /// ```
     fn encode(self, writer: &mut dyn io::Write) -> Result<(), std::io::Error> {
         write!(writer, "{}:", self.len())?;
-        writer.write_all(&self)?;
+        writer.write_all(self)?;
 
         Ok(())
     }
 }
 
+impl Encode for Vec<u8> {
+    /// ```
+    /// use bencodex::{ Encode };
+    ///
+    /// let mut buf = vec![];
+    /// b"hello".to_vec().encode(&mut buf);
+    /// assert_eq!(buf, b"5:hello");
+    /// ```
+    fn encode(self, writer: &mut dyn io::Write) -> Result<(), std::io::Error> {
+        self.as_slice().encode(writer)
+    }
+}
+
 impl Encode for i64 {
     /// ```
     /// use bencodex::{ Encode };
@@ -58,23 +72,36 @@ impl Encode for i64 {
     }
 }
 
-impl Encode for String {
+impl Encode for &str {
     /// ```
     /// use bencodex::{ Encode };
     ///
     /// let mut buf = vec![];
-    /// "foo".to_string().encode(&mut buf);
+    /// "foo".encode(&mut buf);
     /// assert_eq!(buf, b"u3:foo");
     /// ```
     fn encode(self, writer: &mut dyn io::Write) -> Result<(), std::io::Error> {
-        let bytes = self.into_bytes();
+        let bytes = self.as_bytes();
         write!(writer, "u{}:", bytes.len())?;
-        writer.write_all(&bytes)?;
+        writer.write_all(bytes)?;
 
         Ok(())
     }
 }
 
+impl Encode for String {
+    /// ```
+    /// use bencodex::{ Encode };
+    ///
+    /// let mut buf = vec![];
+    /// "foo".to_string().encode(&mut buf);
+    /// assert_eq!(buf, b"u3:foo");
+    /// ```
+    fn encode(self, writer: &mut dyn io::Write) -> Result<(), std::io::Error> {
+        self.as_str().encode(writer)
+    }
+}
+
 impl Encode for bool {
     /// ```
     /// use bencodex::{ Encode };
@@ -93,14 +120,14 @@ impl Encode for bool {
     }
 }
 
-impl Encode for BigInt {
+impl Encode for &BigInt {
     /// ```
     /// use bencodex::{ Encode };
     /// use num_bigint::BigInt;
     ///
     /// let mut buf = vec![];
-    /// BigInt::from(0).encode(&mut buf);
-    /// assert_eq!(buf, b"i0e");
+    /// (&BigInt::from(-1)).encode(&mut buf);
+    /// assert_eq!(buf, b"i-1e");
     /// ```
     fn encode(self, writer: &mut dyn io::Write) -> Result<(), std::io::Error> {
         writer.write_all(&[b'i'])?;
@@ -111,14 +138,27 @@ impl Encode for BigInt {
     }
 }
 
-impl Encode for Vec<BencodexValue> {
+impl Encode for BigInt {
     /// ```
-    /// use bencodex::{ Encode, BencodexValue };
+    /// use bencodex::{ Encode };
     /// use num_bigint::BigInt;
     ///
+    /// let mut buf = vec![];
+    /// BigInt::from(0).encode(&mut buf);
+    /// assert_eq!(buf, b"i0e");
+    /// ```
+    fn encode(self, writer: &mut dyn io::Write) -> Result<(), std::io::Error> {
+        (&self).encode(writer)
+    }
+}
+
+impl Encode for &Vec<BencodexValue> {
+    /// ```
+    /// use bencodex::{ Encode, BencodexValue };
+    ///
     /// let list: Vec<BencodexValue> = vec![0.into(), BencodexValue::Null];
     /// let mut buf = vec![];
-    /// list.encode(&mut buf);
+    /// (&list).encode(&mut buf);
     /// assert_eq!(buf, b"li0ene");
     /// ```
     fn encode(self, writer: &mut dyn io::Write) -> Result<(), std::io::Error> {
@@ -132,18 +172,85 @@ impl Encode for Vec<BencodexValue> {
     }
 }
 
+impl Encode for Vec<BencodexValue> {
+    /// ```
+    /// use bencodex::{ Encode, BencodexValue };
+    /// use num_bigint::BigInt;
+    ///
+    /// let list: Vec<BencodexValue> = vec![0.into(), BencodexValue::Null];
+    /// let mut buf = vec![];
+    /// list.encode(&mut buf);
+    /// assert_eq!(buf, b"li0ene");
+    /// ```
+    fn encode(self, writer: &mut dyn io::Write) -> Result<(), std::io::Error> {
+        (&self).encode(writer)
+    }
+}
+
+fn count_digits(n: usize) -> u64 {
+    n.to_string().len() as u64
+}
+
+fn bytes_encoding_length(length: usize) -> u64 {
+    count_digits(length) + 1 + length as u64
+}
+
+/// Returns the length of the canonical encoding of the key.
+pub(crate) fn key_encoding_length(key: &BencodexKey) -> u64 {
+    match key {
+        BencodexKey::Binary(x) => bytes_encoding_length(x.len()),
+        BencodexKey::Text(x) => 1 + bytes_encoding_length(x.len()),
+    }
+}
+
+impl BencodexValue {
+    /// Returns the length of the canonical encoding of this value, without encoding it.
+    ///
+    /// ```
+    /// use bencodex::BencodexValue;
+    ///
+    /// let value: BencodexValue = vec![BencodexValue::from("foo"), 12.into()].into();
+    /// assert_eq!(value.encoding_length(), b"lu3:fooi12ee".len() as u64);
+    /// ```
+    pub fn encoding_length(&self) -> u64 {
+        match self {
+            BencodexValue::Null | BencodexValue::Boolean(_) => 1,
+            BencodexValue::Number(x) => x.to_str_radix(10).len() as u64 + 2,
+            BencodexValue::Binary(x) => bytes_encoding_length(x.len()),
+            BencodexValue::Text(x) => 1 + bytes_encoding_length(x.len()),
+            BencodexValue::List(x) => 2 + x.iter().map(BencodexValue::encoding_length).sum::<u64>(),
+            BencodexValue::Dictionary(x) => {
+                2 + x
+                    .iter()
+                    .map(|(key, value)| key_encoding_length(key) + value.encoding_length())
+                    .sum::<u64>()
+            }
+        }
+    }
+}
+
 fn encode_null(writer: &mut dyn io::Write) -> Result<(), std::io::Error> {
     writer.write_all(&[b'n'])?;
 
     Ok(())
 }
 
-impl Encode for BencodexValue {
+impl Encode for &BencodexValue {
+    /// Encodes the value without taking its ownership, so it is not needed to clone it.
+    ///
+    /// ```
+    /// use bencodex::{ Encode, BencodexValue };
+    ///
+    /// let value: BencodexValue = vec![BencodexValue::Null].into();
+    /// let mut buf = vec![];
+    /// (&value).encode(&mut buf);
+    /// assert_eq!(buf, b"lne");
+    /// ```
     fn encode(self, writer: &mut dyn io::Write) -> Result<(), std::io::Error> {
         // FIXME: rewrite more beautiful.
         match self {
-            BencodexValue::Binary(x) => x.encode(writer)?,
-            BencodexValue::Text(x) => x.encode(writer)?,
+            BencodexValue::Binary(x) => x.as_slice().encode(writer)?,
+            BencodexValue::Text(x) => x.as_str().encode(writer)?,
             BencodexValue::Dictionary(x) => x.encode(writer)?,
             BencodexValue::List(x) => x.encode(writer)?,
             BencodexValue::Boolean(x) => x.encode(writer)?,
@@ -155,6 +262,12 @@ impl Encode for BencodexValue {
     }
 }
 
+impl Encode for BencodexValue {
+    fn encode(self, writer: &mut dyn io::Write) -> Result<(), std::io::Error> {
+        (&self).encode(writer)
+    }
+}
+
 fn compare_vector<T: Ord>(xs: &[T], ys: &[T]) -> Ordering {
     for (x, y) in xs.iter().zip(ys) {
         match x.cmp(y) {
@@ -176,7 +289,23 @@ fn compare_key(x: &BencodexKey, y: &BencodexKey) -> Ordering {
     }
 }
 
-impl Encode for BTreeMap<BencodexKey, BencodexValue> {
+impl Encode for &BencodexKey {
+    /// ```
+    /// use bencodex::{ Encode, BencodexKey };
+    ///
+    /// let mut buf = vec![];
+    /// (&BencodexKey::from("foo")).encode(&mut buf);
+    /// assert_eq!(buf, b"u3:foo");
+    /// ```
+    fn encode(self, writer: &mut dyn io::Write) -> Result<(), std::io::Error> {
+        match self {
+            BencodexKey::Binary(x) => x.as_slice().encode(writer),
+            BencodexKey::Text(x) => x.as_str().encode(writer),
+        }
+    }
+}
+
+impl Encode for &BTreeMap<BencodexKey, BencodexValue> {
     /// ```
     /// use bencodex::{ Encode, BencodexKey, BencodexValue };
     /// use std::collections::BTreeMap;
@@ -185,22 +314,15 @@ impl Encode for BTreeMap<BencodexKey, BencodexValue> {
     /// dict.insert("".into(), "".into());
     ///
     /// let mut buf = vec![];
-    /// dict.encode(&mut buf);
+    /// (&dict).encode(&mut buf);
     ///
     /// assert_eq!(buf, b"du0:u0:e")
     /// ```
     fn encode(self, writer: &mut dyn io::Write) -> Result<(), std::io::Error> {
-        let pairs = self
-            .into_iter()
-            .sorted_by(|(x, _), (y, _)| compare_key(x, y));
+        let pairs = self.iter().sorted_by(|(x, _), (y, _)| compare_key(x, y));
 
         writer.write_all(&[b'd'])?;
         for (key, value) in pairs {
-            let key = match key {
-                BencodexKey::Binary(x) => BencodexValue::Binary(x),
-                BencodexKey::Text(x) => BencodexValue::Text(x),
-            };
-
             key.encode(writer)?;
             value.encode(writer)?;
         }
@@ -210,6 +332,137 @@ impl Encode for BTreeMap<BencodexKey, BencodexValue> {
     }
 }
 
+impl Encode for BTreeMap<BencodexKey, BencodexValue> {
+    /// ```
+    /// use bencodex::{ Encode, BencodexKey, BencodexValue };
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut dict: BTreeMap<BencodexKey, BencodexValue> = BTreeMap::new();
+    /// dict.insert("".into(), "".into());
+    ///
+    /// let mut buf = vec![];
+    /// dict.encode(&mut buf);
+    ///
+    /// assert_eq!(buf, b"du0:u0:e")
+    /// ```
+    fn encode(self, writer: &mut dyn io::Write) -> Result<(), std::io::Error> {
+        (&self).encode(writer)
+    }
+}
+
+/// Encodes the value leaving out the dictionary entries and list elements at the given paths.
+///
+/// It is useful for encoding signed payloads, which are signed over their encoding without
+/// their signatures. The value is not cloned, and the dictionaries are encoded in canonical
+/// order as [`Encode`] does. Paths which do not point to a subvalue are ignored, and so is the
+/// root path, as there is nothing to leave it out of.
+///
+/// # Examples
+///
+/// ```
+/// use bencodex::{ BencodexPath, BencodexValue };
+/// use bencodex::codec::encode::encode_excluding;
+/// use std::collections::BTreeMap;
+///
+/// let mut tx = BTreeMap::new();
+/// tx.insert("nonce".into(), 1.into());
+/// tx.insert("signature".into(), vec![0xab, 0xcd].into());
+/// let tx = BencodexValue::Dictionary(tx);
+///
+/// let payload = encode_excluding(&tx, &[BencodexPath::root().key("signature")]);
+/// assert_eq!(payload, b"du5:noncei1ee");
+/// ```
+pub fn encode_excluding(value: &BencodexValue, excluded: &[BencodexPath]) -> Vec<u8> {
+    let mut buf = vec![];
+    encode_excluding_to(value, excluded, &mut buf).expect("writing to a Vec never fails");
+    buf
+}
+
+/// Writes the encoding of the value leaving out the subvalues at the given paths, like
+/// [`encode_excluding`] but into the writer.
+///
+/// ```
+/// use bencodex::{ BencodexPath, BencodexValue };
+/// use bencodex::codec::encode::encode_excluding_to;
+///
+/// let list: BencodexValue = vec![BencodexValue::from(1), 2.into(), 3.into()].into();
+/// let mut buf = vec![];
+/// encode_excluding_to(&list, &[BencodexPath::root().index(1)], &mut buf).unwrap();
+/// assert_eq!(buf, b"li1ei3ee");
+/// ```
+pub fn encode_excluding_to(
+    value: &BencodexValue,
+    excluded: &[BencodexPath],
+    writer: &mut dyn io::Write,
+) -> Result<(), std::io::Error> {
+    let excluded = excluded
+        .iter()
+        .map(BencodexPath::segments)
+        .filter(|segments| !segments.is_empty())
+        .collect_vec();
+    encode_excluding_impl(value, &excluded, writer)
+}
+
+fn encode_excluding_impl(
+    value: &BencodexValue,
+    excluded: &[&[PathSegment]],
+    writer: &mut dyn io::Write,
+) -> Result<(), std::io::Error> {
+    if excluded.is_empty() {
+        return value.encode(writer);
+    }
+
+    match value {
+        BencodexValue::List(list) => {
+            writer.write_all(b"l")?;
+            for (index, el) in list.iter().enumerate() {
+                if let Some(rests) =
+                    descend(excluded, |segment| segment == &PathSegment::Index(index))
+                {
+                    encode_excluding_impl(el, &rests, writer)?;
+                }
+            }
+            writer.write_all(b"e")?;
+        }
+        BencodexValue::Dictionary(dict) => {
+            let pairs = dict.iter().sorted_by(|(x, _), (y, _)| compare_key(x, y));
+
+            writer.write_all(b"d")?;
+            for (key, value) in pairs {
+                if let Some(rests) = descend(
+                    excluded,
+                    |segment| matches!(segment, PathSegment::Key(k) if k == key),
+                ) {
+                    key.encode(writer)?;
+                    encode_excluding_impl(value, &rests, writer)?;
+                }
+            }
+            writer.write_all(b"e")?;
+        }
+        _ => value.encode(writer)?,
+    }
+
+    Ok(())
+}
+
+/// Returns the excluded paths under the segment matching the predicate, or [`None`] if the
+/// segment itself is excluded.
+fn descend<'a>(
+    excluded: &[&'a [PathSegment]],
+    matches: impl Fn(&PathSegment) -> bool,
+) -> Option<Vec<&'a [PathSegment]>> {
+    let mut rests = vec![];
+    for segments in excluded {
+        if matches(&segments[0]) {
+            if segments.len() == 1 {
+                return None;
+            }
+            rests.push(&segments[1..]);
+        }
+    }
+    Some(rests)
+}
+
 #[cfg(test)]
 mod tests {
     mod compare_key {
@@ -256,6 +509,33 @@ mod tests {
         }
     }
 
+    mod bencodex_key {
+        use super::super::*;
+
+        #[test]
+        fn ord_should_match_compare_key() {
+            let keys: Vec<BencodexKey> = vec![
+                "".into(),
+                "a".into(),
+                "ab".into(),
+                "b".into(),
+                "\u{ff}".into(),
+                "가".into(),
+                Vec::<u8>::new().into(),
+                vec![0u8].into(),
+                vec![0u8, 0].into(),
+                vec![1u8].into(),
+                vec![0xffu8].into(),
+                b"a".to_vec().into(),
+            ];
+            for x in &keys {
+                for y in &keys {
+                    assert_eq!(compare_key(x, y), x.cmp(y), "comparing {} with {}", x, y);
+                }
+            }
+        }
+    }
+
     mod compare_vector {
         use super::super::*;
 
@@ -295,13 +575,13 @@ mod tests {
     }
 
     mod encode {
-        struct ConditionFailWriter {
+        pub(super) struct ConditionFailWriter {
             throw_counts: Vec<u64>,
             call_count: u64,
         }
 
         impl ConditionFailWriter {
-            fn new(throw_counts: Vec<u64>) -> ConditionFailWriter {
+            pub(super) fn new(throw_counts: Vec<u64>) -> ConditionFailWriter {
                 ConditionFailWriter {
                     throw_counts: throw_counts,
                     call_count: 0,
@@ -562,4 +842,85 @@ mod tests {
             }
         }
     }
+
+    mod encode_excluding {
+        use super::super::*;
+        use super::encode::ConditionFailWriter;
+
+        fn tx() -> BencodexValue {
+            let mut signer = BTreeMap::new();
+            signer.insert(BencodexKey::from("key"), BencodexValue::from(vec![1u8]));
+            signer.insert(BencodexKey::from("sig"), BencodexValue::from(vec![2u8]));
+
+            let mut tx = BTreeMap::new();
+            tx.insert(BencodexKey::from(vec![b'z']), BencodexValue::Null);
+            tx.insert(
+                BencodexKey::from("a"),
+                vec![BencodexValue::from(1), 2.into()].into(),
+            );
+            tx.insert(BencodexKey::from("signer"), signer.into());
+            tx.insert(BencodexKey::from("sig"), BencodexValue::from(vec![3u8]));
+            tx.into()
+        }
+
+        #[test]
+        fn should_equal_encode_without_exclusions() {
+            let value = tx();
+            let mut expected = vec![];
+            (&value).encode(&mut expected).unwrap();
+            assert_eq!(expected, encode_excluding(&value, &[]));
+            assert_eq!(expected, encode_excluding(&value, &[BencodexPath::root()]));
+        }
+
+        #[test]
+        fn should_exclude_nested_keys_in_canonical_order() {
+            let value = tx();
+            let excluded = [
+                BencodexPath::root().key("sig"),
+                BencodexPath::root().key("signer").key("sig"),
+                BencodexPath::root().key("a").index(0),
+            ];
+            assert_eq!(
+                b"d1:znu1:ali2eeu6:signerdu3:key1:\x01ee".to_vec(),
+                encode_excluding(&value, &excluded)
+            );
+
+            let mut expected = value.clone();
+            if let BencodexValue::Dictionary(dict) = &mut expected {
+                dict.remove(&"sig".into());
+                if let Some(BencodexValue::Dictionary(signer)) = dict.get_mut(&"signer".into()) {
+                    signer.remove(&"sig".into());
+                }
+                if let Some(BencodexValue::List(list)) = dict.get_mut(&"a".into()) {
+                    list.remove(0);
+                }
+            }
+            let mut buf = vec![];
+            (&expected).encode(&mut buf).unwrap();
+            assert_eq!(buf, encode_excluding(&value, &excluded));
+        }
+
+        #[test]
+        fn should_ignore_paths_to_nothing() {
+            let value = tx();
+            let mut expected = vec![];
+            (&value).encode(&mut expected).unwrap();
+            let excluded = [
+                BencodexPath::root().key("missing"),
+                BencodexPath::root().key("sig").key("inner"),
+                BencodexPath::root().key("a").index(5),
+                BencodexPath::root().index(0),
+            ];
+            assert_eq!(expected, encode_excluding(&value, &excluded));
+        }
+
+        #[test]
+        fn should_pass_error() {
+            let value = tx();
+            let mut writer = ConditionFailWriter::new(vec![1]);
+            let err = encode_excluding_to(&value, &[BencodexPath::root().key("sig")], &mut writer)
+                .unwrap_err();
+            assert_eq!(std::io::ErrorKind::Other, err.kind());
+        }
+    }
 }
@@ -0,0 +1,310 @@
+//! Decoded Bencodex values which keep the bytes they were decoded from.
+//!
+//! The decoder accepts some encodings which are not canonical, like dictionaries whose keys
+//! are not sorted, or integers with leading zeros. Decoding them into a [`BencodexValue`]
+//! and encoding it again does not give the same bytes, which breaks signatures made over
+//! the original bytes. An [`EncodedValue`] keeps the original bytes of each subtree, and
+//! [`Encode`] copies them verbatim for the subtrees which are not modified.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::result::Result;
+
+use super::decode::{decode_impl, DecodeError};
+use super::encode::Encode;
+use super::types::{BencodexKey, BencodexValue};
+
+/// The parsed contents of an [`EncodedValue`].
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum EncodedNode<'a> {
+    /// A value which is neither a list nor a dictionary.
+    Value(BencodexValue),
+    List(Vec<EncodedValue<'a>>),
+    Dictionary(BTreeMap<BencodexKey, EncodedValue<'a>>),
+}
+
+/// A decoded Bencodex value which keeps the bytes each of its subtrees was decoded from.
+///
+/// Its contents are modified through [`EncodedValue::node_mut`], which drops the original
+/// bytes of the value. As a subvalue can only be reached mutably through the `node_mut` of
+/// all its ancestors, they drop their original bytes too, while their other subvalues keep
+/// theirs.
+///
+/// # Examples
+///
+/// ```
+/// use bencodex::{ BencodexValue, Encode };
+/// use bencodex::codec::encoded::{ EncodedNode, EncodedValue };
+///
+/// // The keys of the inner dictionary are not sorted, so it is not canonical.
+/// let bytes = b"lu1:xdu1:bi2eu1:ai1eee";
+/// let mut value = EncodedValue::decode(bytes).unwrap();
+/// assert!(!value.is_canonical());
+///
+/// if let EncodedNode::List(list) = value.node_mut() {
+///     list[0] = BencodexValue::from("y").into();
+/// }
+///
+/// // The inner dictionary is written as it was given, not in its canonical form.
+/// let mut buf = vec![];
+/// (&value).encode(&mut buf).unwrap();
+/// assert_eq!(buf, b"lu1:ydu1:bi2eu1:ai1eee");
+/// ```
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct EncodedValue<'a> {
+    raw: Option<&'a [u8]>,
+    node: EncodedNode<'a>,
+}
+
+impl<'a> EncodedValue<'a> {
+    /// Decodes a Bencodex value, keeping the bytes of each subtree. Bytes after the first
+    /// value are ignored.
+    pub fn decode(bytes: &'a [u8]) -> Result<EncodedValue<'a>, DecodeError> {
+        Ok(decode_encoded_impl(bytes, 0)?.0)
+    }
+
+    /// Returns the bytes the value was decoded from, or [`None`] if it was modified or not
+    /// decoded at all.
+    pub fn raw(&self) -> Option<&'a [u8]> {
+        self.raw
+    }
+
+    pub fn node(&self) -> &EncodedNode<'a> {
+        &self.node
+    }
+
+    /// Returns the contents to modify, dropping the original bytes of the value.
+    pub fn node_mut(&mut self) -> &mut EncodedNode<'a> {
+        self.raw = None;
+        &mut self.node
+    }
+
+    /// Returns the element at the given index if this is a list.
+    pub fn index(&self, index: usize) -> Option<&EncodedValue<'a>> {
+        match &self.node {
+            EncodedNode::List(list) => list.get(index),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the given key if this is a dictionary.
+    pub fn get(&self, key: &BencodexKey) -> Option<&EncodedValue<'a>> {
+        match &self.node {
+            EncodedNode::Dictionary(dict) => dict.get(key),
+            _ => None,
+        }
+    }
+
+    /// Returns the parsed value, without the original bytes.
+    pub fn to_value(&self) -> BencodexValue {
+        match &self.node {
+            EncodedNode::Value(value) => value.clone(),
+            EncodedNode::List(list) => {
+                BencodexValue::List(list.iter().map(EncodedValue::to_value).collect())
+            }
+            EncodedNode::Dictionary(dict) => BencodexValue::Dictionary(
+                dict.iter()
+                    .map(|(key, value)| (key.clone(), value.to_value()))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Returns whether the value would be encoded the same as its canonical encoding, i.e.,
+    /// the encoding of [`to_value`](EncodedValue::to_value).
+    pub fn is_canonical(&self) -> bool {
+        let mut encoded = vec![];
+        let mut canonical = vec![];
+        self.encode(&mut encoded)
+            .and_then(|_| (&self.to_value()).encode(&mut canonical))
+            .expect("writing to a Vec never fails");
+        encoded == canonical
+    }
+}
+
+impl From<BencodexValue> for EncodedValue<'static> {
+    /// Wraps a value which was not decoded, so it has no original bytes.
+    fn from(value: BencodexValue) -> Self {
+        let node = match value {
+            BencodexValue::List(list) => {
+                EncodedNode::List(list.into_iter().map(EncodedValue::from).collect())
+            }
+            BencodexValue::Dictionary(dict) => EncodedNode::Dictionary(
+                dict.into_iter()
+                    .map(|(key, value)| (key, EncodedValue::from(value)))
+                    .collect(),
+            ),
+            value => EncodedNode::Value(value),
+        };
+
+        EncodedValue { raw: None, node }
+    }
+}
+
+impl Encode for &EncodedValue<'_> {
+    /// Writes the original bytes of the value if it has them, and otherwise encodes it,
+    /// writing the original bytes of its subvalues which have them.
+    fn encode(self, writer: &mut dyn io::Write) -> Result<(), io::Error> {
+        if let Some(raw) = self.raw {
+            return writer.write_all(raw);
+        }
+
+        match &self.node {
+            EncodedNode::Value(value) => value.encode(writer),
+            EncodedNode::List(list) => {
+                writer.write_all(b"l")?;
+                for item in list {
+                    item.encode(writer)?;
+                }
+                writer.write_all(b"e")
+            }
+            EncodedNode::Dictionary(dict) => {
+                writer.write_all(b"d")?;
+                for (key, value) in dict {
+                    key.encode(writer)?;
+                    value.encode(writer)?;
+                }
+                writer.write_all(b"e")
+            }
+        }
+    }
+}
+
+fn decode_encoded_impl(
+    bytes: &[u8],
+    start: usize,
+) -> Result<(EncodedValue<'_>, usize), DecodeError> {
+    let (node, size) = match bytes.get(start) {
+        Some(b'l') => {
+            let mut size = 1;
+            let mut list = vec![];
+            while *bytes
+                .get(start + size)
+                .ok_or(DecodeError::InvalidBencodexValueError)?
+                != b'e'
+            {
+                let (value, value_size) = decode_encoded_impl(bytes, start + size)?;
+                list.push(value);
+                size += value_size;
+            }
+
+            (EncodedNode::List(list), size + 1)
+        }
+        Some(b'd') => {
+            let mut size = 1;
+            let mut dict = BTreeMap::new();
+            while *bytes
+                .get(start + size)
+                .ok_or(DecodeError::InvalidBencodexValueError)?
+                != b'e'
+            {
+                let (key, key_size) = decode_impl(bytes, start + size)?;
+                let key = match key {
+                    BencodexValue::Text(s) => BencodexKey::Text(s),
+                    BencodexValue::Binary(b) => BencodexKey::Binary(b),
+                    _ => return Err(DecodeError::InvalidBencodexValueError),
+                };
+                size += key_size;
+
+                let (value, value_size) = decode_encoded_impl(bytes, start + size)?;
+                if dict.insert(key, value).is_some() {
+                    return Err(DecodeError::InvalidBencodexValueError);
+                }
+                size += value_size;
+            }
+
+            (EncodedNode::Dictionary(dict), size + 1)
+        }
+        _ => {
+            let (value, size) = decode_impl(bytes, start)?;
+            (EncodedNode::Value(value), size)
+        }
+    };
+
+    Ok((
+        EncodedValue {
+            raw: Some(&bytes[start..start + size]),
+            node,
+        },
+        size,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    mod encoded_value {
+        use super::super::*;
+
+        fn encode(value: &EncodedValue) -> Vec<u8> {
+            let mut buf = vec![];
+            value.encode(&mut buf).unwrap();
+            buf
+        }
+
+        #[test]
+        fn should_keep_raw_bytes_of_subtrees() {
+            let bytes = b"du1:bi01eu1:ali-0e3:abceeabc";
+            let value = EncodedValue::decode(bytes).unwrap();
+            assert_eq!(Some(&bytes[..25]), value.raw());
+            assert_eq!(
+                Some(&b"i01e"[..]),
+                value.get(&"b".into()).and_then(EncodedValue::raw)
+            );
+            assert_eq!(
+                Some(&b"li-0e3:abce"[..]),
+                value.get(&"a".into()).and_then(EncodedValue::raw)
+            );
+            assert_eq!(&bytes[..25], encode(&value).as_slice());
+            assert!(!value.is_canonical());
+
+            let mut expected = crate::BencodexDictionary::new();
+            expected.insert(
+                "a".into(),
+                vec![BencodexValue::from(0), vec![b'a', b'b', b'c'].into()].into(),
+            );
+            expected.insert("b".into(), 1.into());
+            assert_eq!(BencodexValue::Dictionary(expected), value.to_value());
+        }
+
+        #[test]
+        fn should_reencode_modified_ancestors_only() {
+            let mut value = EncodedValue::decode(b"du1:bi01eu1:ali-0e3:abcee").unwrap();
+            if let EncodedNode::Dictionary(dict) = value.node_mut() {
+                let a = dict.get_mut(&"a".into()).unwrap();
+                if let EncodedNode::List(list) = a.node_mut() {
+                    list.push(BencodexValue::Null.into());
+                }
+            }
+
+            assert_eq!(None, value.raw());
+            assert_eq!(None, value.get(&"a".into()).unwrap().raw());
+            assert_eq!(b"du1:ali-0e3:abcneu1:bi01ee".to_vec(), encode(&value));
+        }
+
+        #[test]
+        fn should_encode_canonically_without_raw_bytes() {
+            let value: BencodexValue = vec![BencodexValue::from(1), "a".into()].into();
+            let encoded = EncodedValue::from(value.clone());
+            assert_eq!(None, encoded.raw());
+            assert!(encoded.is_canonical());
+            assert_eq!(b"li1eu1:ae".to_vec(), encode(&encoded));
+            assert_eq!(value, encoded.to_value());
+        }
+
+        #[test]
+        fn should_return_error_with_invalid_bytes() {
+            assert_eq!(
+                Err(DecodeError::InvalidBencodexValueError),
+                EncodedValue::decode(b"li1e")
+            );
+            assert_eq!(
+                Err(DecodeError::InvalidBencodexValueError),
+                EncodedValue::decode(b"du1:ai1eu1:ai2ee")
+            );
+            assert_eq!(
+                Err(DecodeError::InvalidBencodexValueError),
+                EncodedValue::decode(b"di1ei2ee")
+            );
+        }
+    }
+}
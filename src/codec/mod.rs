@@ -1,3 +1,6 @@
+pub mod borrowed;
 pub mod decode;
 pub mod encode;
+pub mod encoded;
+pub mod stream;
 pub mod types;
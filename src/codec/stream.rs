@@ -0,0 +1,365 @@
+//! Reading Bencodex values from a reader as a stream of events, without decoding them
+//! into [`BencodexValue`](crate::BencodexValue)s.
+
+use std::io::{self, BufRead, Read};
+use std::str::FromStr;
+
+use num_bigint::BigInt;
+
+use super::decode::DecodeError;
+
+/// A token of a Bencodex value, read by [`EventReader`].
+///
+/// Lists and dictionaries are read as [`Event::ListStart`] and [`Event::DictionaryStart`],
+/// followed by the events of their elements, and then [`Event::End`]. In a dictionary, the
+/// events of each key, [`Event::Binary`] or [`Event::Text`], come before the events of its value.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Event {
+    Null,
+    Boolean(bool),
+    Number(BigInt),
+    Binary(Vec<u8>),
+    Text(String),
+    ListStart,
+    DictionaryStart,
+    End,
+}
+
+enum Frame {
+    List,
+    Dictionary { expects_key: bool },
+}
+
+/// A pull parser which reads a Bencodex value from a reader event by event.
+///
+/// Only a container per nesting level is kept in memory, so it reads values larger than
+/// the memory. It reads a single value, so [`EventReader::next_event`] returns [`None`] after
+/// the value and the bytes after it are left in the reader. As it does not keep the keys of
+/// dictionaries, it does not check whether they are sorted or unique.
+///
+/// Malformed values are reported as [`io::Error`]s of [`io::ErrorKind::InvalidData`] kind,
+/// whose inner errors are [`DecodeError`]s.
+///
+/// # Examples
+///
+/// ```
+/// use bencodex::codec::stream::{ Event, EventReader };
+///
+/// let mut reader = EventReader::new(&b"du3:keyli1eee"[..]);
+/// let events = reader.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+/// assert_eq!(
+///     events,
+///     vec![
+///         Event::DictionaryStart,
+///         Event::Text("key".to_string()),
+///         Event::ListStart,
+///         Event::Number(1.into()),
+///         Event::End,
+///         Event::End,
+///     ]
+/// );
+/// assert_eq!(reader.position(), 13);
+/// ```
+pub struct EventReader<R> {
+    reader: R,
+    stack: Vec<Frame>,
+    position: usize,
+    done: bool,
+}
+
+fn invalid(error: DecodeError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+impl<R: BufRead> EventReader<R> {
+    pub fn new(reader: R) -> EventReader<R> {
+        EventReader {
+            reader,
+            stack: vec![],
+            position: 0,
+            done: false,
+        }
+    }
+
+    /// Returns the number of bytes read so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns the number of containers the next event is in.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Reads the next event, or returns [`None`] if the whole value was read.
+    pub fn next_event(&mut self) -> io::Result<Option<Event>> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let token = self
+            .peek()?
+            .ok_or_else(|| invalid(DecodeError::InvalidBencodexValueError))?;
+        if token == b'e' {
+            if let Some(frame) = self.stack.last() {
+                if let Frame::Dictionary { expects_key: false } = frame {
+                    return Err(self.unexpected(token));
+                }
+                self.bump();
+                self.stack.pop();
+                self.done = self.stack.is_empty();
+                return Ok(Some(Event::End));
+            }
+        }
+
+        if let Some(Frame::Dictionary { expects_key }) = self.stack.last_mut() {
+            if *expects_key && token != b'u' && !token.is_ascii_digit() {
+                return Err(self.unexpected(token));
+            }
+            *expects_key = !*expects_key;
+        }
+
+        let event = match token {
+            b'n' => {
+                self.bump();
+                Event::Null
+            }
+            b't' | b'f' => {
+                self.bump();
+                Event::Boolean(token == b't')
+            }
+            b'i' => {
+                self.bump();
+                let number = self.read_number()?;
+                self.expect(b'e')?;
+                Event::Number(number)
+            }
+            b'0'..=b'9' => Event::Binary(self.read_binary()?),
+            b'u' => {
+                self.bump();
+                let text = String::from_utf8(self.read_binary()?)
+                    .map_err(|_| invalid(DecodeError::InvalidBencodexValueError))?;
+                Event::Text(text)
+            }
+            b'l' => {
+                self.bump();
+                self.stack.push(Frame::List);
+                Event::ListStart
+            }
+            b'd' => {
+                self.bump();
+                self.stack.push(Frame::Dictionary { expects_key: true });
+                Event::DictionaryStart
+            }
+            _ => return Err(self.unexpected(token)),
+        };
+
+        self.done = self.stack.is_empty();
+        Ok(Some(event))
+    }
+
+    fn peek(&mut self) -> io::Result<Option<u8>> {
+        Ok(self.reader.fill_buf()?.first().copied())
+    }
+
+    fn bump(&mut self) {
+        self.reader.consume(1);
+        self.position += 1;
+    }
+
+    fn unexpected(&self, token: u8) -> io::Error {
+        invalid(DecodeError::UnexpectedTokenError {
+            token,
+            point: self.position,
+        })
+    }
+
+    fn expect(&mut self, expected: u8) -> io::Result<()> {
+        match self.peek()? {
+            Some(token) if token == expected => {
+                self.bump();
+                Ok(())
+            }
+            Some(token) => Err(self.unexpected(token)),
+            None => Err(invalid(DecodeError::InvalidBencodexValueError)),
+        }
+    }
+
+    fn read_number(&mut self) -> io::Result<BigInt> {
+        let mut digits = String::new();
+        if self.peek()? == Some(b'-') {
+            self.bump();
+            digits.push('-');
+        }
+        while let Some(token @ b'0'..=b'9') = self.peek()? {
+            self.bump();
+            digits.push(token as char);
+        }
+
+        BigInt::from_str(&digits).map_err(|_| match self.peek() {
+            Ok(Some(token)) => self.unexpected(token),
+            _ => invalid(DecodeError::InvalidBencodexValueError),
+        })
+    }
+
+    fn read_binary(&mut self) -> io::Result<Vec<u8>> {
+        let length = self.read_number()?;
+        let length = u64::try_from(&length).map_err(|_| {
+            invalid(DecodeError::UnexpectedTokenError {
+                token: b'-',
+                point: self.position,
+            })
+        })?;
+        self.expect(b':')?;
+
+        let mut buf = vec![];
+        let read = (&mut self.reader).take(length).read_to_end(&mut buf)?;
+        self.position += read;
+        if (read as u64) < length {
+            return Err(invalid(DecodeError::InvalidBencodexValueError));
+        }
+
+        Ok(buf)
+    }
+}
+
+impl<R: BufRead> Iterator for EventReader<R> {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_event() {
+            Ok(event) => event.map(Ok),
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod event_reader {
+        use super::super::*;
+
+        fn read_all(bytes: &[u8]) -> io::Result<Vec<Event>> {
+            EventReader::new(bytes).collect()
+        }
+
+        fn decode_error(bytes: &[u8]) -> DecodeError {
+            let err = read_all(bytes).unwrap_err();
+            assert_eq!(io::ErrorKind::InvalidData, err.kind());
+            *err.into_inner().unwrap().downcast::<DecodeError>().unwrap()
+        }
+
+        #[test]
+        fn should_read_scalars() {
+            assert_eq!(vec![Event::Null], read_all(b"n").unwrap());
+            assert_eq!(vec![Event::Boolean(true)], read_all(b"t").unwrap());
+            assert_eq!(vec![Event::Boolean(false)], read_all(b"f").unwrap());
+            assert_eq!(
+                vec![Event::Number((-123).into())],
+                read_all(b"i-123e").unwrap()
+            );
+            assert_eq!(vec![Event::Binary(vec![])], read_all(b"0:").unwrap());
+            assert_eq!(
+                vec![Event::Binary(b"abc".to_vec())],
+                read_all(b"3:abc").unwrap()
+            );
+            assert_eq!(
+                vec![Event::Text("\u{AC00}".to_string())],
+                read_all("u3:\u{AC00}".as_bytes()).unwrap()
+            );
+        }
+
+        #[test]
+        fn should_read_nested_containers() {
+            assert_eq!(
+                vec![
+                    Event::ListStart,
+                    Event::DictionaryStart,
+                    Event::Binary(b"a".to_vec()),
+                    Event::ListStart,
+                    Event::End,
+                    Event::End,
+                    Event::Null,
+                    Event::End,
+                ],
+                read_all(b"ld1:aleene").unwrap()
+            );
+        }
+
+        #[test]
+        fn should_stop_after_value() {
+            let mut reader = EventReader::new(&b"i1eextra"[..]);
+            assert_eq!(Some(Event::Number(1.into())), reader.next_event().unwrap());
+            assert_eq!(None, reader.next_event().unwrap());
+            assert_eq!(3, reader.position());
+            assert_eq!(b"extra", reader.into_inner());
+        }
+
+        #[test]
+        fn should_track_depth() {
+            let mut reader = EventReader::new(&b"lle1:ae"[..]);
+            let mut depths = vec![];
+            while reader.next_event().unwrap().is_some() {
+                depths.push(reader.depth());
+            }
+            assert_eq!(vec![1, 2, 1, 1, 0], depths);
+        }
+
+        #[test]
+        fn should_return_error_with_invalid_bytes() {
+            assert_eq!(DecodeError::InvalidBencodexValueError, decode_error(b""));
+            assert_eq!(DecodeError::InvalidBencodexValueError, decode_error(b"l"));
+            assert_eq!(
+                DecodeError::InvalidBencodexValueError,
+                decode_error(b"5:abc")
+            );
+            assert_eq!(
+                DecodeError::InvalidBencodexValueError,
+                decode_error(b"u2:\xff\xff")
+            );
+            assert_eq!(
+                DecodeError::UnexpectedTokenError {
+                    token: b'x',
+                    point: 0
+                },
+                decode_error(b"x")
+            );
+            assert_eq!(
+                DecodeError::UnexpectedTokenError {
+                    token: b':',
+                    point: 1
+                },
+                decode_error(b"i:e")
+            );
+            assert_eq!(
+                DecodeError::UnexpectedTokenError {
+                    token: b'n',
+                    point: 1
+                },
+                decode_error(b"dnne")
+            );
+            assert_eq!(
+                DecodeError::UnexpectedTokenError {
+                    token: b'e',
+                    point: 4
+                },
+                decode_error(b"d1:ae")
+            );
+        }
+
+        #[test]
+        fn should_stop_after_error() {
+            let mut reader = EventReader::new(&b"lx"[..]);
+            assert!(matches!(reader.next(), Some(Ok(Event::ListStart))));
+            assert!(matches!(reader.next(), Some(Err(_))));
+            assert!(reader.next().is_none());
+        }
+    }
+}
@@ -1,6 +1,10 @@
+use itertools::{EitherOrBoth, Itertools};
 use num_bigint::BigInt;
+use std::cmp::Ordering;
 use std::{collections::BTreeMap, fmt::Debug};
 
+use super::decode::DecodeError;
+
 /// The type alias of `BTreepMap<BencodexKey, BencodexValue>` to reduce code size.
 ///
 /// ```
@@ -40,7 +44,28 @@ pub type BencodexList = Vec<BencodexValue>;
 /// ```
 pub const BENCODEX_NULL: BencodexValue = BencodexValue::Null;
 
-#[derive(PartialEq, Debug, Clone)]
+/// A Bencodex value.
+///
+/// Values are totally ordered in the same way as their canonical encodings are ordered
+/// when compared byte by byte, so sorting values sorts their encodings too.
+///
+/// ```
+/// use bencodex::{ BencodexValue, Encode };
+///
+/// let mut values: Vec<BencodexValue> = vec![10.into(), 9.into(), "a".into(), BencodexValue::Null];
+/// values.sort();
+///
+/// let encoded: Vec<Vec<u8>> = values
+///     .into_iter()
+///     .map(|value| {
+///         let mut buf = vec![];
+///         value.encode(&mut buf).unwrap();
+///         buf
+///     })
+///     .collect();
+/// assert_eq!(encoded, vec![b"i10e".to_vec(), b"i9e".to_vec(), b"n".to_vec(), b"u1:a".to_vec()]);
+/// ```
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
 pub enum BencodexValue {
     Binary(Vec<u8>),
     Text(String),
@@ -51,12 +76,168 @@ pub enum BencodexValue {
     Null,
 }
 
-#[derive(PartialEq, Eq, Debug, PartialOrd, Clone, Ord)]
+/// A key of [`BencodexDictionary`].
+///
+/// Its derived ordering, binary keys before text keys and then byte by byte, is the key
+/// ordering of the canonical encoding.
+#[derive(PartialEq, Eq, Hash, Debug, PartialOrd, Clone, Ord)]
 pub enum BencodexKey {
     Binary(Vec<u8>),
     Text(String),
 }
 
+/// The kind of a [`BencodexValue`], numbered as `ValueKind` of Bencodex.Net.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ValueKind {
+    Null = 0,
+    Boolean = 1,
+    Integer = 2,
+    Binary = 3,
+    Text = 4,
+    List = 5,
+    Dictionary = 6,
+}
+
+impl TryFrom<u8> for ValueKind {
+    type Error = DecodeError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => ValueKind::Null,
+            1 => ValueKind::Boolean,
+            2 => ValueKind::Integer,
+            3 => ValueKind::Binary,
+            4 => ValueKind::Text,
+            5 => ValueKind::List,
+            6 => ValueKind::Dictionary,
+            _ => {
+                return Err(DecodeError::UnexpectedTokenError {
+                    token: value,
+                    point: 0,
+                })
+            }
+        })
+    }
+}
+
+/// Compares `x_len:x` and `y_len:y` byte by byte, which is how binaries and texts
+/// are encoded after their type prefix.
+fn compare_length_prefixed(x: &[u8], y: &[u8]) -> Ordering {
+    let x_prefix = format!("{}:", x.len());
+    let y_prefix = format!("{}:", y.len());
+    x_prefix
+        .as_bytes()
+        .iter()
+        .chain(x)
+        .cmp(y_prefix.as_bytes().iter().chain(y))
+}
+
+fn compare_key_encoding(x: &BencodexKey, y: &BencodexKey) -> Ordering {
+    match (x, y) {
+        (BencodexKey::Binary(x), BencodexKey::Binary(y)) => compare_length_prefixed(x, y),
+        (BencodexKey::Text(x), BencodexKey::Text(y)) => {
+            compare_length_prefixed(x.as_bytes(), y.as_bytes())
+        }
+        (BencodexKey::Binary(_), BencodexKey::Text(_)) => Ordering::Less,
+        (BencodexKey::Text(_), BencodexKey::Binary(_)) => Ordering::Greater,
+    }
+}
+
+fn key_leading_byte(key: &BencodexKey) -> u8 {
+    match key {
+        BencodexKey::Binary(x) => x.len().to_string().as_bytes()[0],
+        BencodexKey::Text(_) => b'u',
+    }
+}
+
+impl BencodexValue {
+    /// Returns the kind of this value.
+    pub fn kind(&self) -> ValueKind {
+        match self {
+            BencodexValue::Null => ValueKind::Null,
+            BencodexValue::Boolean(_) => ValueKind::Boolean,
+            BencodexValue::Number(_) => ValueKind::Integer,
+            BencodexValue::Binary(_) => ValueKind::Binary,
+            BencodexValue::Text(_) => ValueKind::Text,
+            BencodexValue::List(_) => ValueKind::List,
+            BencodexValue::Dictionary(_) => ValueKind::Dictionary,
+        }
+    }
+
+    /// Returns the first byte of the canonical encoding of this value.
+    fn leading_byte(&self) -> u8 {
+        match self {
+            BencodexValue::Binary(x) => x.len().to_string().as_bytes()[0],
+            BencodexValue::Text(_) => b'u',
+            BencodexValue::Boolean(true) => b't',
+            BencodexValue::Boolean(false) => b'f',
+            BencodexValue::Number(_) => b'i',
+            BencodexValue::List(_) => b'l',
+            BencodexValue::Dictionary(_) => b'd',
+            BencodexValue::Null => b'n',
+        }
+    }
+}
+
+impl Ord for BencodexValue {
+    /// Compares two values as their canonical encodings would be compared byte by byte,
+    /// without encoding them.
+    ///
+    /// Since an encoding is never a prefix of another one, two lists or dictionaries are
+    /// ordered by their first differing element or entry. When one runs out of elements,
+    /// its closing `e` is compared with the first byte of the other's next element.
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (BencodexValue::Binary(x), BencodexValue::Binary(y)) => compare_length_prefixed(x, y),
+            (BencodexValue::Text(x), BencodexValue::Text(y)) => {
+                compare_length_prefixed(x.as_bytes(), y.as_bytes())
+            }
+            (BencodexValue::Number(x), BencodexValue::Number(y)) => x
+                .to_str_radix(10)
+                .bytes()
+                .chain([b'e'])
+                .cmp(y.to_str_radix(10).bytes().chain([b'e'])),
+            (BencodexValue::List(xs), BencodexValue::List(ys)) => {
+                for pair in xs.iter().zip_longest(ys) {
+                    let ordering = match pair {
+                        EitherOrBoth::Both(x, y) => x.cmp(y),
+                        EitherOrBoth::Left(x) => x.leading_byte().cmp(&b'e'),
+                        EitherOrBoth::Right(y) => b'e'.cmp(&y.leading_byte()),
+                    };
+                    if ordering != Ordering::Equal {
+                        return ordering;
+                    }
+                }
+
+                Ordering::Equal
+            }
+            (BencodexValue::Dictionary(xs), BencodexValue::Dictionary(ys)) => {
+                for pair in xs.iter().zip_longest(ys) {
+                    let ordering = match pair {
+                        EitherOrBoth::Both((xk, xv), (yk, yv)) => {
+                            compare_key_encoding(xk, yk).then_with(|| xv.cmp(yv))
+                        }
+                        EitherOrBoth::Left((x, _)) => key_leading_byte(x).cmp(&b'e'),
+                        EitherOrBoth::Right((y, _)) => b'e'.cmp(&key_leading_byte(y)),
+                    };
+                    if ordering != Ordering::Equal {
+                        return ordering;
+                    }
+                }
+
+                Ordering::Equal
+            }
+            _ => self.leading_byte().cmp(&other.leading_byte()),
+        }
+    }
+}
+
+impl PartialOrd for BencodexValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl From<&str> for BencodexKey {
     fn from(val: &str) -> Self {
         BencodexKey::Text(val.to_string())
@@ -172,6 +353,91 @@ where
 
 #[cfg(test)]
 mod tests {
+    mod ord {
+        use std::collections::{BTreeMap, HashSet};
+
+        use super::super::{BencodexKey, BencodexValue};
+        use crate::Encode;
+
+        fn encode(value: &BencodexValue) -> Vec<u8> {
+            let mut buf = vec![];
+            value.clone().encode(&mut buf).unwrap();
+            buf
+        }
+
+        fn values() -> Vec<BencodexValue> {
+            let mut dict_a = BTreeMap::new();
+            dict_a.insert(BencodexKey::from("a"), BencodexValue::from(1));
+            let mut dict_b = dict_a.clone();
+            dict_b.insert(BencodexKey::from(vec![0u8; 10]), BencodexValue::Null);
+            let mut dict_c = BTreeMap::new();
+            dict_c.insert(BencodexKey::from(vec![0u8]), BencodexValue::Null);
+            let mut dict_d = BTreeMap::new();
+            dict_d.insert(BencodexKey::from("a"), BencodexValue::from(2));
+
+            vec![
+                BencodexValue::Null,
+                true.into(),
+                false.into(),
+                0.into(),
+                (-1).into(),
+                9.into(),
+                10.into(),
+                (-10).into(),
+                vec![0u8; 0].into(),
+                vec![0u8; 3].into(),
+                vec![0u8; 10].into(),
+                vec![1u8; 10].into(),
+                "".into(),
+                "a".into(),
+                "b".into(),
+                "ab".into(),
+                "0123456789".into(),
+                BencodexValue::List(vec![]),
+                vec![1].into(),
+                vec![1, 2].into(),
+                vec![BencodexValue::from(1), "a".into()].into(),
+                vec![BencodexValue::from(1), vec![0u8; 12].into()].into(),
+                vec![
+                    BencodexValue::from(1),
+                    BencodexValue::Dictionary(BTreeMap::new()),
+                ]
+                .into(),
+                BencodexValue::Dictionary(BTreeMap::new()),
+                BencodexValue::Dictionary(dict_a),
+                BencodexValue::Dictionary(dict_b),
+                BencodexValue::Dictionary(dict_c),
+                BencodexValue::Dictionary(dict_d),
+            ]
+        }
+
+        #[test]
+        fn should_be_consistent_with_encoding() {
+            let values = values();
+            for x in &values {
+                for y in &values {
+                    assert_eq!(
+                        encode(x).cmp(&encode(y)),
+                        x.cmp(y),
+                        "comparing {} with {}",
+                        x,
+                        y
+                    );
+                    assert_eq!(x == y, x.cmp(y).is_eq());
+                }
+            }
+        }
+
+        #[test]
+        fn should_be_hashable() {
+            let mut set = HashSet::new();
+            for value in values().into_iter().chain(values()) {
+                set.insert(value);
+            }
+            assert_eq!(values().len(), set.len());
+        }
+    }
+
     mod into {
         use std::array::IntoIter;
         use std::{collections::BTreeMap, iter::FromIterator};
@@ -0,0 +1,509 @@
+//! Conversions between Rust types and Bencodex values.
+//!
+//! [`ToBencodex`] and [`FromBencodex`] are implemented for the Rust types corresponding to
+//! Bencodex values: `()` for null, [`bool`], integer types except [`u8`], [`BigInt`],
+//! [`String`] for texts, `Vec<u8>` for binaries, `Vec<T>` for lists, and
+//! `BTreeMap<BencodexKey, T>` for dictionaries. [`u8`] is left out so that `Vec<u8>` is a
+//! binary, not a list of integers.
+//!
+//! They are also implemented by the code generated from schemas, with the helper functions
+//! of this module.
+//!
+//! ```
+//! use std::collections::BTreeMap;
+//! use bencodex::{ BencodexKey, BencodexValue, FromBencodex, ToBencodex };
+//!
+//! let value = BTreeMap::from([(BencodexKey::from("ids"), vec![1i64, 2])]).to_bencodex();
+//! assert_eq!(
+//!     BTreeMap::<BencodexKey, Vec<i64>>::from_bencodex(&value),
+//!     Ok(BTreeMap::from([(BencodexKey::from("ids"), vec![1, 2])]))
+//! );
+//! assert!(Vec::<u8>::from_bencodex(&value).is_err());
+//! ```
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+use num_bigint::BigInt;
+
+use crate::{
+    BencodexDictionary, BencodexKey, BencodexList, BencodexPath, BencodexValue, PathSegment,
+    ValueKind,
+};
+
+/// A type which can be converted into a Bencodex value.
+pub trait ToBencodex {
+    fn to_bencodex(&self) -> BencodexValue;
+}
+
+/// A type which can be converted from a Bencodex value.
+pub trait FromBencodex: Sized {
+    fn from_bencodex(value: &BencodexValue) -> Result<Self, FromBencodexError>;
+}
+
+/// The error type which is returned from [`FromBencodex::from_bencodex`]. The paths are
+/// relative to the value given to it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum FromBencodexError {
+    /// This should be used when the value at the given path is of another kind than the
+    /// type expects.
+    ///
+    /// ```
+    /// use bencodex::{ BencodexPath, BencodexValue, FromBencodex, FromBencodexError, ValueKind };
+    ///
+    /// let value = BencodexValue::from(vec![BencodexValue::from(1), "a".into()]);
+    /// assert_eq!(
+    ///     Vec::<i64>::from_bencodex(&value),
+    ///     Err(FromBencodexError::UnexpectedKindError {
+    ///         path: BencodexPath::root().index(1),
+    ///         expected: ValueKind::Integer,
+    ///         actual: ValueKind::Text,
+    ///     })
+    /// );
+    /// ```
+    UnexpectedKindError {
+        path: BencodexPath,
+        expected: ValueKind,
+        actual: ValueKind,
+    },
+    /// This should be used when the integer at the given path does not fit in the type.
+    IntegerOutOfRangeError { path: BencodexPath },
+    /// This should be used when the list at the given path has another number of items than
+    /// the type expects.
+    TupleLengthMismatchError {
+        path: BencodexPath,
+        expected: usize,
+        actual: usize,
+    },
+    /// This should be used when the dictionary lacks the key at the given path.
+    MissingKeyError { path: BencodexPath },
+    /// This should be used when the dictionary has the key at the given path, which the type
+    /// does not expect.
+    UnexpectedKeyError { path: BencodexPath },
+    /// This should be used when the value at the given path cannot be converted into any of
+    /// the variants of an enum.
+    NoMatchingVariantError { path: BencodexPath },
+}
+
+impl FromBencodexError {
+    pub fn path(&self) -> &BencodexPath {
+        match self {
+            FromBencodexError::UnexpectedKindError { path, .. }
+            | FromBencodexError::IntegerOutOfRangeError { path }
+            | FromBencodexError::TupleLengthMismatchError { path, .. }
+            | FromBencodexError::MissingKeyError { path }
+            | FromBencodexError::UnexpectedKeyError { path }
+            | FromBencodexError::NoMatchingVariantError { path } => path,
+        }
+    }
+
+    /// Returns the error with its path prefixed by the given segment, i.e., the error found
+    /// at the subvalue of the segment.
+    pub fn under(mut self, segment: PathSegment) -> FromBencodexError {
+        let path = match &mut self {
+            FromBencodexError::UnexpectedKindError { path, .. }
+            | FromBencodexError::IntegerOutOfRangeError { path }
+            | FromBencodexError::TupleLengthMismatchError { path, .. }
+            | FromBencodexError::MissingKeyError { path }
+            | FromBencodexError::UnexpectedKeyError { path }
+            | FromBencodexError::NoMatchingVariantError { path } => path,
+        };
+        *path = std::iter::once(segment)
+            .chain(path.segments().iter().cloned())
+            .collect();
+        self
+    }
+}
+
+impl fmt::Display for FromBencodexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Error for FromBencodexError {}
+
+fn unexpected_kind(value: &BencodexValue, expected: ValueKind) -> FromBencodexError {
+    FromBencodexError::UnexpectedKindError {
+        path: BencodexPath::root(),
+        expected,
+        actual: value.kind(),
+    }
+}
+
+/// Returns the list the value is, or an error if it is not a list.
+pub fn as_list(value: &BencodexValue) -> Result<&BencodexList, FromBencodexError> {
+    match value {
+        BencodexValue::List(list) => Ok(list),
+        _ => Err(unexpected_kind(value, ValueKind::List)),
+    }
+}
+
+/// Returns the list the value is, or an error if it is not a list of exactly the given
+/// number of items.
+pub fn as_tuple(value: &BencodexValue, length: usize) -> Result<&BencodexList, FromBencodexError> {
+    let list = as_list(value)?;
+    if list.len() != length {
+        return Err(FromBencodexError::TupleLengthMismatchError {
+            path: BencodexPath::root(),
+            expected: length,
+            actual: list.len(),
+        });
+    }
+
+    Ok(list)
+}
+
+/// Returns the dictionary the value is, or an error if it is not a dictionary.
+pub fn as_dictionary(value: &BencodexValue) -> Result<&BencodexDictionary, FromBencodexError> {
+    match value {
+        BencodexValue::Dictionary(dict) => Ok(dict),
+        _ => Err(unexpected_kind(value, ValueKind::Dictionary)),
+    }
+}
+
+/// Converts the item at the index of the list, which has to exist.
+pub fn item<T: FromBencodex>(list: &BencodexList, index: usize) -> Result<T, FromBencodexError> {
+    T::from_bencodex(&list[index]).map_err(|e| e.under(PathSegment::Index(index)))
+}
+
+/// Converts the value of the key of the dictionary, or returns an error if there is no such
+/// key.
+pub fn required<T: FromBencodex>(
+    dict: &BencodexDictionary,
+    key: &BencodexKey,
+) -> Result<T, FromBencodexError> {
+    match optional(dict, key)? {
+        Some(value) => Ok(value),
+        None => Err(FromBencodexError::MissingKeyError {
+            path: BencodexPath::root().key(key.clone()),
+        }),
+    }
+}
+
+/// Converts the value of the key of the dictionary, if any.
+pub fn optional<T: FromBencodex>(
+    dict: &BencodexDictionary,
+    key: &BencodexKey,
+) -> Result<Option<T>, FromBencodexError> {
+    dict.get(key)
+        .map(|value| T::from_bencodex(value).map_err(|e| e.under(key.clone().into())))
+        .transpose()
+}
+
+/// Converts the values of the keys of the dictionary other than the given keys.
+pub fn additional<T: FromBencodex>(
+    dict: &BencodexDictionary,
+    keys: &[BencodexKey],
+) -> Result<BTreeMap<BencodexKey, T>, FromBencodexError> {
+    dict.iter()
+        .filter(|(key, _)| !keys.contains(key))
+        .map(|(key, value)| {
+            T::from_bencodex(value)
+                .map(|value| (key.clone(), value))
+                .map_err(|e| e.under(key.clone().into()))
+        })
+        .collect()
+}
+
+/// Returns an error if the dictionary has any key other than the given keys.
+pub fn reject_unexpected_keys(
+    dict: &BencodexDictionary,
+    keys: &[BencodexKey],
+) -> Result<(), FromBencodexError> {
+    match dict.keys().find(|key| !keys.contains(key)) {
+        Some(key) => Err(FromBencodexError::UnexpectedKeyError {
+            path: BencodexPath::root().key(key.clone()),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Returns the error of a value which none of the variants of an enum is converted from,
+/// given the errors of the variants.
+///
+/// If all the variants but one failed because the value is of another kind, the error of
+/// the remaining variant is returned as it tells what is wrong in detail. Otherwise,
+/// [`FromBencodexError::NoMatchingVariantError`] is returned.
+pub fn no_matching_variant(errors: Vec<FromBencodexError>) -> FromBencodexError {
+    let mut compatible = errors.into_iter().filter(
+        |e| !matches!(e, FromBencodexError::UnexpectedKindError { path, .. } if path.is_empty()),
+    );
+    match (compatible.next(), compatible.next()) {
+        (Some(error), None) => error,
+        _ => FromBencodexError::NoMatchingVariantError {
+            path: BencodexPath::root(),
+        },
+    }
+}
+
+impl ToBencodex for BencodexValue {
+    fn to_bencodex(&self) -> BencodexValue {
+        self.clone()
+    }
+}
+
+impl FromBencodex for BencodexValue {
+    fn from_bencodex(value: &BencodexValue) -> Result<Self, FromBencodexError> {
+        Ok(value.clone())
+    }
+}
+
+impl ToBencodex for () {
+    fn to_bencodex(&self) -> BencodexValue {
+        BencodexValue::Null
+    }
+}
+
+impl FromBencodex for () {
+    fn from_bencodex(value: &BencodexValue) -> Result<Self, FromBencodexError> {
+        match value {
+            BencodexValue::Null => Ok(()),
+            _ => Err(unexpected_kind(value, ValueKind::Null)),
+        }
+    }
+}
+
+impl ToBencodex for bool {
+    fn to_bencodex(&self) -> BencodexValue {
+        BencodexValue::Boolean(*self)
+    }
+}
+
+impl FromBencodex for bool {
+    fn from_bencodex(value: &BencodexValue) -> Result<Self, FromBencodexError> {
+        match value {
+            BencodexValue::Boolean(b) => Ok(*b),
+            _ => Err(unexpected_kind(value, ValueKind::Boolean)),
+        }
+    }
+}
+
+impl ToBencodex for BigInt {
+    fn to_bencodex(&self) -> BencodexValue {
+        BencodexValue::Number(self.clone())
+    }
+}
+
+impl FromBencodex for BigInt {
+    fn from_bencodex(value: &BencodexValue) -> Result<Self, FromBencodexError> {
+        match value {
+            BencodexValue::Number(n) => Ok(n.clone()),
+            _ => Err(unexpected_kind(value, ValueKind::Integer)),
+        }
+    }
+}
+
+macro_rules! bencodex_integer_convert_impl {
+    ($x:tt) => {
+        impl ToBencodex for $x {
+            fn to_bencodex(&self) -> BencodexValue {
+                BencodexValue::Number((*self).into())
+            }
+        }
+
+        impl FromBencodex for $x {
+            fn from_bencodex(value: &BencodexValue) -> Result<Self, FromBencodexError> {
+                match value {
+                    BencodexValue::Number(n) => {
+                        $x::try_from(n).map_err(|_| FromBencodexError::IntegerOutOfRangeError {
+                            path: BencodexPath::root(),
+                        })
+                    }
+                    _ => Err(unexpected_kind(value, ValueKind::Integer)),
+                }
+            }
+        }
+    };
+}
+
+bencodex_integer_convert_impl!(u16);
+bencodex_integer_convert_impl!(u32);
+bencodex_integer_convert_impl!(u64);
+bencodex_integer_convert_impl!(u128);
+bencodex_integer_convert_impl!(i8);
+bencodex_integer_convert_impl!(i16);
+bencodex_integer_convert_impl!(i32);
+bencodex_integer_convert_impl!(i64);
+bencodex_integer_convert_impl!(i128);
+
+impl ToBencodex for String {
+    fn to_bencodex(&self) -> BencodexValue {
+        BencodexValue::Text(self.clone())
+    }
+}
+
+impl FromBencodex for String {
+    fn from_bencodex(value: &BencodexValue) -> Result<Self, FromBencodexError> {
+        match value {
+            BencodexValue::Text(t) => Ok(t.clone()),
+            _ => Err(unexpected_kind(value, ValueKind::Text)),
+        }
+    }
+}
+
+impl ToBencodex for Vec<u8> {
+    fn to_bencodex(&self) -> BencodexValue {
+        BencodexValue::Binary(self.clone())
+    }
+}
+
+impl FromBencodex for Vec<u8> {
+    fn from_bencodex(value: &BencodexValue) -> Result<Self, FromBencodexError> {
+        match value {
+            BencodexValue::Binary(b) => Ok(b.clone()),
+            _ => Err(unexpected_kind(value, ValueKind::Binary)),
+        }
+    }
+}
+
+impl<T: ToBencodex> ToBencodex for Vec<T> {
+    fn to_bencodex(&self) -> BencodexValue {
+        BencodexValue::List(self.iter().map(ToBencodex::to_bencodex).collect())
+    }
+}
+
+impl<T: FromBencodex> FromBencodex for Vec<T> {
+    fn from_bencodex(value: &BencodexValue) -> Result<Self, FromBencodexError> {
+        let list = as_list(value)?;
+        (0..list.len()).map(|index| item(list, index)).collect()
+    }
+}
+
+impl<T: ToBencodex> ToBencodex for BTreeMap<BencodexKey, T> {
+    fn to_bencodex(&self) -> BencodexValue {
+        BencodexValue::Dictionary(
+            self.iter()
+                .map(|(key, value)| (key.clone(), value.to_bencodex()))
+                .collect(),
+        )
+    }
+}
+
+impl<T: FromBencodex> FromBencodex for BTreeMap<BencodexKey, T> {
+    fn from_bencodex(value: &BencodexValue) -> Result<Self, FromBencodexError> {
+        additional(as_dictionary(value)?, &[])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod from_bencodex {
+        use super::super::*;
+
+        #[test]
+        fn should_convert_integers_in_range() {
+            let value = BencodexValue::from(300);
+            assert_eq!(Ok(300), i64::from_bencodex(&value));
+            assert_eq!(Ok(300), u16::from_bencodex(&value));
+            assert_eq!(Ok(BigInt::from(300)), BigInt::from_bencodex(&value));
+            assert_eq!(
+                Err(FromBencodexError::IntegerOutOfRangeError {
+                    path: BencodexPath::root()
+                }),
+                i8::from_bencodex(&value)
+            );
+            assert_eq!(
+                Err(FromBencodexError::IntegerOutOfRangeError {
+                    path: BencodexPath::root()
+                }),
+                u32::from_bencodex(&BencodexValue::from(-1))
+            );
+        }
+
+        #[test]
+        fn should_distinguish_binaries_from_lists() {
+            let binary = BencodexValue::from(vec![1u8, 2]);
+            let list = BencodexValue::from(vec![1, 2]);
+            assert_eq!(binary, vec![1u8, 2].to_bencodex());
+            assert_eq!(list, vec![1i64, 2].to_bencodex());
+            assert_eq!(Ok(vec![1u8, 2]), Vec::<u8>::from_bencodex(&binary));
+            assert_eq!(Ok(vec![1u16, 2]), Vec::<u16>::from_bencodex(&list));
+            assert_eq!(
+                Err(FromBencodexError::UnexpectedKindError {
+                    path: BencodexPath::root(),
+                    expected: ValueKind::Binary,
+                    actual: ValueKind::List,
+                }),
+                Vec::<u8>::from_bencodex(&list)
+            );
+        }
+
+        #[test]
+        fn should_prefix_paths_of_nested_errors() {
+            let mut dict = BencodexDictionary::new();
+            dict.insert(vec![0u8].into(), vec![BencodexValue::Null].into());
+            let value = BencodexValue::Dictionary(dict);
+            assert_eq!(
+                Err(FromBencodexError::UnexpectedKindError {
+                    path: BencodexPath::root().key(vec![0u8]).index(0),
+                    expected: ValueKind::Text,
+                    actual: ValueKind::Null,
+                }),
+                BTreeMap::<BencodexKey, Vec<String>>::from_bencodex(&value)
+            );
+        }
+    }
+
+    mod helpers {
+        use super::super::*;
+
+        #[test]
+        fn should_tell_binary_and_text_keys_apart() {
+            let mut dict = BencodexDictionary::new();
+            dict.insert("a".into(), 1.into());
+            dict.insert(b"b".to_vec().into(), 2.into());
+
+            assert_eq!(Ok(1), required::<i64>(&dict, &"a".into()));
+            assert_eq!(Ok(None), optional::<i64>(&dict, &b"a".to_vec().into()));
+            assert_eq!(
+                Err(FromBencodexError::MissingKeyError {
+                    path: BencodexPath::root().key("b")
+                }),
+                required::<i64>(&dict, &"b".into())
+            );
+            assert_eq!(
+                Err(FromBencodexError::UnexpectedKeyError {
+                    path: BencodexPath::root().key(b"b".to_vec())
+                }),
+                reject_unexpected_keys(&dict, &["a".into(), "b".into()])
+            );
+            assert_eq!(
+                Ok(BTreeMap::from([(BencodexKey::from(b"b".to_vec()), 2)])),
+                additional::<i64>(&dict, &["a".into()])
+            );
+        }
+
+        #[test]
+        fn should_report_error_of_only_compatible_variant() {
+            let kind_error = FromBencodexError::UnexpectedKindError {
+                path: BencodexPath::root(),
+                expected: ValueKind::Text,
+                actual: ValueKind::List,
+            };
+            let length_error = FromBencodexError::TupleLengthMismatchError {
+                path: BencodexPath::root(),
+                expected: 2,
+                actual: 3,
+            };
+            let no_matching_variant_error = FromBencodexError::NoMatchingVariantError {
+                path: BencodexPath::root(),
+            };
+
+            assert_eq!(
+                length_error,
+                no_matching_variant(vec![kind_error.clone(), length_error.clone()])
+            );
+            assert_eq!(
+                no_matching_variant_error,
+                no_matching_variant(vec![kind_error.clone()])
+            );
+            assert_eq!(
+                no_matching_variant_error,
+                no_matching_variant(vec![length_error.clone(), length_error])
+            );
+            assert_eq!(no_matching_variant_error, no_matching_variant(vec![]));
+        }
+    }
+}
@@ -0,0 +1,468 @@
+//! Structural diffs between two Bencodex values.
+//!
+//! [`diff`] compares two values and returns the [`Change`]s which turn the first value into
+//! the second one, each at the [`BencodexPath`] where it is found. Dictionaries are compared
+//! key by key, where a binary key and a text key are different keys even if they are spelled
+//! the same, as [`BencodexKey`] does. Lists are compared index by index, or by their longest
+//! common subsequence with [`ListDiff::Lcs`].
+//!
+//! ```
+//! use bencodex::{ BencodexDictionary, BencodexValue };
+//! use bencodex::diff::{ diff, render };
+//! use bencodex::inspect::SummaryOptions;
+//!
+//! let mut a = BencodexDictionary::new();
+//! a.insert("balance".into(), 100.into());
+//! a.insert(b"nonce".to_vec().into(), 1.into());
+//! let mut b = a.clone();
+//! b.insert("balance".into(), 90.into());
+//! b.insert("nonce".into(), 2.into());
+//!
+//! let a = BencodexValue::Dictionary(a);
+//! let b = BencodexValue::Dictionary(b);
+//! assert_eq!(
+//!     render(&diff(&a, &b), SummaryOptions::default()),
+//!     "~ $[\"balance\"]: 100 -> 90\n+ $[\"nonce\"]: 2\n"
+//! );
+//! ```
+
+use std::fmt;
+
+use itertools::{EitherOrBoth, Itertools};
+
+use crate::inspect::SummaryOptions;
+use crate::{BencodexKey, BencodexPath, BencodexValue};
+
+/// A difference between two values found by [`diff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change<'a> {
+    /// The value at the path exists only in the second value.
+    Added {
+        path: BencodexPath,
+        value: &'a BencodexValue,
+    },
+    /// The value at the path exists only in the first value.
+    Removed {
+        path: BencodexPath,
+        value: &'a BencodexValue,
+    },
+    /// The value at the path is replaced by another value. Two lists or two dictionaries are
+    /// never replaced, but the changes inside them are found instead.
+    Replaced {
+        path: BencodexPath,
+        old: &'a BencodexValue,
+        new: &'a BencodexValue,
+    },
+}
+
+impl<'a> Change<'a> {
+    pub fn path(&self) -> &BencodexPath {
+        match self {
+            Change::Added { path, .. } | Change::Removed { path, .. } => path,
+            Change::Replaced { path, .. } => path,
+        }
+    }
+}
+
+impl<'a> fmt::Display for Change<'a> {
+    /// Formats the change on a single line, with the values in the Bencodex Inspect notation:
+    /// `+ path: value` for an added value, `- path: value` for a removed value, and
+    /// `~ path: old -> new` for a replaced value.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added { path, value } => write!(f, "+ {}: {}", path, value),
+            Change::Removed { path, value } => write!(f, "- {}: {}", path, value),
+            Change::Replaced { path, old, new } => write!(f, "~ {}: {} -> {}", path, old, new),
+        }
+    }
+}
+
+/// How [`diff_with_options`] compares two lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ListDiff {
+    /// Compares the items at the same index, and the extra items of the longer list are
+    /// added or removed. Inserting an item into a list changes all the items after it.
+    #[default]
+    ByIndex,
+    /// Finds the longest common subsequence of the lists, and the other items are added or
+    /// removed. The paths of removed items are indices in the first list, and the paths of
+    /// added items are indices in the second list. Items are never compared inside, and it
+    /// takes time and memory proportional to the product of the lengths of the lists, except
+    /// for their common prefix and suffix. If the product exceeds [`LCS_MAX_CELLS`], the
+    /// lists are compared [`ByIndex`](ListDiff::ByIndex) instead.
+    Lcs,
+}
+
+/// Options used by [`diff_with_options`].
+///
+/// # Examples
+///
+/// ```
+/// use bencodex::diff::{ DiffOptions, ListDiff };
+///
+/// DiffOptions {
+///   lists: ListDiff::Lcs,
+/// };
+/// ```
+///
+/// Or you can use [`DiffOptions::default`], which compares lists by index:
+///
+/// ```
+/// use bencodex::diff::DiffOptions;
+///
+/// DiffOptions::default();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DiffOptions {
+    pub lists: ListDiff,
+}
+
+fn diff_lists_by_index<'a>(
+    a: &'a [BencodexValue],
+    b: &'a [BencodexValue],
+    path: &mut BencodexPath,
+    options: DiffOptions,
+    changes: &mut Vec<Change<'a>>,
+) {
+    for (index, pair) in a.iter().zip_longest(b).enumerate() {
+        path.push(index.into());
+        match pair {
+            EitherOrBoth::Both(a, b) => diff_impl(a, b, path, options, changes),
+            EitherOrBoth::Left(value) => changes.push(Change::Removed {
+                path: path.clone(),
+                value,
+            }),
+            EitherOrBoth::Right(value) => changes.push(Change::Added {
+                path: path.clone(),
+                value,
+            }),
+        }
+        path.pop();
+    }
+}
+
+/// The largest table [`ListDiff::Lcs`] allocates, in cells, which is 32 MiB on 64-bit
+/// platforms. Lists needing a larger table are compared by index instead.
+pub const LCS_MAX_CELLS: usize = 1 << 22;
+
+fn diff_lists_by_lcs<'a>(
+    a: &'a [BencodexValue],
+    b: &'a [BencodexValue],
+    path: &mut BencodexPath,
+    options: DiffOptions,
+    changes: &mut Vec<Change<'a>>,
+) {
+    let prefix = a.iter().zip(b).take_while(|(a, b)| a == b).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let middle_a = &a[prefix..a.len() - suffix];
+    let middle_b = &b[prefix..b.len() - suffix];
+    let cells = (middle_a.len() + 1).checked_mul(middle_b.len() + 1);
+    if !matches!(cells, Some(cells) if cells <= LCS_MAX_CELLS) {
+        return diff_lists_by_index(a, b, path, options, changes);
+    }
+
+    // lengths[i][j] is the length of the LCS of middle_a[i..] and middle_b[j..].
+    let mut lengths = vec![vec![0usize; middle_b.len() + 1]; middle_a.len() + 1];
+    for i in (0..middle_a.len()).rev() {
+        for j in (0..middle_b.len()).rev() {
+            lengths[i][j] = if middle_a[i] == middle_b[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut push = |index: usize, value: &'a BencodexValue, added: bool| {
+        path.push((prefix + index).into());
+        changes.push(if added {
+            Change::Added {
+                path: path.clone(),
+                value,
+            }
+        } else {
+            Change::Removed {
+                path: path.clone(),
+                value,
+            }
+        });
+        path.pop();
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < middle_a.len() && j < middle_b.len() {
+        if middle_a[i] == middle_b[j] {
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            push(i, &middle_a[i], false);
+            i += 1;
+        } else {
+            push(j, &middle_b[j], true);
+            j += 1;
+        }
+    }
+    for (i, value) in middle_a.iter().enumerate().skip(i) {
+        push(i, value, false);
+    }
+    for (j, value) in middle_b.iter().enumerate().skip(j) {
+        push(j, value, true);
+    }
+}
+
+fn diff_impl<'a>(
+    a: &'a BencodexValue,
+    b: &'a BencodexValue,
+    path: &mut BencodexPath,
+    options: DiffOptions,
+    changes: &mut Vec<Change<'a>>,
+) {
+    match (a, b) {
+        _ if a == b => {}
+        (BencodexValue::List(a), BencodexValue::List(b)) => match options.lists {
+            ListDiff::ByIndex => diff_lists_by_index(a, b, path, options, changes),
+            ListDiff::Lcs => diff_lists_by_lcs(a, b, path, options, changes),
+        },
+        (BencodexValue::Dictionary(a), BencodexValue::Dictionary(b)) => {
+            let pairs = a
+                .iter()
+                .merge_join_by(b, |(a, _), (b, _)| BencodexKey::cmp(a, b));
+            for pair in pairs {
+                let key = match &pair {
+                    EitherOrBoth::Both((key, _), _)
+                    | EitherOrBoth::Left((key, _))
+                    | EitherOrBoth::Right((key, _)) => *key,
+                };
+                path.push(key.clone().into());
+                match pair {
+                    EitherOrBoth::Both((_, a), (_, b)) => diff_impl(a, b, path, options, changes),
+                    EitherOrBoth::Left((_, value)) => changes.push(Change::Removed {
+                        path: path.clone(),
+                        value,
+                    }),
+                    EitherOrBoth::Right((_, value)) => changes.push(Change::Added {
+                        path: path.clone(),
+                        value,
+                    }),
+                }
+                path.pop();
+            }
+        }
+        _ => changes.push(Change::Replaced {
+            path: path.clone(),
+            old: a,
+            new: b,
+        }),
+    }
+}
+
+/// Returns the changes which turn `a` into `b`, comparing lists index by index. See
+/// [`diff_with_options`] for details.
+pub fn diff<'a>(a: &'a BencodexValue, b: &'a BencodexValue) -> Vec<Change<'a>> {
+    diff_with_options(a, b, DiffOptions::default())
+}
+
+/// Returns the changes which turn `a` into `b`, in the order of their paths in `a` and `b`.
+/// It is empty if and only if the values are equal.
+///
+/// Two lists or two dictionaries are compared inside, and the other values are replaced as a
+/// whole if they differ.
+///
+/// # Examples
+///
+/// ```
+/// use bencodex::{ BencodexPath, BencodexValue };
+/// use bencodex::diff::{ diff_with_options, Change, DiffOptions, ListDiff };
+///
+/// let a: BencodexValue = vec![1, 2, 3].into();
+/// let b: BencodexValue = vec![0, 1, 2, 3].into();
+///
+/// let by_index = diff_with_options(&a, &b, DiffOptions { lists: ListDiff::ByIndex });
+/// assert_eq!(by_index.len(), 4);
+///
+/// let by_lcs = diff_with_options(&a, &b, DiffOptions { lists: ListDiff::Lcs });
+/// assert_eq!(
+///     by_lcs,
+///     vec![Change::Added { path: BencodexPath::root().index(0), value: &0.into() }]
+/// );
+/// ```
+pub fn diff_with_options<'a>(
+    a: &'a BencodexValue,
+    b: &'a BencodexValue,
+    options: DiffOptions,
+) -> Vec<Change<'a>> {
+    let mut changes = Vec::new();
+    diff_impl(a, b, &mut BencodexPath::root(), options, &mut changes);
+    changes
+}
+
+/// Renders the changes for humans, a line per change as [`Change`] is formatted, but with
+/// the values summarized by [`BencodexValue::summary`] with the options.
+pub fn render(changes: &[Change<'_>], options: SummaryOptions) -> String {
+    changes
+        .iter()
+        .map(|change| match change {
+            Change::Added { path, value } => format!("+ {}: {}\n", path, value.summary(options)),
+            Change::Removed { path, value } => {
+                format!("- {}: {}\n", path, value.summary(options))
+            }
+            Change::Replaced { path, old, new } => format!(
+                "~ {}: {} -> {}\n",
+                path,
+                old.summary(options),
+                new.summary(options)
+            ),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    mod diff_with_options {
+        use super::super::*;
+        use crate::BencodexDictionary;
+
+        const LCS: DiffOptions = DiffOptions {
+            lists: ListDiff::Lcs,
+        };
+
+        #[test]
+        fn should_return_nothing_for_equal_values() {
+            let value: BencodexValue = vec![vec![1, 2], vec![3]].into();
+            assert_eq!(Vec::<Change>::new(), diff(&value, &value.clone()));
+            assert_eq!(
+                Vec::<Change>::new(),
+                diff_with_options(&value, &value.clone(), LCS)
+            );
+        }
+
+        #[test]
+        fn should_replace_values_of_different_kinds() {
+            let a: BencodexValue = vec![1].into();
+            let b: BencodexValue = "1".into();
+            assert_eq!(
+                vec![Change::Replaced {
+                    path: BencodexPath::root(),
+                    old: &a,
+                    new: &b,
+                }],
+                diff(&a, &b)
+            );
+        }
+
+        #[test]
+        fn should_diff_binary_and_text_keys_separately() {
+            let mut a = BencodexDictionary::new();
+            a.insert(b"key".to_vec().into(), 1.into());
+            a.insert("same".into(), vec![1, 2].into());
+            let mut b = BencodexDictionary::new();
+            b.insert("key".into(), 1.into());
+            b.insert("same".into(), vec![1, 3, 4].into());
+            let (a, b) = (BencodexValue::Dictionary(a), BencodexValue::Dictionary(b));
+
+            assert_eq!(
+                vec![
+                    Change::Removed {
+                        path: BencodexPath::root().key(b"key".to_vec()),
+                        value: &1.into(),
+                    },
+                    Change::Added {
+                        path: BencodexPath::root().key("key"),
+                        value: &1.into(),
+                    },
+                    Change::Replaced {
+                        path: BencodexPath::root().key("same").index(1),
+                        old: &2.into(),
+                        new: &3.into(),
+                    },
+                    Change::Added {
+                        path: BencodexPath::root().key("same").index(2),
+                        value: &4.into(),
+                    },
+                ],
+                diff(&a, &b)
+            );
+        }
+
+        #[test]
+        fn should_diff_lists_by_lcs() {
+            let a: BencodexValue = vec![1, 2, 3, 4, 5, 6].into();
+            let b: BencodexValue = vec![1, 3, 7, 4, 8, 6].into();
+            assert_eq!(
+                vec![
+                    Change::Removed {
+                        path: BencodexPath::root().index(1),
+                        value: &2.into(),
+                    },
+                    Change::Added {
+                        path: BencodexPath::root().index(2),
+                        value: &7.into(),
+                    },
+                    Change::Removed {
+                        path: BencodexPath::root().index(4),
+                        value: &5.into(),
+                    },
+                    Change::Added {
+                        path: BencodexPath::root().index(4),
+                        value: &8.into(),
+                    },
+                ],
+                diff_with_options(&a, &b, LCS)
+            );
+
+            let empty = BencodexValue::List(vec![]);
+            assert_eq!(6, diff_with_options(&empty, &b, LCS).len());
+        }
+
+        #[test]
+        fn should_fall_back_to_index_for_long_lists() {
+            // The middles of 2048 items need a table of 2049 * 2049 cells, more than the limit.
+            let a: BencodexValue = (0..2048).collect::<Vec<_>>().into();
+            let b: BencodexValue = (1..2049).collect::<Vec<_>>().into();
+            let changes = diff_with_options(&a, &b, LCS);
+            assert_eq!(diff(&a, &b), changes);
+            assert_eq!(2048, changes.len());
+
+            // With 2048 * 2048 cells, the same shift is found by LCS.
+            let a: BencodexValue = (0..2047).collect::<Vec<_>>().into();
+            let b: BencodexValue = (1..2048).collect::<Vec<_>>().into();
+            assert_eq!(2, diff_with_options(&a, &b, LCS).len());
+        }
+    }
+
+    mod render {
+        use super::super::*;
+
+        #[test]
+        fn should_render_changes_with_summaries() {
+            let old: BencodexValue = vec![0u8; 32].into();
+            let new: BencodexValue = vec![1, 2].into();
+            let changes = vec![
+                Change::Replaced {
+                    path: BencodexPath::root().key("hash"),
+                    old: &old,
+                    new: &new,
+                },
+                Change::Removed {
+                    path: BencodexPath::root().index(0),
+                    value: &BencodexValue::Null,
+                },
+            ];
+            let options = SummaryOptions {
+                max_depth: 4,
+                max_items: 1,
+                max_bytes: 2,
+            };
+
+            assert_eq!(
+                "~ $[\"hash\"]: <32 bytes: 0000…> -> [1, … 1 more]\n- $[0]: null\n",
+                render(&changes, options)
+            );
+            assert_eq!("- $[0]: null", changes[1].to_string());
+        }
+    }
+}
@@ -0,0 +1,342 @@
+//! Fingerprints of Bencodex values, which identify values without comparing them whole.
+//!
+//! To use it, you should enable `fingerprint` feature.
+
+use std::convert::TryFrom;
+use std::io;
+use std::result::Result;
+
+use sha1::{Digest, Sha1};
+
+use crate::{BencodexValue, DecodeError, Encode, ValueKind};
+
+/// Binaries and texts up to this many bytes are inlined into their fingerprint's digest
+/// instead of being hashed. It is the size of a SHA-1 digest.
+const INLINE_DIGEST_LIMIT: usize = 20;
+
+/// The size of a serialized fingerprint without its digest: one byte for the kind and eight
+/// bytes for the encoding length.
+const SERIALIZED_HEADER_SIZE: usize = 9;
+
+/// A fingerprint of a [`BencodexValue`], laid out after `Fingerprint` of Bencodex.Net.
+///
+/// The layout below follows Bencodex.Net's, and the SHA-1 digests are checked against the
+/// FIPS 180 test vectors, but fingerprints have not been checked against the output of
+/// Bencodex.Net itself, so do not rely on them to match it yet.
+///
+/// A fingerprint consists of the kind of the value, the length of its canonical encoding,
+/// and a digest:
+///
+/// - [`BencodexValue::Null`] has an empty digest.
+/// - [`BencodexValue::Boolean`] has a single byte, `1` for `true` and `0` for `false`.
+/// - [`BencodexValue::Number`] has the little-endian two's complement bytes of the number.
+/// - [`BencodexValue::Binary`] and [`BencodexValue::Text`] have their bytes (UTF-8 for texts)
+///   if they are 20 bytes or shorter, and the SHA-1 hash of their bytes otherwise.
+/// - [`BencodexValue::List`] and [`BencodexValue::Dictionary`] have the SHA-1 hash of their
+///   canonical encoding.
+///
+/// Two values are equal if and only if their fingerprints are equal, barring hash collisions.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Fingerprint {
+    kind: ValueKind,
+    encoding_length: u64,
+    digest: Vec<u8>,
+}
+
+impl Fingerprint {
+    pub fn new(kind: ValueKind, encoding_length: u64, digest: Vec<u8>) -> Fingerprint {
+        Fingerprint {
+            kind,
+            encoding_length,
+            digest,
+        }
+    }
+
+    pub fn kind(&self) -> ValueKind {
+        self.kind
+    }
+
+    /// The length of the canonical encoding of the value, in bytes.
+    pub fn encoding_length(&self) -> u64 {
+        self.encoding_length
+    }
+
+    pub fn digest(&self) -> &[u8] {
+        &self.digest
+    }
+
+    /// Serializes the fingerprint in the format of Bencodex.Net: the kind in a byte, the
+    /// encoding length in 8 little-endian bytes, and then the digest.
+    ///
+    /// ```
+    /// use bencodex::BencodexValue;
+    ///
+    /// let fingerprint = BencodexValue::Boolean(true).fingerprint();
+    /// assert_eq!(fingerprint.serialize(), vec![1, 1, 0, 0, 0, 0, 0, 0, 0, 1]);
+    /// ```
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(SERIALIZED_HEADER_SIZE + self.digest.len());
+        buf.push(self.kind as u8);
+        buf.extend_from_slice(&self.encoding_length.to_le_bytes());
+        buf.extend_from_slice(&self.digest);
+        buf
+    }
+
+    /// Deserializes a fingerprint serialized by [`Fingerprint::serialize`].
+    ///
+    /// ```
+    /// use bencodex::{ BencodexValue, Fingerprint };
+    ///
+    /// let fingerprint = BencodexValue::from("foo").fingerprint();
+    /// assert_eq!(Fingerprint::deserialize(&fingerprint.serialize()), Ok(fingerprint));
+    /// ```
+    pub fn deserialize(bytes: &[u8]) -> Result<Fingerprint, DecodeError> {
+        if bytes.len() < SERIALIZED_HEADER_SIZE {
+            return Err(DecodeError::InvalidBencodexValueError);
+        }
+
+        let kind = ValueKind::try_from(bytes[0])?;
+        let mut encoding_length = [0; 8];
+        encoding_length.copy_from_slice(&bytes[1..SERIALIZED_HEADER_SIZE]);
+
+        Ok(Fingerprint {
+            kind,
+            encoding_length: u64::from_le_bytes(encoding_length),
+            digest: bytes[SERIALIZED_HEADER_SIZE..].to_vec(),
+        })
+    }
+}
+
+/// An [`io::Write`] which hashes and counts the written bytes instead of keeping them.
+pub(crate) struct HashingWriter {
+    hasher: Sha1,
+    length: u64,
+}
+
+impl HashingWriter {
+    pub(crate) fn new() -> HashingWriter {
+        HashingWriter {
+            hasher: Sha1::new(),
+            length: 0,
+        }
+    }
+
+    /// Returns the fingerprint of the list or dictionary whose encoding has been written.
+    pub(crate) fn finish(self, kind: ValueKind) -> Fingerprint {
+        Fingerprint::new(kind, self.length, self.hasher.finalize().to_vec())
+    }
+}
+
+impl io::Write for HashingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.hasher.update(buf);
+        self.length += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn inline_or_hash(bytes: &[u8]) -> Vec<u8> {
+    if bytes.len() > INLINE_DIGEST_LIMIT {
+        Sha1::digest(bytes).to_vec()
+    } else {
+        bytes.to_vec()
+    }
+}
+
+impl BencodexValue {
+    /// Computes the [`Fingerprint`] of this value.
+    ///
+    /// Lists and dictionaries are hashed by streaming their canonical encoding into the
+    /// hasher, so the encoded bytes are never allocated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bencodex::{ BencodexValue, ValueKind };
+    ///
+    /// let fingerprint = BencodexValue::from("foo").fingerprint();
+    /// assert_eq!(fingerprint.kind(), ValueKind::Text);
+    /// assert_eq!(fingerprint.encoding_length(), 6);
+    /// assert_eq!(fingerprint.digest(), b"foo");
+    ///
+    /// let list: BencodexValue = vec![BencodexValue::Null].into();
+    /// assert_eq!(list.fingerprint().encoding_length(), 3);
+    /// assert_eq!(list.fingerprint().digest().len(), 20);
+    /// ```
+    pub fn fingerprint(&self) -> Fingerprint {
+        let kind = self.kind();
+        let digest = match self {
+            BencodexValue::Null => vec![],
+            BencodexValue::Boolean(x) => vec![*x as u8],
+            BencodexValue::Number(x) => x.to_signed_bytes_le(),
+            BencodexValue::Binary(x) => inline_or_hash(x),
+            BencodexValue::Text(x) => inline_or_hash(x.as_bytes()),
+            BencodexValue::List(_) | BencodexValue::Dictionary(_) => {
+                let mut writer = HashingWriter::new();
+                self.encode(&mut writer)
+                    .expect("writing to a hasher should not fail");
+                return writer.finish(kind);
+            }
+        };
+
+        Fingerprint::new(kind, self.encoding_length(), digest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod fingerprint {
+        use super::super::*;
+        use crate::BencodexDictionary;
+
+        fn encode(value: &BencodexValue) -> Vec<u8> {
+            let mut buf = vec![];
+            value.encode(&mut buf).unwrap();
+            buf
+        }
+
+        #[test]
+        fn should_have_encoding_length() {
+            let mut dict = BencodexDictionary::new();
+            dict.insert("foo".into(), vec![0u8; 100].into());
+            let values: Vec<BencodexValue> = vec![
+                BencodexValue::Null,
+                true.into(),
+                false.into(),
+                0.into(),
+                (-1234567).into(),
+                vec![0u8; 0].into(),
+                vec![0u8; 10].into(),
+                "".into(),
+                "가나다".into(),
+                vec![1, 2, 3].into(),
+                BencodexValue::Dictionary(dict),
+            ];
+            for value in values {
+                assert_eq!(
+                    encode(&value).len() as u64,
+                    value.fingerprint().encoding_length(),
+                    "{}",
+                    value
+                );
+                assert_eq!(
+                    encode(&value).len() as u64,
+                    value.encoding_length(),
+                    "{}",
+                    value
+                );
+            }
+        }
+
+        #[test]
+        fn should_inline_small_values() {
+            assert_eq!(
+                Fingerprint::new(ValueKind::Null, 1, vec![]),
+                BencodexValue::Null.fingerprint()
+            );
+            assert_eq!(
+                Fingerprint::new(ValueKind::Boolean, 1, vec![0]),
+                BencodexValue::Boolean(false).fingerprint()
+            );
+            assert_eq!(
+                Fingerprint::new(ValueKind::Integer, 5, vec![0x00, 0x01]),
+                BencodexValue::from(256).fingerprint()
+            );
+            assert_eq!(
+                Fingerprint::new(ValueKind::Integer, 4, vec![0xff]),
+                BencodexValue::from(-1).fingerprint()
+            );
+            assert_eq!(
+                Fingerprint::new(ValueKind::Binary, 23, vec![7; 20]),
+                BencodexValue::Binary(vec![7; 20]).fingerprint()
+            );
+        }
+
+        #[test]
+        fn should_hash_large_values() {
+            let binary = vec![7; 21];
+            assert_eq!(
+                Fingerprint::new(ValueKind::Binary, 24, Sha1::digest(&binary).to_vec()),
+                BencodexValue::Binary(binary).fingerprint()
+            );
+
+            let text = "a".repeat(21);
+            assert_eq!(
+                Fingerprint::new(ValueKind::Text, 25, Sha1::digest(text.as_bytes()).to_vec()),
+                BencodexValue::Text(text).fingerprint()
+            );
+
+            let list: BencodexValue = vec![1, 2].into();
+            assert_eq!(
+                Fingerprint::new(ValueKind::List, 8, Sha1::digest(b"li1ei2ee").to_vec()),
+                list.fingerprint()
+            );
+        }
+    }
+
+    mod known_answers {
+        use super::super::*;
+
+        fn from_hex(hex: &str) -> Vec<u8> {
+            (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+                .collect()
+        }
+
+        // The two-block message and its SHA-1 digest from FIPS 180-2, Appendix A.2.
+        const FIPS_MESSAGE: &str = "abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+        const FIPS_DIGEST: &str = "84983e441c3bd26ebaae4aa1f95129e5e54670f1";
+
+        #[test]
+        fn should_hash_long_text() {
+            let fingerprint = BencodexValue::from(FIPS_MESSAGE).fingerprint();
+            assert_eq!(
+                Fingerprint::new(ValueKind::Text, 60, from_hex(FIPS_DIGEST)),
+                fingerprint
+            );
+
+            let mut serialized = vec![4, 60, 0, 0, 0, 0, 0, 0, 0];
+            serialized.extend(from_hex(FIPS_DIGEST));
+            assert_eq!(serialized, fingerprint.serialize());
+        }
+
+        #[test]
+        fn should_hash_long_binary() {
+            // A well-known digest from outside FIPS 180, of a 43-byte message.
+            let message = b"The quick brown fox jumps over the lazy dog";
+            assert_eq!(
+                Fingerprint::new(
+                    ValueKind::Binary,
+                    46,
+                    from_hex("2fd4e1c67a2d28fced849ee1bb76e7391b93eb12")
+                ),
+                BencodexValue::Binary(message.to_vec()).fingerprint()
+            );
+            assert_eq!(
+                Fingerprint::new(ValueKind::Binary, 59, from_hex(FIPS_DIGEST)),
+                BencodexValue::Binary(FIPS_MESSAGE.as_bytes().to_vec()).fingerprint()
+            );
+        }
+    }
+
+    mod deserialize {
+        use super::super::*;
+
+        #[test]
+        fn should_return_error_with_invalid_source() {
+            assert_eq!(
+                Err(DecodeError::InvalidBencodexValueError),
+                Fingerprint::deserialize(&[5, 0, 0])
+            );
+            assert_eq!(
+                Err(DecodeError::UnexpectedTokenError { token: 7, point: 0 }),
+                Fingerprint::deserialize(&[7, 0, 0, 0, 0, 0, 0, 0, 0])
+            );
+        }
+    }
+}
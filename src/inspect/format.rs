@@ -0,0 +1,176 @@
+use std::fmt;
+
+use crate::{BencodexKey, BencodexValue};
+
+fn write_binary(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    f.write_str("b\"")?;
+    for byte in bytes {
+        write!(f, "\\x{:02x}", byte)?;
+    }
+    f.write_str("\"")
+}
+
+fn write_text(f: &mut fmt::Formatter<'_>, text: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in text.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+/// Writes a nested `value` in pretty mode, indenting its continuation lines by
+/// one more level than its parent.
+fn write_nested(f: &mut fmt::Formatter<'_>, value: &BencodexValue) -> fmt::Result {
+    f.write_str(&format!("{:#}", value).replace('\n', "\n  "))
+}
+
+impl fmt::Display for BencodexKey {
+    /// Formats the key in the Bencodex Inspect notation, the same as the
+    /// corresponding [`BencodexValue::Binary`] or [`BencodexValue::Text`].
+    ///
+    /// ```
+    /// use bencodex::BencodexKey;
+    ///
+    /// assert_eq!(BencodexKey::from("foo").to_string(), "\"foo\"");
+    /// assert_eq!(BencodexKey::from(vec![0x01, 0xab]).to_string(), "b\"\\x01\\xab\"");
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BencodexKey::Binary(x) => write_binary(f, x),
+            BencodexKey::Text(x) => write_text(f, x),
+        }
+    }
+}
+
+impl fmt::Display for BencodexValue {
+    /// Formats the value in the Bencodex Inspect notation, the human-readable
+    /// notation also used by Bencodex.Net.
+    ///
+    /// The default format writes the whole value on a single line. The alternate
+    /// format (`{:#}`) writes each list element and dictionary entry on its own
+    /// line, indented by two spaces and followed by a trailing comma.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bencodex::{ BencodexDictionary, BencodexValue };
+    ///
+    /// let mut dict = BencodexDictionary::new();
+    /// dict.insert("n".into(), 123.into());
+    /// dict.insert("text".into(), vec![1u8, 2].into());
+    /// let value = BencodexValue::Dictionary(dict);
+    ///
+    /// assert_eq!(value.to_string(), "{\"n\": 123, \"text\": b\"\\x01\\x02\"}");
+    /// assert_eq!(
+    ///     format!("{:#}", value),
+    ///     "{\n  \"n\": 123,\n  \"text\": b\"\\x01\\x02\",\n}"
+    /// );
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BencodexValue::Binary(x) => write_binary(f, x),
+            BencodexValue::Text(x) => write_text(f, x),
+            BencodexValue::Boolean(x) => write!(f, "{}", x),
+            BencodexValue::Number(x) => write!(f, "{}", x),
+            BencodexValue::Null => f.write_str("null"),
+            BencodexValue::List(x) if x.is_empty() => f.write_str("[]"),
+            BencodexValue::List(x) if f.alternate() => {
+                f.write_str("[\n")?;
+                for item in x {
+                    f.write_str("  ")?;
+                    write_nested(f, item)?;
+                    f.write_str(",\n")?;
+                }
+                f.write_str("]")
+            }
+            BencodexValue::List(x) => {
+                f.write_str("[")?;
+                for (i, item) in x.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_str("]")
+            }
+            BencodexValue::Dictionary(x) if x.is_empty() => f.write_str("{}"),
+            BencodexValue::Dictionary(x) if f.alternate() => {
+                f.write_str("{\n")?;
+                for (key, value) in x {
+                    write!(f, "  {}: ", key)?;
+                    write_nested(f, value)?;
+                    f.write_str(",\n")?;
+                }
+                f.write_str("}")
+            }
+            BencodexValue::Dictionary(x) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in x.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}: {}", key, value)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod display {
+        use super::super::*;
+        use crate::BencodexDictionary;
+
+        #[test]
+        fn scalars() {
+            assert_eq!("null", BencodexValue::Null.to_string());
+            assert_eq!("true", BencodexValue::Boolean(true).to_string());
+            assert_eq!("false", BencodexValue::Boolean(false).to_string());
+            assert_eq!("-123", BencodexValue::from(-123).to_string());
+            assert_eq!("b\"\"", BencodexValue::Binary(vec![]).to_string());
+            assert_eq!(
+                "b\"\\x00\\x61\\xff\"",
+                BencodexValue::Binary(vec![0x00, b'a', 0xff]).to_string()
+            );
+        }
+
+        #[test]
+        fn text_escapes() {
+            assert_eq!(
+                "\"a\\\"b\\\\c\\nd\\re\\tf\\u0001\\u007f한\"",
+                BencodexValue::from("a\"b\\c\nd\re\tf\u{1}\u{7f}한").to_string()
+            );
+        }
+
+        #[test]
+        fn containers() {
+            assert_eq!("[]", BencodexValue::List(vec![]).to_string());
+            assert_eq!(
+                "{}",
+                BencodexValue::Dictionary(BencodexDictionary::new()).to_string()
+            );
+            assert_eq!("[]", format!("{:#}", BencodexValue::List(vec![])));
+
+            let mut dict = BencodexDictionary::new();
+            dict.insert("list".into(), vec![1, 2].into());
+            dict.insert(vec![0x01u8].into(), BencodexValue::Null);
+            let value = BencodexValue::Dictionary(dict);
+
+            assert_eq!("{b\"\\x01\": null, \"list\": [1, 2]}", value.to_string());
+            assert_eq!(
+                "{\n  b\"\\x01\": null,\n  \"list\": [\n    1,\n    2,\n  ],\n}",
+                format!("{:#}", value)
+            );
+        }
+    }
+}
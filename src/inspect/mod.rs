@@ -0,0 +1,6 @@
+mod format;
+mod parse;
+mod summary;
+
+pub use parse::{from_inspect, InspectError};
+pub use summary::{Summary, SummaryOptions};
@@ -0,0 +1,429 @@
+use std::error::Error;
+use std::fmt;
+use std::result::Result;
+use std::str::FromStr;
+
+use num_bigint::BigInt;
+
+use crate::{BencodexDictionary, BencodexKey, BencodexValue};
+
+/// The error type which is returned from parsing the Bencodex Inspect notation through [`from_inspect`].
+#[derive(Debug, PartialEq)]
+pub enum InspectError {
+    /// This should be used when the input ended while a value was still being parsed.
+    UnexpectedEndError,
+    /// This should be used when there is an unexpected character at the given byte offset.
+    ///
+    /// ```
+    /// use bencodex::inspect::{ from_inspect, InspectError };
+    ///
+    /// let error = from_inspect("[1, ?]").unwrap_err();
+    /// assert_eq!(InspectError::UnexpectedTokenError { token: '?', point: 4 }, error);
+    /// ```
+    UnexpectedTokenError { token: char, point: usize },
+    /// This should be used when a string contains an invalid escape sequence starting at the given byte offset.
+    InvalidEscapeError { point: usize },
+    /// This should be used when a dictionary key at the given byte offset is neither text nor binary.
+    InvalidKeyError { point: usize },
+    /// This should be used when a dictionary key at the given byte offset appears twice.
+    DuplicateKeyError { point: usize },
+}
+
+impl fmt::Display for InspectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Error for InspectError {}
+
+fn parse_hex(digits: &str) -> Option<u32> {
+    if digits.bytes().all(|x| x.is_ascii_hexdigit()) {
+        u32::from_str_radix(digits, 16).ok()
+    } else {
+        None
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    point: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.source[self.point..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.point += c.len_utf8();
+        }
+    }
+
+    fn next_token(&mut self) -> Result<char, InspectError> {
+        self.skip_whitespace();
+        self.peek().ok_or(InspectError::UnexpectedEndError)
+    }
+
+    fn unexpected(&self, token: char) -> InspectError {
+        InspectError::UnexpectedTokenError {
+            token,
+            point: self.point,
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), InspectError> {
+        match self.next_token()? {
+            c if c == expected => {
+                self.point += c.len_utf8();
+                Ok(())
+            }
+            c => Err(self.unexpected(c)),
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<BencodexValue, InspectError> {
+        match self.next_token()? {
+            '[' => self.parse_list(),
+            '{' => self.parse_dictionary(),
+            '"' => Ok(BencodexValue::Text(self.parse_text()?)),
+            'b' => Ok(BencodexValue::Binary(self.parse_binary()?)),
+            '-' | '0'..='9' => self.parse_number(),
+            'n' => self.parse_keyword("null", BencodexValue::Null),
+            't' => self.parse_keyword("true", BencodexValue::Boolean(true)),
+            'f' => self.parse_keyword("false", BencodexValue::Boolean(false)),
+            c => Err(self.unexpected(c)),
+        }
+    }
+
+    fn parse_keyword(
+        &mut self,
+        keyword: &str,
+        value: BencodexValue,
+    ) -> Result<BencodexValue, InspectError> {
+        for expected in keyword.chars() {
+            match self.peek() {
+                Some(c) if c == expected => self.point += 1,
+                Some(c) => return Err(self.unexpected(c)),
+                None => return Err(InspectError::UnexpectedEndError),
+            }
+        }
+
+        Ok(value)
+    }
+
+    fn parse_number(&mut self) -> Result<BencodexValue, InspectError> {
+        let start = self.point;
+        if self.peek() == Some('-') {
+            self.point += 1;
+        }
+        while let Some('0'..='9') = self.peek() {
+            self.point += 1;
+        }
+
+        BigInt::from_str(&self.source[start..self.point])
+            .map(BencodexValue::Number)
+            .map_err(|_| match self.peek() {
+                Some(c) => self.unexpected(c),
+                None => InspectError::UnexpectedEndError,
+            })
+    }
+
+    // point must be on '"'
+    fn parse_text(&mut self) -> Result<String, InspectError> {
+        self.point += 1;
+        let mut text = String::new();
+        loop {
+            let c = self.peek().ok_or(InspectError::UnexpectedEndError)?;
+            match c {
+                '"' => {
+                    self.point += 1;
+                    return Ok(text);
+                }
+                '\\' => text.push(self.parse_escape()?),
+                c => {
+                    text.push(c);
+                    self.point += c.len_utf8();
+                }
+            }
+        }
+    }
+
+    // point must be on 'b'
+    fn parse_binary(&mut self) -> Result<Vec<u8>, InspectError> {
+        self.point += 1;
+        match self.peek() {
+            Some('"') => self.point += 1,
+            Some(c) => return Err(self.unexpected(c)),
+            None => return Err(InspectError::UnexpectedEndError),
+        }
+
+        let mut bytes = Vec::new();
+        loop {
+            let c = self.peek().ok_or(InspectError::UnexpectedEndError)?;
+            match c {
+                '"' => {
+                    self.point += 1;
+                    return Ok(bytes);
+                }
+                '\\' if self.source[self.point..].starts_with("\\x") => {
+                    let start = self.point;
+                    let digits = self
+                        .source
+                        .get(start + 2..start + 4)
+                        .ok_or(InspectError::InvalidEscapeError { point: start })?;
+                    let byte = parse_hex(digits)
+                        .ok_or(InspectError::InvalidEscapeError { point: start })?;
+                    bytes.push(byte as u8);
+                    self.point += 4;
+                }
+                '\\' => {
+                    let start = self.point;
+                    let escaped = self.parse_escape()?;
+                    if !escaped.is_ascii() {
+                        return Err(InspectError::InvalidEscapeError { point: start });
+                    }
+                    bytes.push(escaped as u8);
+                }
+                c if c.is_ascii() => {
+                    bytes.push(c as u8);
+                    self.point += 1;
+                }
+                c => return Err(self.unexpected(c)),
+            }
+        }
+    }
+
+    // point must be on '\\'
+    fn parse_escape(&mut self) -> Result<char, InspectError> {
+        let start = self.point;
+        let invalid = InspectError::InvalidEscapeError { point: start };
+        let escaped = match self.source[start + 1..].chars().next() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => {
+                let escaped = self
+                    .source
+                    .get(start + 2..start + 6)
+                    .and_then(parse_hex)
+                    .and_then(char::from_u32)
+                    .ok_or(invalid)?;
+                self.point += 6;
+                return Ok(escaped);
+            }
+            Some(_) => return Err(invalid),
+            None => return Err(InspectError::UnexpectedEndError),
+        };
+        self.point += 2;
+
+        Ok(escaped)
+    }
+
+    // point must be on '['
+    fn parse_list(&mut self) -> Result<BencodexValue, InspectError> {
+        self.point += 1;
+        let mut list = Vec::new();
+        loop {
+            if self.next_token()? == ']' {
+                self.point += 1;
+                return Ok(BencodexValue::List(list));
+            }
+
+            list.push(self.parse_value()?);
+            match self.next_token()? {
+                ',' => self.point += 1,
+                ']' => continue,
+                c => return Err(self.unexpected(c)),
+            }
+        }
+    }
+
+    // point must be on '{'
+    fn parse_dictionary(&mut self) -> Result<BencodexValue, InspectError> {
+        self.point += 1;
+        let mut map = BencodexDictionary::new();
+        loop {
+            if self.next_token()? == '}' {
+                self.point += 1;
+                return Ok(BencodexValue::Dictionary(map));
+            }
+
+            let point = self.point;
+            let key = match self.parse_value()? {
+                BencodexValue::Text(s) => BencodexKey::Text(s),
+                BencodexValue::Binary(b) => BencodexKey::Binary(b),
+                _ => return Err(InspectError::InvalidKeyError { point }),
+            };
+            self.expect(':')?;
+            let value = self.parse_value()?;
+            if map.insert(key, value).is_some() {
+                return Err(InspectError::DuplicateKeyError { point });
+            }
+
+            match self.next_token()? {
+                ',' => self.point += 1,
+                '}' => continue,
+                c => return Err(self.unexpected(c)),
+            }
+        }
+    }
+}
+
+/// Parses a Bencodex value written in the Bencodex Inspect notation, which is what
+/// the [`Display`](std::fmt::Display) implementation of [`BencodexValue`] produces.
+///
+/// Whitespace between tokens and trailing commas are allowed, so both the single-line
+/// and the pretty (`{:#}`) forms are accepted.
+///
+/// # Examples
+///
+/// ```
+/// use bencodex::BencodexValue;
+/// use bencodex::inspect::from_inspect;
+///
+/// let value = from_inspect(r#"{ "text": b"\x01\x02", "n": 123 }"#).unwrap();
+/// assert_eq!(from_inspect(&value.to_string()).unwrap(), value);
+/// assert_eq!(from_inspect(&format!("{:#}", value)).unwrap(), value);
+/// ```
+pub fn from_inspect(s: &str) -> Result<BencodexValue, InspectError> {
+    let mut parser = Parser {
+        source: s,
+        point: 0,
+    };
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    match parser.peek() {
+        None => Ok(value),
+        Some(c) => Err(parser.unexpected(c)),
+    }
+}
+
+impl FromStr for BencodexValue {
+    type Err = InspectError;
+
+    /// Parses the Bencodex Inspect notation. See [`from_inspect`].
+    ///
+    /// ```
+    /// use bencodex::BencodexValue;
+    ///
+    /// let value: BencodexValue = "[null, true, -1]".parse().unwrap();
+    /// assert_eq!(value, vec![BencodexValue::Null, true.into(), (-1).into()].into());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        from_inspect(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod from_inspect {
+        use super::super::*;
+
+        #[test]
+        fn scalars() {
+            assert_eq!(Ok(BencodexValue::Null), from_inspect("null"));
+            assert_eq!(Ok(BencodexValue::Boolean(true)), from_inspect(" true "));
+            assert_eq!(Ok(BencodexValue::Boolean(false)), from_inspect("false"));
+            assert_eq!(Ok(BencodexValue::from(-42)), from_inspect("-42"));
+            assert_eq!(
+                Ok(BencodexValue::Number(
+                    BigInt::from_str("123456789012345678901234567890").unwrap()
+                )),
+                from_inspect("123456789012345678901234567890")
+            );
+            assert_eq!(
+                Ok(BencodexValue::Binary(vec![0x01, 0xab, b'c', b'"'])),
+                from_inspect(r#"b"\x01\xABc\"""#)
+            );
+            assert_eq!(
+                Ok(BencodexValue::from("a\"b\\c\nd\u{1}한")),
+                from_inspect(r#""a\"b\\c\nd\u0001한""#)
+            );
+        }
+
+        #[test]
+        fn containers() {
+            let mut dict = BencodexDictionary::new();
+            dict.insert(vec![0x00u8].into(), vec![1, 2].into());
+            dict.insert(
+                "".into(),
+                BencodexValue::Dictionary(BencodexDictionary::new()),
+            );
+            let expected = BencodexValue::Dictionary(dict);
+
+            assert_eq!(
+                Ok(expected.clone()),
+                from_inspect(r#"{"": {}, b"\x00": [1, 2]}"#)
+            );
+            assert_eq!(
+                Ok(expected),
+                from_inspect("{\n  \"\": {},\n  b\"\\x00\": [\n    1,\n    2,\n  ],\n}")
+            );
+            assert_eq!(Ok(BencodexValue::List(vec![])), from_inspect("[ ]"));
+        }
+
+        #[test]
+        fn should_return_unexpected_end_error() {
+            assert_eq!(Err(InspectError::UnexpectedEndError), from_inspect(""));
+            assert_eq!(Err(InspectError::UnexpectedEndError), from_inspect("[1,"));
+            assert_eq!(Err(InspectError::UnexpectedEndError), from_inspect("\"abc"));
+            assert_eq!(Err(InspectError::UnexpectedEndError), from_inspect("nul"));
+        }
+
+        #[test]
+        fn should_return_unexpected_token_error() {
+            assert_eq!(
+                Err(InspectError::UnexpectedTokenError {
+                    token: 'x',
+                    point: 5
+                }),
+                from_inspect("null x")
+            );
+            assert_eq!(
+                Err(InspectError::UnexpectedTokenError {
+                    token: ' ',
+                    point: 1
+                }),
+                from_inspect("- 1")
+            );
+            assert_eq!(
+                Err(InspectError::UnexpectedTokenError {
+                    token: '2',
+                    point: 3
+                }),
+                from_inspect("[1 2]")
+            );
+        }
+
+        #[test]
+        fn should_return_invalid_escape_error() {
+            assert_eq!(
+                Err(InspectError::InvalidEscapeError { point: 2 }),
+                from_inspect(r#"b"\xZZ""#)
+            );
+            assert_eq!(
+                Err(InspectError::InvalidEscapeError { point: 1 }),
+                from_inspect(r#""\q""#)
+            );
+        }
+
+        #[test]
+        fn should_return_key_errors() {
+            assert_eq!(
+                Err(InspectError::InvalidKeyError { point: 1 }),
+                from_inspect("{1: null}")
+            );
+            assert_eq!(
+                Err(InspectError::DuplicateKeyError { point: 12 }),
+                from_inspect(r#"{"a": null, "a": null}"#)
+            );
+        }
+    }
+}
@@ -0,0 +1,321 @@
+use std::fmt;
+
+use crate::{BencodexKey, BencodexValue};
+
+/// Options used by [`BencodexValue::summary`] to decide which parts of a value are elided.
+///
+/// # Examples
+///
+/// ```
+/// use bencodex::inspect::SummaryOptions;
+///
+/// SummaryOptions {
+///   max_depth: 2,
+///   max_items: 4,
+///   max_bytes: 8,
+/// };
+/// ```
+///
+/// Or you can use [`SummaryOptions::default`]:
+///
+/// ```
+/// use bencodex::inspect::SummaryOptions;
+///
+/// SummaryOptions::default();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SummaryOptions {
+    /// Lists and dictionaries nested deeper than this are replaced by their size, and their
+    /// fingerprint with `fingerprint` feature.
+    pub max_depth: usize,
+    /// Only the first `max_items` elements of a list or entries of a dictionary are shown.
+    pub max_items: usize,
+    /// Binaries and texts longer than `max_bytes` bytes are replaced by their size and first bytes.
+    pub max_bytes: usize,
+}
+
+impl Default for SummaryOptions {
+    fn default() -> Self {
+        SummaryOptions {
+            max_depth: 4,
+            max_items: 8,
+            max_bytes: 16,
+        }
+    }
+}
+
+/// A log-friendly summary of a [`BencodexValue`], returned by [`BencodexValue::summary`].
+///
+/// It is written in the single-line Bencodex Inspect notation, except for the parts elided
+/// by its [`SummaryOptions`].
+pub struct Summary<'a> {
+    value: &'a BencodexValue,
+    options: SummaryOptions,
+}
+
+impl BencodexValue {
+    /// Returns a [`Summary`] of this value, which elides long binaries and texts, long lists
+    /// and dictionaries, and deep nesting so that it can be written to logs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bencodex::BencodexValue;
+    /// use bencodex::inspect::SummaryOptions;
+    ///
+    /// let options = SummaryOptions { max_depth: 1, max_items: 2, max_bytes: 3 };
+    ///
+    /// let binary = BencodexValue::Binary(vec![0x0a; 2097152]);
+    /// assert_eq!(binary.summary(options).to_string(), "<2097152 bytes: 0a0a0a…>");
+    ///
+    /// let list: BencodexValue = vec![1, 2, 3, 4, 5].into();
+    /// assert_eq!(list.summary(options).to_string(), "[1, 2, … 3 more]");
+    ///
+    /// let nested: BencodexValue = vec![vec![1, 2]].into();
+    /// # #[cfg(feature = "fingerprint")]
+    /// assert_eq!(nested.summary(options).to_string(), "[<list: 2 items, cbf5eef9…>]");
+    /// # #[cfg(not(feature = "fingerprint"))]
+    /// assert_eq!(nested.summary(options).to_string(), "[<list: 2 items>]");
+    /// ```
+    pub fn summary(&self, options: SummaryOptions) -> Summary<'_> {
+        Summary {
+            value: self,
+            options,
+        }
+    }
+}
+
+fn write_bytes_summary(f: &mut fmt::Formatter<'_>, bytes: &[u8], max_bytes: usize) -> fmt::Result {
+    write!(f, "<{} bytes: ", bytes.len())?;
+    for byte in &bytes[..max_bytes] {
+        write!(f, "{:02x}", byte)?;
+    }
+    f.write_str("…>")
+}
+
+/// Writes the first bytes of the fingerprint digest, which is enough to tell apart
+/// elided values in logs.
+#[cfg(feature = "fingerprint")]
+fn write_fingerprint_summary(f: &mut fmt::Formatter<'_>, value: &BencodexValue) -> fmt::Result {
+    f.write_str(", ")?;
+    for byte in value.fingerprint().digest().iter().take(4) {
+        write!(f, "{:02x}", byte)?;
+    }
+    f.write_str("…")
+}
+
+/// Writes nothing, as fingerprints are computed only with `fingerprint` feature.
+#[cfg(not(feature = "fingerprint"))]
+fn write_fingerprint_summary(_: &mut fmt::Formatter<'_>, _: &BencodexValue) -> fmt::Result {
+    Ok(())
+}
+
+fn write_text_summary(f: &mut fmt::Formatter<'_>, text: &str, max_bytes: usize) -> fmt::Result {
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let prefix = BencodexValue::Text(text[..end].to_string()).to_string();
+    write!(
+        f,
+        "<{} bytes of text: {}…>",
+        text.len(),
+        &prefix[..prefix.len() - 1]
+    )
+}
+
+fn write_key_summary(
+    f: &mut fmt::Formatter<'_>,
+    key: &BencodexKey,
+    options: &SummaryOptions,
+) -> fmt::Result {
+    match key {
+        BencodexKey::Binary(x) if x.len() > options.max_bytes => {
+            write_bytes_summary(f, x, options.max_bytes)
+        }
+        BencodexKey::Text(x) if x.len() > options.max_bytes => {
+            write_text_summary(f, x, options.max_bytes)
+        }
+        key => write!(f, "{}", key),
+    }
+}
+
+fn write_summary(
+    f: &mut fmt::Formatter<'_>,
+    value: &BencodexValue,
+    options: &SummaryOptions,
+    depth: usize,
+) -> fmt::Result {
+    match value {
+        BencodexValue::Binary(x) if x.len() > options.max_bytes => {
+            write_bytes_summary(f, x, options.max_bytes)
+        }
+        BencodexValue::Text(x) if x.len() > options.max_bytes => {
+            write_text_summary(f, x, options.max_bytes)
+        }
+        BencodexValue::List(x) if !x.is_empty() && depth >= options.max_depth => {
+            write!(f, "<list: {} items", x.len())?;
+            write_fingerprint_summary(f, value)?;
+            f.write_str(">")
+        }
+        BencodexValue::Dictionary(x) if !x.is_empty() && depth >= options.max_depth => {
+            write!(f, "<dictionary: {} entries", x.len())?;
+            write_fingerprint_summary(f, value)?;
+            f.write_str(">")
+        }
+        BencodexValue::List(x) if !x.is_empty() => {
+            f.write_str("[")?;
+            for (i, item) in x.iter().take(options.max_items).enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                write_summary(f, item, options, depth + 1)?;
+            }
+            if x.len() > options.max_items {
+                if options.max_items > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "… {} more", x.len() - options.max_items)?;
+            }
+            f.write_str("]")
+        }
+        BencodexValue::Dictionary(x) if !x.is_empty() => {
+            f.write_str("{")?;
+            for (i, (key, value)) in x.iter().take(options.max_items).enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                write_key_summary(f, key, options)?;
+                f.write_str(": ")?;
+                write_summary(f, value, options, depth + 1)?;
+            }
+            if x.len() > options.max_items {
+                if options.max_items > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "… {} more", x.len() - options.max_items)?;
+            }
+            f.write_str("}")
+        }
+        value => write!(f, "{}", value),
+    }
+}
+
+impl fmt::Display for Summary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_summary(f, self.value, &self.options, 0)
+    }
+}
+
+impl fmt::Debug for Summary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod summary {
+        use super::super::*;
+        use crate::BencodexDictionary;
+
+        const OPTIONS: SummaryOptions = SummaryOptions {
+            max_depth: 2,
+            max_items: 2,
+            max_bytes: 4,
+        };
+
+        #[test]
+        fn should_not_elide_small_values() {
+            let value: BencodexValue = vec![
+                BencodexValue::Binary(vec![1, 2, 3, 4]),
+                BencodexValue::from("abcd"),
+            ]
+            .into();
+            assert_eq!(value.to_string(), value.summary(OPTIONS).to_string());
+        }
+
+        #[test]
+        fn should_elide_long_binaries_and_texts() {
+            assert_eq!(
+                "<5 bytes: 01020304…>",
+                BencodexValue::Binary(vec![1, 2, 3, 4, 5])
+                    .summary(OPTIONS)
+                    .to_string()
+            );
+            assert_eq!(
+                "<6 bytes of text: \"a\\nbc…>",
+                BencodexValue::from("a\nbcde").summary(OPTIONS).to_string()
+            );
+            // "가" takes 3 bytes in UTF-8, so the second one does not fit in 4 bytes.
+            assert_eq!(
+                "<9 bytes of text: \"가…>",
+                BencodexValue::from("가나다").summary(OPTIONS).to_string()
+            );
+        }
+
+        #[test]
+        fn should_elide_long_containers() {
+            let mut dict = BencodexDictionary::new();
+            dict.insert("a".into(), 1.into());
+            dict.insert("b".into(), 2.into());
+            dict.insert("c".into(), 3.into());
+            dict.insert("long key".into(), 4.into());
+            assert_eq!(
+                "{\"a\": 1, \"b\": 2, … 2 more}",
+                BencodexValue::Dictionary(dict.clone())
+                    .summary(OPTIONS)
+                    .to_string()
+            );
+
+            let options = SummaryOptions {
+                max_items: 0,
+                ..OPTIONS
+            };
+            assert_eq!(
+                "[… 3 more]",
+                BencodexValue::from(vec![1, 2, 3])
+                    .summary(options)
+                    .to_string()
+            );
+
+            let options = SummaryOptions {
+                max_items: 4,
+                ..OPTIONS
+            };
+            assert_eq!(
+                "{\"a\": 1, \"b\": 2, \"c\": 3, <8 bytes of text: \"long…>: 4}",
+                BencodexValue::Dictionary(dict).summary(options).to_string()
+            );
+        }
+
+        #[test]
+        #[cfg(feature = "fingerprint")]
+        fn should_elide_deep_nesting() {
+            let value: BencodexValue = vec![vec![vec![1, 2, 3]], vec![]].into();
+            assert_eq!(
+                "[[<list: 3 items, 846d18c0…>], []]",
+                value.summary(OPTIONS).to_string()
+            );
+
+            let mut dict = BencodexDictionary::new();
+            dict.insert("a".into(), 1.into());
+            let value: BencodexValue = vec![vec![BencodexValue::Dictionary(dict)]].into();
+            assert_eq!(
+                "[[<dictionary: 1 entries, 0b56d6ac…>]]",
+                value.summary(OPTIONS).to_string()
+            );
+        }
+
+        #[test]
+        #[cfg(not(feature = "fingerprint"))]
+        fn should_elide_deep_nesting_without_fingerprints() {
+            let value: BencodexValue = vec![vec![vec![1, 2, 3]], vec![]].into();
+            assert_eq!(
+                "[[<list: 3 items>], []]",
+                value.summary(OPTIONS).to_string()
+            );
+        }
+    }
+}
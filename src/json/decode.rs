@@ -1,35 +1,269 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
 use std::result::Result;
+use std::str::FromStr;
 
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
 use base64::Engine;
-use itertools::Itertools;
-use serde_json::Value;
+use num_bigint::BigInt;
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_json::{Number, Value};
 
-use crate::{BencodexDictionary, BencodexKey, BencodexValue};
+use crate::codec::borrowed::LazyValue;
+use crate::{BencodexDictionary, BencodexKey, BencodexValue, DecodeError, Encode};
 
 /// The error type which is returned from decoding json to bencodex.
 #[derive(Debug, PartialEq)]
 pub enum JsonDecodeError {
     /// This should be used when it failed to decode because the given json string is invalid. It is used by [`from_json_string`].
     /// For example, it will be returned when `nulll` string is given.
-    InvalidJsonString,
+    ///
+    /// The `line` and the `column` are 1-based, and the `message` is the reason [`serde_json`]
+    /// gives, e.g., `trailing characters`.
+    InvalidJsonString {
+        line: usize,
+        column: usize,
+        message: String,
+    },
     /// This should be used when it failed to decode because the given json is invalid. It is used by [`from_json`] and [`from_json_string`].
     /// For example, it will be returned when `serde_json::Value::String("0xZZ")` is given.
-    InvalidJson,
+    ///
+    /// The `pointer` is the [JSON Pointer] of the invalid value, or of the value of the
+    /// invalid key. The `line` and the `column` are given only when it is decoded from a
+    /// string or a reader, and point to where the decoder noticed the error, which is
+    /// right after the invalid value or key.
+    ///
+    /// ```
+    /// use bencodex::json::{ from_json_string, JsonDecodeError, JsonDecodeErrorReason };
+    ///
+    /// let result = from_json_string("{\"\u{FEFF}a\": [\n  \"0x00\",\n  \"0xZZ\"\n]}");
+    /// assert_eq!(
+    ///     result.unwrap_err(),
+    ///     JsonDecodeError::InvalidJson {
+    ///         pointer: "/\u{FEFF}a/1".to_string(),
+    ///         line: Some(3),
+    ///         column: Some(8),
+    ///         reason: JsonDecodeErrorReason::InvalidHex,
+    ///     }
+    /// );
+    /// ```
+    ///
+    /// [JSON Pointer]: https://www.rfc-editor.org/rfc/rfc6901
+    InvalidJson {
+        pointer: String,
+        line: Option<usize>,
+        column: Option<usize>,
+        reason: JsonDecodeErrorReason,
+    },
 }
 
-fn from_json_key_impl(s: &str) -> Result<BencodexKey, JsonDecodeError> {
+impl fmt::Display for JsonDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Error for JsonDecodeError {}
+
+/// The reason why a valid JSON is not valid Bencodex JSON, given by
+/// [`JsonDecodeError::InvalidJson`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum JsonDecodeErrorReason {
+    /// A string starting with `0x` is not followed by a valid hexadecimal.
+    InvalidHex,
+    /// A string starting with `b64:` or `b64url:` is not followed by a valid base64.
+    InvalidBase64,
+    /// A string starting with `b58:` is not followed by a valid base58.
+    InvalidBase58,
+    /// A string is neither a binary, an integer, nor a text starting with `\u{FEFF}`.
+    MissingTextPrefix,
+    /// A JSON number is given, while integers should be given as strings.
+    UnexpectedNumber,
+    /// A string which looks like an integer, consisting of digits and `-`, is not an integer.
+    InvalidInteger,
+    /// A value is not of the type given by a hint, e.g., to
+    /// [`from_plain_json`](crate::json::from_plain_json).
+    UnexpectedType,
+    /// An object has several keys for the same Bencodex key, while
+    /// [`JsonDecodeOptions::reject_duplicate_keys`] is set.
+    DuplicateKey,
+    /// An object has a key out of the canonical order after more than a mebibyte of its
+    /// entries is written, so that [`transcode_from_json`] cannot reorder them.
+    UnorderedKeys,
+}
+
+/// Options used by [`from_json_with_options`], [`from_json_string_with_options`] and
+/// [`transcode_from_json_with_options`] when decoding JSON to Bencodex.
+///
+/// The default options decode strict [Bencodex JSON], and each option relaxes it to accept
+/// JSON written by hand.
+///
+/// # Examples
+///
+/// ```
+/// use bencodex::{ BencodexDictionary, BencodexValue };
+/// use bencodex::json::{ from_json_string_with_options, JsonDecodeOptions };
+///
+/// let options = JsonDecodeOptions {
+///   allow_numbers: true,
+///   allow_unprefixed_texts: true,
+///   hex_as_text: true,
+///   ..Default::default()
+/// };
+/// let result = from_json_string_with_options(
+///     r#"{"address": "0xdeadbeef", "amount": 10, "raw": "b64:AQ=="}"#,
+///     options,
+/// );
+///
+/// let mut expected = BencodexDictionary::new();
+/// expected.insert("address".into(), "0xdeadbeef".into());
+/// expected.insert("amount".into(), 10.into());
+/// expected.insert("raw".into(), vec![1u8].into());
+/// assert_eq!(result, Ok(BencodexValue::Dictionary(expected)));
+/// ```
+///
+/// [Bencodex JSON]: https://github.com/planetarium/bencodex/blob/main/JSON.md
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct JsonDecodeOptions {
+    /// Whether to accept JSON numbers which are integers fitting in 64 bits as Bencodex
+    /// integers. Other numbers are rejected with [`JsonDecodeErrorReason::InvalidInteger`].
+    pub allow_numbers: bool,
+    /// Whether to accept strings without the `\u{FEFF}` prefix, both keys and values, as texts.
+    /// Strings of digits are still decoded as integers if they are integers.
+    pub allow_unprefixed_texts: bool,
+    /// Whether to decode strings starting with `0x` as texts instead of hexadecimal binaries,
+    /// so binaries should be given explicitly with the other prefixes, e.g., `b64:`.
+    pub hex_as_text: bool,
+    /// Whether to reject objects having several keys for the same Bencodex key, e.g., `0x61`
+    /// and `b64:YQ==`, with [`JsonDecodeErrorReason::DuplicateKey`]. If not set, the last one
+    /// wins.
+    ///
+    /// As [`Value`] keeps only the last one of the same JSON keys, [`from_json_with_options`]
+    /// cannot reject them, unlike the ones decoding from a string or a reader.
+    pub reject_duplicate_keys: bool,
+}
+
+/// URL-safe base64, accepting both padded and unpadded strings.
+const URL_SAFE_INDIFFERENT: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::URL_SAFE,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+pub(super) fn from_json_key_impl(s: &str) -> Result<BencodexKey, JsonDecodeErrorReason> {
     if let Some(rest) = s.strip_prefix("b64:") {
         let binary = base64::engine::general_purpose::STANDARD
             .decode(rest)
-            .map_err(|_| JsonDecodeError::InvalidJson)?;
+            .map_err(|_| JsonDecodeErrorReason::InvalidBase64)?;
+        Ok(BencodexKey::Binary(binary))
+    } else if let Some(rest) = s.strip_prefix("b64url:") {
+        let binary = URL_SAFE_INDIFFERENT
+            .decode(rest)
+            .map_err(|_| JsonDecodeErrorReason::InvalidBase64)?;
+        Ok(BencodexKey::Binary(binary))
+    } else if let Some(rest) = s.strip_prefix("b58:") {
+        let binary = bs58::decode(rest)
+            .into_vec()
+            .map_err(|_| JsonDecodeErrorReason::InvalidBase58)?;
         Ok(BencodexKey::Binary(binary))
     } else if let Some(rest) = s.strip_prefix("0x") {
-        let binary = hex::decode(rest).map_err(|_| JsonDecodeError::InvalidJson)?;
+        let binary = hex::decode(rest).map_err(|_| JsonDecodeErrorReason::InvalidHex)?;
         Ok(BencodexKey::Binary(binary))
     } else if let Some(rest) = s.strip_prefix('\u{FEFF}') {
         Ok(BencodexKey::Text(rest.to_string()))
     } else {
-        Err(JsonDecodeError::InvalidJson)
+        Err(JsonDecodeErrorReason::MissingTextPrefix)
+    }
+}
+
+fn from_json_key_with_options(
+    s: &str,
+    options: &JsonDecodeOptions,
+) -> Result<BencodexKey, JsonDecodeErrorReason> {
+    if options.hex_as_text && s.starts_with("0x") {
+        return Ok(BencodexKey::Text(s.to_string()));
+    }
+
+    match from_json_key_impl(s) {
+        Err(JsonDecodeErrorReason::MissingTextPrefix) if options.allow_unprefixed_texts => {
+            Ok(BencodexKey::Text(s.to_string()))
+        }
+        result => result,
+    }
+}
+
+fn from_json_str_impl(
+    s: &str,
+    options: &JsonDecodeOptions,
+) -> Result<BencodexValue, JsonDecodeErrorReason> {
+    if !s.is_empty() && s.bytes().all(|x| x.is_ascii_digit() || x == b'-') {
+        match BigInt::from_str(s) {
+            Ok(number) => return Ok(BencodexValue::Number(number)),
+            Err(_) if !options.allow_unprefixed_texts => {
+                return Err(JsonDecodeErrorReason::InvalidInteger)
+            }
+            Err(_) => {}
+        }
+    }
+
+    match from_json_key_with_options(s, options)? {
+        BencodexKey::Binary(b) => Ok(BencodexValue::Binary(b)),
+        BencodexKey::Text(t) => Ok(BencodexValue::Text(t)),
+    }
+}
+
+/// Converts a JSON number to an integer, if it is an integer fitting in 64 bits.
+pub(super) fn from_json_number_impl(n: &Number) -> Result<BigInt, JsonDecodeErrorReason> {
+    match (n.as_i64(), n.as_u64()) {
+        (Some(n), _) => Ok(BigInt::from(n)),
+        (_, Some(n)) => Ok(BigInt::from(n)),
+        _ => Err(JsonDecodeErrorReason::InvalidInteger),
+    }
+}
+
+/// Escapes a JSON object key as a reference token of [JSON Pointer].
+///
+/// [JSON Pointer]: https://www.rfc-editor.org/rfc/rfc6901#section-3
+fn escape_pointer_token(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// The reason why decoding failed and where, whose pointer is built while the failure is
+/// returned from the nested values, so nothing is spent to track it while it succeeds.
+pub(super) struct Failure {
+    reason: JsonDecodeErrorReason,
+    /// The reference tokens of the JSON pointer, from the innermost one.
+    tokens: Vec<String>,
+}
+
+impl Failure {
+    pub(super) fn new(reason: JsonDecodeErrorReason) -> Failure {
+        Failure {
+            reason,
+            tokens: vec![],
+        }
+    }
+
+    pub(super) fn at(mut self, token: String) -> Failure {
+        self.tokens.push(token);
+        self
+    }
+
+    pub(super) fn into_error(self, line: Option<usize>, column: Option<usize>) -> JsonDecodeError {
+        JsonDecodeError::InvalidJson {
+            pointer: self
+                .tokens
+                .iter()
+                .rev()
+                .map(|token| format!("/{}", escape_pointer_token(token)))
+                .collect(),
+            line,
+            column,
+            reason: self.reason,
+        }
     }
 }
 
@@ -53,48 +287,56 @@ fn from_json_key_impl(s: &str) -> Result<BencodexKey, JsonDecodeError> {
 /// In error case:
 ///
 /// ```
-/// use serde_json::Value;
-/// use bencodex::json::{ from_json, JsonDecodeError };
+/// use serde_json::{Number, Value};
+/// use bencodex::json::{ from_json, JsonDecodeError, JsonDecodeErrorReason };
 ///
 /// let result = from_json(&Value::String("0xZZ".to_string()));
 /// assert!(result.is_err());
-/// assert_eq!(result.unwrap_err(), JsonDecodeError::InvalidJson);
+/// assert_eq!(
+///     result.unwrap_err(),
+///     JsonDecodeError::InvalidJson {
+///         pointer: "".to_string(),
+///         line: None,
+///         column: None,
+///         reason: JsonDecodeErrorReason::InvalidHex,
+///     }
+/// );
 /// ```
 pub fn from_json(value: &Value) -> Result<BencodexValue, JsonDecodeError> {
-    match value {
-        Value::Null => Ok(BencodexValue::Null),
-        Value::Bool(b) => Ok(BencodexValue::Boolean(*b)),
-        Value::Number(_) => Err(JsonDecodeError::InvalidJson),
-        Value::Array(arr) => Ok(BencodexValue::List(
-            arr.iter().map(from_json).try_collect()?,
-        )),
-        Value::Object(obj) => {
-            let mut map = BencodexDictionary::new();
-            for (k, v) in obj {
-                map.insert(from_json_key_impl(k)?, from_json(v)?);
-            }
+    from_json_with_options(value, JsonDecodeOptions::default())
+}
 
-            Ok(BencodexValue::Dictionary(map))
-        }
-        Value::String(s) => {
-            if let Ok(key) = from_json_key_impl(s) {
-                Ok(match key {
-                    BencodexKey::Text(t) => BencodexValue::Text(t.to_owned()),
-                    BencodexKey::Binary(b) => BencodexValue::Binary(b.to_owned()),
-                })
-            } else if s
-                .as_bytes()
-                .iter()
-                .all(|x| x.is_ascii_digit() || *x == b'-')
-            {
-                Ok(BencodexValue::Number(
-                    s.parse().map_err(|_| JsonDecodeError::InvalidJson)?,
-                ))
-            } else {
-                Err(JsonDecodeError::InvalidJson)
-            }
-        }
+/// Decode JSON value to Bencodex value with the given options.
+///
+/// # Examples
+///
+/// ```
+/// use serde_json::json;
+/// use bencodex::BencodexValue;
+/// use bencodex::json::{ from_json_with_options, JsonDecodeOptions };
+///
+/// let options = JsonDecodeOptions {
+///   allow_numbers: true,
+///   ..Default::default()
+/// };
+/// let result = from_json_with_options(&json!([1, "2"]), options);
+/// assert_eq!(result, Ok(BencodexValue::from(vec![1, 2])));
+/// ```
+pub fn from_json_with_options(
+    value: &Value,
+    options: JsonDecodeOptions,
+) -> Result<BencodexValue, JsonDecodeError> {
+    let failure = RefCell::new(None);
+    let result = BuildSeed {
+        options: &options,
+        failure: &failure,
     }
+    .deserialize(value);
+
+    result.map_err(|_| match failure.into_inner() {
+        Some(FailureOrIo::Json(f)) => f.into_error(None, None),
+        _ => unreachable!("a Value fails to deserialize only by the seed"),
+    })
 }
 
 /// Decode JSON string to Bencodex value.
@@ -119,19 +361,559 @@ pub fn from_json(value: &Value) -> Result<BencodexValue, JsonDecodeError> {
 ///
 /// let result = from_json_string("nulll");
 /// assert!(result.is_err());
-/// assert_eq!(result.unwrap_err(), JsonDecodeError::InvalidJsonString);
+/// assert!(matches!(
+///     result.unwrap_err(),
+///     JsonDecodeError::InvalidJsonString { line: 1, column: 5, .. }
+/// ));
 /// ```
 ///
 /// In error case which return [`JsonDecodeError::InvalidJson`]:
 ///
 /// ```
-/// use bencodex::json::{ from_json_string, JsonDecodeError };
+/// use bencodex::json::{ from_json_string, JsonDecodeError, JsonDecodeErrorReason };
 ///
 /// let result = from_json_string("\"0xZZ\"");
 /// assert!(result.is_err());
-/// assert_eq!(result.unwrap_err(), JsonDecodeError::InvalidJson);
+/// assert!(matches!(
+///     result.unwrap_err(),
+///     JsonDecodeError::InvalidJson { reason: JsonDecodeErrorReason::InvalidHex, .. }
+/// ));
 /// ```
 pub fn from_json_string(s: &str) -> Result<BencodexValue, JsonDecodeError> {
-    let json = serde_json::from_str(s).map_err(|_| JsonDecodeError::InvalidJsonString)?;
-    from_json(&json)
+    from_json_string_with_options(s, JsonDecodeOptions::default())
+}
+
+/// Decode JSON string to Bencodex value with the given options.
+///
+/// # Examples
+///
+/// ```
+/// use bencodex::json::{
+///     from_json_string_with_options, JsonDecodeError, JsonDecodeErrorReason, JsonDecodeOptions,
+/// };
+///
+/// let options = JsonDecodeOptions {
+///   reject_duplicate_keys: true,
+///   ..Default::default()
+/// };
+/// let result = from_json_string_with_options(r#"{"0x61": null, "b64:YQ==": null}"#, options);
+/// assert!(matches!(
+///     result.unwrap_err(),
+///     JsonDecodeError::InvalidJson { reason: JsonDecodeErrorReason::DuplicateKey, .. }
+/// ));
+/// ```
+pub fn from_json_string_with_options(
+    s: &str,
+    options: JsonDecodeOptions,
+) -> Result<BencodexValue, JsonDecodeError> {
+    let failure = RefCell::new(None);
+    let mut deserializer = serde_json::Deserializer::from_str(s);
+    let result = BuildSeed {
+        options: &options,
+        failure: &failure,
+    }
+    .deserialize(&mut deserializer)
+    .and_then(|value| deserializer.end().map(|_| value));
+
+    result.map_err(|e| match failure.into_inner() {
+        Some(FailureOrIo::Json(f)) => f.into_error(Some(e.line()), Some(e.column())),
+        _ => invalid_json_string(&e),
+    })
+}
+
+fn invalid_json_string(e: &serde_json::Error) -> JsonDecodeError {
+    let message = e.to_string();
+    JsonDecodeError::InvalidJsonString {
+        line: e.line(),
+        column: e.column(),
+        // Strip the position serde_json appends, as it is given separately.
+        message: match message.rfind(" at line ") {
+            Some(i) => message[..i].to_string(),
+            None => message,
+        },
+    }
+}
+
+/// The failure kept aside by the seeds, because it is lost when it is converted into the
+/// error type of the deserializer.
+enum FailureOrIo {
+    Io(io::Error),
+    Json(Failure),
+}
+
+fn fail<E: de::Error>(slot: &RefCell<Option<FailureOrIo>>, failure: FailureOrIo) -> E {
+    let message = match &failure {
+        FailureOrIo::Io(e) => e.to_string(),
+        FailureOrIo::Json(f) => format!("{:?}", f.reason),
+    };
+    slot.replace(Some(failure));
+    E::custom(message)
+}
+
+/// Adds the token to the pointer of the failure returned from a nested value.
+fn fail_at<E>(slot: &RefCell<Option<FailureOrIo>>, token: impl FnOnce() -> String, e: E) -> E {
+    if let Some(FailureOrIo::Json(failure)) = slot.borrow_mut().as_mut() {
+        failure.tokens.push(token());
+    }
+    e
+}
+
+/// Fails with the reason at an object key.
+fn fail_key<E: de::Error>(
+    slot: &RefCell<Option<FailureOrIo>>,
+    key: &str,
+    reason: JsonDecodeErrorReason,
+) -> E {
+    fail(
+        slot,
+        FailureOrIo::Json(Failure::new(reason).at(key.to_string())),
+    )
+}
+
+/// Decodes an object key, failing if it is a duplicate while it should be rejected.
+fn decode_key<E: de::Error>(
+    key: &str,
+    options: &JsonDecodeOptions,
+    slot: &RefCell<Option<FailureOrIo>>,
+    contains: impl FnOnce(&BencodexKey) -> bool,
+) -> Result<BencodexKey, E> {
+    let bkey =
+        from_json_key_with_options(key, options).map_err(|reason| fail_key(slot, key, reason))?;
+    if options.reject_duplicate_keys && contains(&bkey) {
+        return Err(fail_key(slot, key, JsonDecodeErrorReason::DuplicateKey));
+    }
+
+    Ok(bkey)
+}
+
+/// The reason to reject a JSON number which is not an integer fitting in 64 bits, as
+/// [`from_json_number_impl`] gives.
+fn float_reason(options: &JsonDecodeOptions) -> JsonDecodeErrorReason {
+    if options.allow_numbers {
+        JsonDecodeErrorReason::InvalidInteger
+    } else {
+        JsonDecodeErrorReason::UnexpectedNumber
+    }
+}
+
+/// Deserializes a Bencodex JSON value into a [`BencodexValue`], from JSON text or from a
+/// [`Value`], so the rules to decode Bencodex JSON are written only here.
+struct BuildSeed<'a> {
+    options: &'a JsonDecodeOptions,
+    failure: &'a RefCell<Option<FailureOrIo>>,
+}
+
+impl<'de> DeserializeSeed<'de> for BuildSeed<'_> {
+    type Value = BencodexValue;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<BencodexValue, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for BuildSeed<'_> {
+    type Value = BencodexValue;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a Bencodex JSON value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<BencodexValue, E> {
+        Ok(BencodexValue::Null)
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<BencodexValue, E> {
+        Ok(BencodexValue::Boolean(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<BencodexValue, E> {
+        self.number(BigInt::from(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<BencodexValue, E> {
+        self.number(BigInt::from(v))
+    }
+
+    fn visit_f64<E: de::Error>(self, _: f64) -> Result<BencodexValue, E> {
+        Err(self.invalid(float_reason(self.options)))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<BencodexValue, E> {
+        from_json_str_impl(v, self.options).map_err(|r| self.invalid(r))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<BencodexValue, A::Error> {
+        let mut list = vec![];
+        while let Some(value) = seq
+            .next_element_seed(BuildSeed {
+                options: self.options,
+                failure: self.failure,
+            })
+            .map_err(|e| fail_at(self.failure, || list.len().to_string(), e))?
+        {
+            list.push(value);
+        }
+
+        Ok(BencodexValue::List(list))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<BencodexValue, A::Error> {
+        let mut dict = BencodexDictionary::new();
+        while let Some(key) = map.next_key::<std::borrow::Cow<str>>()? {
+            let bkey = decode_key(&key, self.options, self.failure, |bkey| {
+                dict.contains_key(bkey)
+            })?;
+            let value = map
+                .next_value_seed(BuildSeed {
+                    options: self.options,
+                    failure: self.failure,
+                })
+                .map_err(|e| fail_at(self.failure, || key.to_string(), e))?;
+            dict.insert(bkey, value);
+        }
+
+        Ok(BencodexValue::Dictionary(dict))
+    }
+}
+
+impl BuildSeed<'_> {
+    fn invalid<E: de::Error>(&self, reason: JsonDecodeErrorReason) -> E {
+        fail(self.failure, FailureOrIo::Json(Failure::new(reason)))
+    }
+
+    fn number<E: de::Error>(&self, number: BigInt) -> Result<BencodexValue, E> {
+        if !self.options.allow_numbers {
+            return Err(self.invalid(JsonDecodeErrorReason::UnexpectedNumber));
+        }
+        Ok(BencodexValue::Number(number))
+    }
+}
+
+/// How many bytes [`transcode_from_json`] holds back from the writer, so a dictionary whose
+/// keys turn out to be out of order can be taken back and reordered.
+const TRANSCODE_WINDOW: usize = 1 << 20;
+
+/// The output of [`WriteSeed`], which holds back the last bytes written to the writer for a
+/// while, so that they can be taken back.
+struct Spool<'w> {
+    writer: &'w mut dyn io::Write,
+    held: Vec<u8>,
+    released: usize,
+    window: usize,
+}
+
+impl<'w> Spool<'w> {
+    fn new(writer: &'w mut dyn io::Write, window: usize) -> Self {
+        Spool {
+            writer,
+            held: vec![],
+            released: 0,
+            window,
+        }
+    }
+
+    /// The number of bytes written so far.
+    fn position(&self) -> usize {
+        self.released + self.held.len()
+    }
+
+    /// Takes back the bytes written since the position, or returns [`None`] if some of them
+    /// are already released to the writer.
+    fn take_back(&mut self, position: usize) -> Option<Vec<u8>> {
+        let index = position.checked_sub(self.released)?;
+        Some(self.held.split_off(index))
+    }
+
+    /// Releases all the bytes held back to the writer.
+    fn release(&mut self) -> io::Result<()> {
+        self.writer.write_all(&self.held)?;
+        self.released += self.held.len();
+        self.held.clear();
+        Ok(())
+    }
+}
+
+impl io::Write for Spool<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.held.extend_from_slice(buf);
+        // Release in chunks of the window at least, not to move the held bytes every write.
+        let excess = self.held.len().saturating_sub(self.window);
+        if excess > self.window {
+            self.writer.write_all(&self.held[..excess])?;
+            self.held.drain(..excess);
+            self.released += excess;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Deserializes a Bencodex JSON value and writes its canonical Bencodex encoding.
+///
+/// Scalars are decoded by [`BuildSeed`], so the JSON is decoded by the same rules as
+/// [`from_json`] does.
+struct WriteSeed<'a, 'w> {
+    spool: &'a mut Spool<'w>,
+    options: &'a JsonDecodeOptions,
+    failure: &'a RefCell<Option<FailureOrIo>>,
+}
+
+impl WriteSeed<'_, '_> {
+    fn write<E: de::Error>(&self, result: io::Result<()>) -> Result<(), E> {
+        result.map_err(|e| fail(self.failure, FailureOrIo::Io(e)))
+    }
+
+    fn build(&self) -> BuildSeed<'_> {
+        BuildSeed {
+            options: self.options,
+            failure: self.failure,
+        }
+    }
+
+    fn write_scalar<E: de::Error>(self, value: Result<BencodexValue, E>) -> Result<(), E> {
+        let result = value?.encode(self.spool);
+        self.write(result)
+    }
+
+    /// Writes the value of an object entry aside, into its own buffer.
+    fn write_aside<'de, A: MapAccess<'de>>(
+        &self,
+        map: &mut A,
+        key: &str,
+    ) -> Result<Vec<u8>, A::Error> {
+        let mut value = vec![];
+        let mut spool = Spool::new(&mut value, usize::MAX);
+        map.next_value_seed(WriteSeed {
+            spool: &mut spool,
+            options: self.options,
+            failure: self.failure,
+        })
+        .map_err(|e| fail_at(self.failure, || key.to_string(), e))?;
+        let result = spool.release();
+        self.write(result)?;
+        Ok(value)
+    }
+}
+
+/// Splits the encoded dictionary written until its keys turn out to be out of order into its
+/// entries.
+fn split_entries(encoded: &[u8]) -> Result<BTreeMap<BencodexKey, Vec<u8>>, DecodeError> {
+    let mut entries = BTreeMap::new();
+    if let Some(iter) = LazyValue::new(encoded).entries()? {
+        for entry in iter {
+            let (key, value) = entry?;
+            entries.insert(key.to_bencodex(), value.encoded()?.to_vec());
+        }
+    }
+
+    Ok(entries)
+}
+
+impl<'de> DeserializeSeed<'de> for WriteSeed<'_, '_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for WriteSeed<'_, '_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a Bencodex JSON value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<(), E> {
+        let value = self.build().visit_unit();
+        self.write_scalar(value)
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<(), E> {
+        let value = self.build().visit_bool(v);
+        self.write_scalar(value)
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<(), E> {
+        let value = self.build().visit_i64(v);
+        self.write_scalar(value)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<(), E> {
+        let value = self.build().visit_u64(v);
+        self.write_scalar(value)
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<(), E> {
+        let value = self.build().visit_f64(v);
+        self.write_scalar(value)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<(), E> {
+        let value = self.build().visit_str(v);
+        self.write_scalar(value)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let result = self.spool.write_all(b"l");
+        self.write(result)?;
+        let mut index = 0;
+        while seq
+            .next_element_seed(WriteSeed {
+                spool: self.spool,
+                options: self.options,
+                failure: self.failure,
+            })
+            .map_err(|e| fail_at(self.failure, || index.to_string(), e))?
+            .is_some()
+        {
+            index += 1;
+        }
+        let result = self.spool.write_all(b"e");
+        self.write(result)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        // JSON objects are not ordered, so the entries are written as they are read while
+        // their keys are in the canonical order. Once a key is out of order, the entries
+        // written until then are taken back, and all the entries are encoded aside to be
+        // written in the canonical order after all of them are read.
+        let start = self.spool.position();
+        let result = self.spool.write_all(b"d");
+        self.write(result)?;
+        let mut last_key: Option<BencodexKey> = None;
+        let mut entries: Option<BTreeMap<BencodexKey, Vec<u8>>> = None;
+        while let Some(key) = map.next_key::<std::borrow::Cow<str>>()? {
+            let bkey = decode_key(&key, self.options, self.failure, |_| false)?;
+            if entries.is_none() && last_key.as_ref().is_some_and(|last| last >= &bkey) {
+                let Some(mut written) = self.spool.take_back(start) else {
+                    return Err(fail_key(
+                        self.failure,
+                        &key,
+                        JsonDecodeErrorReason::UnorderedKeys,
+                    ));
+                };
+                written.push(b'e');
+                let split = split_entries(&written)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+                    .and_then(|split| self.spool.write_all(b"d").map(|_| split));
+                entries = Some(split.map_err(|e| fail(self.failure, FailureOrIo::Io(e)))?);
+            }
+
+            match &mut entries {
+                None => {
+                    let result = bkey.encode(self.spool);
+                    self.write(result)?;
+                    map.next_value_seed(WriteSeed {
+                        spool: self.spool,
+                        options: self.options,
+                        failure: self.failure,
+                    })
+                    .map_err(|e| fail_at(self.failure, || key.to_string(), e))?;
+                    last_key = Some(bkey);
+                }
+                Some(entries) => {
+                    if self.options.reject_duplicate_keys && entries.contains_key(&bkey) {
+                        return Err(fail_key(
+                            self.failure,
+                            &key,
+                            JsonDecodeErrorReason::DuplicateKey,
+                        ));
+                    }
+                    let value = self.write_aside(&mut map, &key)?;
+                    entries.insert(bkey, value);
+                }
+            }
+        }
+
+        let write = |writer: &mut dyn io::Write| -> io::Result<()> {
+            for (key, value) in entries.iter().flatten() {
+                key.encode(writer)?;
+                writer.write_all(value)?;
+            }
+            writer.write_all(b"e")
+        };
+        let result = write(self.spool);
+        self.write(result)
+    }
+}
+
+/// Reads Bencodex JSON from the reader and writes its canonical Bencodex encoding into the
+/// writer, without building a [`Value`] nor a [`BencodexValue`].
+///
+/// Values are written as they are read while the keys of every object are in the canonical
+/// order. The last mebibyte written is held back from the writer, so once an object has a key
+/// out of order, its entries are taken back and kept encoded in memory until the whole
+/// object is read, to be written in the canonical order. If its entries written before are
+/// already given to the writer, it fails with [`JsonDecodeErrorReason::UnorderedKeys`]. As
+/// [`from_json`] does, the last one wins if an object has several keys for the same
+/// Bencodex key, unless [`JsonDecodeOptions::reject_duplicate_keys`] is given to
+/// [`transcode_from_json_with_options`]. The reader is not buffered, so wrap it in
+/// [`io::BufReader`] if needed.
+///
+/// If the JSON is malformed, an [`io::Error`] of [`io::ErrorKind::InvalidData`] kind is
+/// returned whose inner error is a [`JsonDecodeError`], and the Bencodex written until then is
+/// incomplete. Errors from the reader and the writer are returned as they are.
+///
+/// # Examples
+///
+/// ```
+/// use bencodex::json::transcode_from_json;
+///
+/// let json = "{\"\u{FEFF}b\": [\"1\", null], \"0x61\": true}";
+/// let mut encoded = vec![];
+/// transcode_from_json(json.as_bytes(), &mut encoded).unwrap();
+/// assert_eq!(encoded, b"d1:atu1:bli1enee");
+/// ```
+pub fn transcode_from_json<R: io::Read>(reader: R, writer: &mut dyn io::Write) -> io::Result<()> {
+    transcode_from_json_with_options(reader, writer, JsonDecodeOptions::default())
+}
+
+/// Reads JSON from the reader and writes its canonical Bencodex encoding into the writer,
+/// as [`transcode_from_json`] does, with the given options.
+///
+/// # Examples
+///
+/// ```
+/// use bencodex::json::{ transcode_from_json_with_options, JsonDecodeOptions };
+///
+/// let options = JsonDecodeOptions {
+///   allow_numbers: true,
+///   allow_unprefixed_texts: true,
+///   ..Default::default()
+/// };
+/// let mut encoded = vec![];
+/// transcode_from_json_with_options(r#"{"b": 1, "a": "x"}"#.as_bytes(), &mut encoded, options)
+///     .unwrap();
+/// assert_eq!(encoded, b"du1:au1:xu1:bi1ee");
+/// ```
+pub fn transcode_from_json_with_options<R: io::Read>(
+    reader: R,
+    writer: &mut dyn io::Write,
+    options: JsonDecodeOptions,
+) -> io::Result<()> {
+    let failure = RefCell::new(None);
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let mut spool = Spool::new(writer, TRANSCODE_WINDOW);
+    let result = WriteSeed {
+        spool: &mut spool,
+        options: &options,
+        failure: &failure,
+    }
+    .deserialize(&mut deserializer)
+    .and_then(|_| deserializer.end());
+    let result = result.and_then(|_| spool.release().map_err(serde_json::Error::io));
+
+    result.map_err(|e| match failure.into_inner() {
+        Some(FailureOrIo::Io(io)) => io,
+        Some(FailureOrIo::Json(f)) => io::Error::new(
+            io::ErrorKind::InvalidData,
+            f.into_error(Some(e.line()), Some(e.column())),
+        ),
+        None if e.is_io() => e.into(),
+        None => io::Error::new(io::ErrorKind::InvalidData, invalid_json_string(&e)),
+    })
 }
@@ -1,73 +1,344 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::BufRead;
+
 use base64::Engine;
+use num_bigint::BigInt;
+use num_traits::ToPrimitive;
 
+use crate::codec::stream::{Event, EventReader};
+use crate::path::{BencodexPath, PathSegment};
 use crate::{BencodexKey, BencodexValue};
 
+/// The overrides of [`JsonEncodeOptions::binary_encoding_overrides`] under a value, with the
+/// segments from the value to their paths.
+type Overrides<'a> = Vec<(&'a [PathSegment], BinaryEncoding)>;
+
+/// Writes the contents of a JSON string literal, escaping them as [RFC 8259] requires.
+/// Non-ASCII characters are escaped too if [`JsonEncodeOptions::ascii_only`] is set.
+///
+/// [RFC 8259]: https://www.rfc-editor.org/rfc/rfc8259#section-7
+fn write_escaped(
+    s: &str,
+    options: &JsonEncodeOptions,
+    buf: &mut dyn std::io::Write,
+) -> std::io::Result<()> {
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        let escaped: &[u8] = match c {
+            '"' => b"\\\"",
+            '\\' => b"\\\\",
+            '\u{08}' => b"\\b",
+            '\u{0C}' => b"\\f",
+            '\n' => b"\\n",
+            '\r' => b"\\r",
+            '\t' => b"\\t",
+            '\u{00}'..='\u{1F}' => &[],
+            c if options.ascii_only && !c.is_ascii() => &[],
+            _ => continue,
+        };
+
+        buf.write_all(&s.as_bytes()[start..i])?;
+        if escaped.is_empty() {
+            for unit in c.encode_utf16(&mut [0; 2]) {
+                write!(buf, "\\u{:04x}", unit)?;
+            }
+        } else {
+            buf.write_all(escaped)?;
+        }
+        start = i + c.len_utf8();
+    }
+
+    buf.write_all(&s.as_bytes()[start..])
+}
+
+/// Starts a new line indented for the given depth if [`JsonEncodeOptions::indent`] is set.
+fn write_newline(
+    depth: usize,
+    options: &JsonEncodeOptions,
+    buf: &mut dyn std::io::Write,
+) -> std::io::Result<()> {
+    if let Some(indent) = options.indent {
+        writeln!(buf)?;
+        write!(buf, "{:width$}", "", width = indent * depth)?;
+    }
+
+    Ok(())
+}
+
+fn write_key_separator(
+    options: &JsonEncodeOptions,
+    buf: &mut dyn std::io::Write,
+) -> std::io::Result<()> {
+    buf.write_all(if options.indent.is_some() {
+        b": "
+    } else {
+        b":"
+    })
+}
+
+/// Writes a [`serde_json::Value`], e.g., a JSON Schema document, with the indentation,
+/// escaping and trailing newline of [`JsonEncodeOptions`], as Bencodex JSON is written.
+#[cfg(feature = "schema")]
+pub(crate) fn write_serde_json(
+    value: &serde_json::Value,
+    options: &JsonEncodeOptions,
+    buf: &mut dyn std::io::Write,
+) -> std::io::Result<()> {
+    write_serde_json_impl(value, options, 0, buf)?;
+    if options.trailing_newline {
+        writeln!(buf)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "schema")]
+fn write_serde_json_impl(
+    value: &serde_json::Value,
+    options: &JsonEncodeOptions,
+    depth: usize,
+    buf: &mut dyn std::io::Write,
+) -> std::io::Result<()> {
+    let write_string = |s: &str, buf: &mut dyn std::io::Write| {
+        buf.write_all(b"\"")?;
+        write_escaped(s, options, buf)?;
+        buf.write_all(b"\"")
+    };
+    match value {
+        serde_json::Value::Null => buf.write_all(b"null"),
+        serde_json::Value::Bool(b) => buf.write_all(if *b { b"true" } else { b"false" }),
+        serde_json::Value::Number(n) => write!(buf, "{}", n),
+        serde_json::Value::String(s) => write_string(s, buf),
+        serde_json::Value::Array(items) => {
+            buf.write_all(b"[")?;
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    buf.write_all(b",")?;
+                }
+                write_newline(depth + 1, options, buf)?;
+                write_serde_json_impl(item, options, depth + 1, buf)?;
+            }
+            if !items.is_empty() {
+                write_newline(depth, options, buf)?;
+            }
+            buf.write_all(b"]")
+        }
+        serde_json::Value::Object(map) => {
+            buf.write_all(b"{")?;
+            for (index, (key, value)) in map.iter().enumerate() {
+                if index > 0 {
+                    buf.write_all(b",")?;
+                }
+                write_newline(depth + 1, options, buf)?;
+                write_string(key, buf)?;
+                write_key_separator(options, buf)?;
+                write_serde_json_impl(value, options, depth + 1, buf)?;
+            }
+            if !map.is_empty() {
+                write_newline(depth, options, buf)?;
+            }
+            buf.write_all(b"}")
+        }
+    }
+}
+
+fn write_binary(
+    value: &[u8],
+    encoding: BinaryEncoding,
+    buf: &mut dyn std::io::Write,
+) -> std::io::Result<()> {
+    match encoding {
+        BinaryEncoding::Base64 => buf.write_fmt(format_args!(
+            "\"b64:{}\"",
+            base64::engine::general_purpose::STANDARD.encode(value)
+        )),
+        BinaryEncoding::Base64Url => buf.write_fmt(format_args!(
+            "\"b64url:{}\"",
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(value)
+        )),
+        BinaryEncoding::Base58 => buf.write_fmt(format_args!(
+            "\"b58:{}\"",
+            bs58::encode(value).into_string()
+        )),
+        BinaryEncoding::Hex => buf.write_fmt(format_args!("\"0x{}\"", hex::encode(value))),
+    }
+}
+
+/// Returns the encoding overridden for the value itself, if any.
+fn overridden_encoding(overrides: &Overrides<'_>) -> Option<BinaryEncoding> {
+    overrides
+        .iter()
+        .find(|(segments, _)| segments.is_empty())
+        .map(|(_, encoding)| *encoding)
+}
+
+/// Returns the overrides under the subvalue at the segment matching the predicate.
+fn descend<'a>(overrides: &Overrides<'a>, matches: impl Fn(&PathSegment) -> bool) -> Overrides<'a> {
+    overrides
+        .iter()
+        .filter(|(segments, _)| segments.first().is_some_and(&matches))
+        .map(|(segments, encoding)| (&segments[1..], *encoding))
+        .collect()
+}
+
+/// Returns the string a binary key is written as, without the quotes.
+fn binary_key_string(key: &[u8], options: &JsonEncodeOptions) -> String {
+    let mut buf = vec![];
+    write_binary(key, options.binary_encoding, &mut buf).expect("writing to a Vec never fails");
+    String::from_utf8(buf[1..buf.len() - 1].to_vec()).expect("binaries are encoded in ASCII")
+}
+
+/// Returns the error for a text key written the same as a binary key of the same dictionary
+/// in [`JsonMode::Plain`], as the JSON object would have the same key twice.
+fn colliding_key_error(text: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        format!(
+            "the text key {:?} collides with a binary key in the plain mode",
+            text
+        ),
+    )
+}
+
+/// Returns the error for a key not greater than the previous key of the same dictionary, as
+/// the keys of the canonical encoding are sorted without duplicates.
+fn unordered_key_error(key: &BencodexKey) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("the key {:?} is duplicated or out of order", key),
+    )
+}
+
+/// Fails if a text key of the dictionary is written the same as one of its binary keys in
+/// [`JsonMode::Plain`].
+fn check_plain_keys(
+    dict: &BTreeMap<BencodexKey, BencodexValue>,
+    options: &JsonEncodeOptions,
+) -> std::io::Result<()> {
+    // Binary keys come first in the canonical order.
+    for key in dict.keys() {
+        match key {
+            BencodexKey::Binary(b) => {
+                let text = binary_key_string(b, options);
+                if dict.contains_key(&BencodexKey::Text(text.clone())) {
+                    return Err(colliding_key_error(&text));
+                }
+            }
+            BencodexKey::Text(_) => break,
+        }
+    }
+
+    Ok(())
+}
+
 fn to_json_key_impl(
     value: &BencodexKey,
     options: &JsonEncodeOptions,
     buf: &mut dyn std::io::Write,
 ) -> std::io::Result<()> {
     match value {
-        BencodexKey::Binary(arg0) => match options.binary_encoding {
-            BinaryEncoding::Base64 => buf.write_fmt(format_args!(
-                "\"b64:{}\"",
-                base64::engine::general_purpose::STANDARD.encode(arg0)
-            )),
-            BinaryEncoding::Hex => buf.write_fmt(format_args!("\"0x{}\"", hex::encode(arg0))),
-        },
+        BencodexKey::Binary(arg0) => write_binary(arg0, options.binary_encoding, buf),
         BencodexKey::Text(arg0) => {
-            buf.write_fmt(format_args!("\"\u{FEFF}{}\"", arg0.replace('\n', "\\n")))
+            buf.write_all(b"\"")?;
+            if options.mode == JsonMode::Bencodex {
+                write_escaped("\u{FEFF}", options, buf)?;
+            }
+            write_escaped(arg0, options, buf)?;
+            buf.write_all(b"\"")
         }
     }?;
 
     Ok(())
 }
 
+fn write_number(
+    value: &BigInt,
+    options: &JsonEncodeOptions,
+    buf: &mut dyn std::io::Write,
+) -> std::io::Result<()> {
+    match (options.mode, value.to_i64()) {
+        (JsonMode::Plain, Some(n)) => write!(buf, "{}", n),
+        _ => write!(buf, "\"{}\"", value),
+    }
+}
+
 fn to_json_value_impl(
     value: &BencodexValue,
     options: &JsonEncodeOptions,
+    overrides: &Overrides<'_>,
+    depth: usize,
     buf: &mut dyn std::io::Write,
 ) -> std::io::Result<()> {
     match value {
-        BencodexValue::Binary(arg0) => to_json_key_impl(&BencodexKey::from(arg0), options, buf),
+        BencodexValue::Binary(arg0) => write_binary(
+            arg0,
+            overridden_encoding(overrides).unwrap_or(options.binary_encoding),
+            buf,
+        ),
         BencodexValue::Text(arg0) => to_json_key_impl(&BencodexKey::from(arg0), options, buf),
         BencodexValue::Boolean(arg0) => buf
             .write_all(if *arg0 { b"true" } else { b"false" })
             .map(|_| ()),
-        BencodexValue::Number(arg0) => write!(buf, "\"{}\"", arg0),
+        BencodexValue::Number(arg0) => write_number(arg0, options, buf),
         BencodexValue::List(arg0) => {
             buf.write_all(b"[")?;
             for (i, item) in arg0.iter().enumerate() {
-                to_json_value_impl(item, options, buf)?;
+                write_newline(depth + 1, options, buf)?;
+                let overrides = descend(overrides, |segment| segment == &PathSegment::Index(i));
+                to_json_value_impl(item, options, &overrides, depth + 1, buf)?;
                 if i < arg0.len() - 1 {
                     buf.write_all(b",")?;
                 }
             }
+            if !arg0.is_empty() {
+                write_newline(depth, options, buf)?;
+            }
             buf.write_all(b"]").map(|_| ())
         }
         BencodexValue::Dictionary(arg0) => {
+            if options.mode == JsonMode::Plain {
+                check_plain_keys(arg0, options)?;
+            }
             buf.write_all(b"{")?;
             let mut iter = arg0.iter().peekable();
             while let Some((key, value)) = iter.next() {
+                write_newline(depth + 1, options, buf)?;
                 to_json_key_impl(key, options, buf)?;
-                buf.write_all(b":")?;
-                to_json_value_impl(value, options, buf)?;
+                write_key_separator(options, buf)?;
+                let overrides = descend(
+                    overrides,
+                    |segment| matches!(segment, PathSegment::Key(k) if k == key),
+                );
+                to_json_value_impl(value, options, &overrides, depth + 1, buf)?;
                 if iter.peek().is_some() {
                     buf.write_all(b",")?;
                 }
             }
-            buf.write(b"}").map(|_| ())
+            if !arg0.is_empty() {
+                write_newline(depth, options, buf)?;
+            }
+            buf.write_all(b"}")
         }
-        BencodexValue::Null => buf.write(b"null").map(|_| ()),
+        BencodexValue::Null => buf.write_all(b"null"),
     }?;
 
     Ok(())
 }
 
 /// An enum type to choose how to encode Bencodex binary type when encoding to JSON.
+///
+/// Each encoding writes binaries with its own prefix, so [`from_json`](crate::json::from_json)
+/// decodes any of them back.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
 pub enum BinaryEncoding {
+    /// Standard base64 with padding, prefixed with `b64:`.
     Base64,
+    /// Hexadecimal in lowercase, prefixed with `0x`.
     Hex,
+    /// URL-safe base64 without padding, prefixed with `b64url:`.
+    Base64Url,
+    /// Base58 with the Bitcoin alphabet, prefixed with `b58:`.
+    Base58,
 }
 
 impl Default for BinaryEncoding {
@@ -76,8 +347,61 @@ impl Default for BinaryEncoding {
     }
 }
 
+/// An enum type to choose which flavor of JSON to encode Bencodex to.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum JsonMode {
+    /// [Bencodex JSON], which is decoded back to the same value by
+    /// [`from_json`](crate::json::from_json).
+    ///
+    /// [Bencodex JSON]: https://github.com/planetarium/bencodex/blob/main/JSON.md
+    #[default]
+    Bencodex,
+    /// JSON for consumers not aware of Bencodex JSON, which loses the types of values.
+    ///
+    /// Texts are written without the `\u{FEFF}` prefix, and integers are written as JSON
+    /// numbers if they fit in [`i64`], or as strings otherwise. Binaries are written as
+    /// [`JsonEncodeOptions::binary_encoding`] says. As texts and binaries cannot be told apart,
+    /// it is decoded back with type hints by [`from_plain_json`](crate::json::from_plain_json).
+    ///
+    /// For the same reason, a text key and a binary key of a dictionary can be written as the
+    /// same JSON key, e.g., the text `"0x00"` and the binary `0x00` in [`BinaryEncoding::Hex`].
+    /// Encoding such a dictionary fails with [`std::io::ErrorKind::InvalidInput`] instead of
+    /// writing an object with duplicate keys.
+    ///
+    /// ```
+    /// use bencodex::BencodexValue;
+    /// use bencodex::json::{ to_json_with_options, JsonEncodeOptions, JsonMode };
+    ///
+    /// let value = BencodexValue::from(vec![BencodexValue::from("text"), 1.into()]);
+    /// let json = to_json_with_options(&value, JsonEncodeOptions::new().with_mode(JsonMode::Plain))
+    ///     .unwrap();
+    /// assert_eq!(json, "[\"text\",1]");
+    /// ```
+    Plain,
+}
+
+/// The [JSON Schema] of Bencodex JSON, the JSON written in [`JsonMode::Bencodex`].
+///
+/// Binaries are described in all the encodings of [`BinaryEncoding`], so any JSON written
+/// by [`to_json_with_options`] in [`JsonMode::Bencodex`] is valid against it.
+///
+/// # Examples
+///
+/// ```
+/// use bencodex::json::BENCODEX_JSON_SCHEMA;
+///
+/// let schema: serde_json::Value = serde_json::from_str(BENCODEX_JSON_SCHEMA).unwrap();
+/// assert_eq!(schema["title"], "Bencodex JSON");
+/// ```
+///
+/// [JSON Schema]: https://json-schema.org/
+pub const BENCODEX_JSON_SCHEMA: &str = include_str!("bencodex.schema.json");
+
 /// Options used by [`to_json_with_options`] when encoding Bencodex to JSON.
 ///
+/// More options may be added later, so it is built from [`JsonEncodeOptions::new`] (or
+/// [`JsonEncodeOptions::default`], which is the same) with its `with_*` methods.
+///
 /// # Examples
 ///
 /// If you want to encode binary as hexadecimal string, you can use like below:
@@ -85,9 +409,7 @@ impl Default for BinaryEncoding {
 /// ```
 /// use bencodex::json::{ JsonEncodeOptions, BinaryEncoding };
 ///
-/// JsonEncodeOptions {
-///   binary_encoding: BinaryEncoding::Hex,
-/// };
+/// JsonEncodeOptions::new().with_binary_encoding(BinaryEncoding::Hex);
 /// ```
 ///
 /// If you want to encode binary as base64 string, you can use like below:
@@ -95,9 +417,7 @@ impl Default for BinaryEncoding {
 /// ```
 /// use bencodex::json::{ JsonEncodeOptions, BinaryEncoding };
 ///
-/// JsonEncodeOptions {
-///   binary_encoding: BinaryEncoding::Base64,
-/// };
+/// JsonEncodeOptions::new().with_binary_encoding(BinaryEncoding::Base64);
 /// ```
 ///
 /// Or you can use [`JsonEncodeOptions::default`] for base64 case:
@@ -107,20 +427,330 @@ impl Default for BinaryEncoding {
 ///
 /// JsonEncodeOptions::default();
 /// ```
-#[derive(Default)]
+///
+/// If you want to read the JSON, e.g., to review diffs of it, you can pretty-print it:
+///
+/// ```
+/// use bencodex::BencodexValue;
+/// use bencodex::json::{ to_json_with_options, JsonEncodeOptions };
+///
+/// let value = BencodexValue::from(vec![BencodexValue::from("\u{AC00}"), BencodexValue::List(vec![])]);
+/// let options = JsonEncodeOptions::new()
+///     .with_indent(Some(2))
+///     .with_trailing_newline(true)
+///     .with_ascii_only(true);
+/// let json = to_json_with_options(&value, options).unwrap();
+/// assert_eq!(json, "[\n  \"\\ufeff\\uac00\",\n  []\n]\n");
+/// ```
+///
+/// If you want to encode some binaries differently from the others, you can override the
+/// encoding of them by their paths:
+///
+/// ```
+/// use bencodex::{ BencodexDictionary, BencodexPath, BencodexValue };
+/// use bencodex::json::{ to_json_with_options, JsonEncodeOptions, BinaryEncoding };
+///
+/// let mut dict = BencodexDictionary::new();
+/// dict.insert("signature".into(), vec![0xffu8, 0xfe].into());
+/// dict.insert("payload".into(), vec![0xffu8, 0xfe].into());
+/// let options = JsonEncodeOptions::new()
+///     .with_binary_encoding(BinaryEncoding::Base64)
+///     .with_binary_encoding_override(BencodexPath::root().key("signature"), BinaryEncoding::Hex)
+///     .with_binary_encoding_override(
+///         BencodexPath::root().key("payload"),
+///         BinaryEncoding::Base64Url,
+///     );
+/// let json = to_json_with_options(&BencodexValue::Dictionary(dict), options).unwrap();
+/// assert_eq!(
+///     json,
+///     "{\"\u{FEFF}payload\":\"b64url:__4\",\"\u{FEFF}signature\":\"0xfffe\"}"
+/// );
+/// ```
+#[non_exhaustive]
 pub struct JsonEncodeOptions {
     pub binary_encoding: BinaryEncoding,
+    /// Which flavor of JSON to write, [`JsonMode::Bencodex`] by default.
+    pub mode: JsonMode,
+    /// The number of spaces to indent nested elements with, each on its own line.
+    /// If [`None`], the whole JSON is written in a line without spaces.
+    pub indent: Option<usize>,
+    /// Whether to end the JSON with a newline.
+    pub trailing_newline: bool,
+    /// Whether to escape non-ASCII characters as `\uXXXX` so the JSON is ASCII only.
+    pub ascii_only: bool,
+    /// The encodings of binaries at the given paths, used instead of
+    /// [`JsonEncodeOptions::binary_encoding`]. They apply to binary values only, not to
+    /// binary keys of dictionaries.
+    pub binary_encoding_overrides: BTreeMap<BencodexPath, BinaryEncoding>,
+}
+
+impl JsonEncodeOptions {
+    /// Returns the default options: binaries in base64, [`JsonMode::Bencodex`], and the whole
+    /// JSON in a line without a trailing newline.
+    pub const fn new() -> JsonEncodeOptions {
+        JsonEncodeOptions {
+            binary_encoding: BinaryEncoding::Base64,
+            mode: JsonMode::Bencodex,
+            indent: None,
+            trailing_newline: false,
+            ascii_only: false,
+            binary_encoding_overrides: BTreeMap::new(),
+        }
+    }
+
+    /// Sets [`JsonEncodeOptions::binary_encoding`].
+    pub const fn with_binary_encoding(mut self, binary_encoding: BinaryEncoding) -> Self {
+        self.binary_encoding = binary_encoding;
+        self
+    }
+
+    /// Sets [`JsonEncodeOptions::mode`].
+    pub const fn with_mode(mut self, mode: JsonMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets [`JsonEncodeOptions::indent`].
+    pub const fn with_indent(mut self, indent: Option<usize>) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    /// Sets [`JsonEncodeOptions::trailing_newline`].
+    pub const fn with_trailing_newline(mut self, trailing_newline: bool) -> Self {
+        self.trailing_newline = trailing_newline;
+        self
+    }
+
+    /// Sets [`JsonEncodeOptions::ascii_only`].
+    pub const fn with_ascii_only(mut self, ascii_only: bool) -> Self {
+        self.ascii_only = ascii_only;
+        self
+    }
+
+    /// Adds an entry to [`JsonEncodeOptions::binary_encoding_overrides`], replacing the
+    /// encoding previously given for the same path.
+    pub fn with_binary_encoding_override(
+        mut self,
+        path: BencodexPath,
+        binary_encoding: BinaryEncoding,
+    ) -> Self {
+        self.binary_encoding_overrides.insert(path, binary_encoding);
+        self
+    }
+}
+
+impl Default for JsonEncodeOptions {
+    fn default() -> Self {
+        JsonEncodeOptions::new()
+    }
+}
+
+fn root_overrides(options: &JsonEncodeOptions) -> Overrides<'_> {
+    options
+        .binary_encoding_overrides
+        .iter()
+        .map(|(path, encoding)| (path.segments(), *encoding))
+        .collect()
 }
 
 /// Encode Bencodex to JSON with default options.
-pub fn to_json(value: &BencodexValue) -> String {
+///
+/// # Examples
+///
+/// ```
+/// use bencodex::BencodexValue;
+/// use bencodex::json::to_json;
+///
+/// let json = to_json(&BencodexValue::from("say \"hi\"\t")).unwrap();
+/// assert_eq!(json, "\"\u{FEFF}say \\\"hi\\\"\\t\"");
+/// ```
+pub fn to_json(value: &BencodexValue) -> std::io::Result<String> {
     to_json_with_options(value, JsonEncodeOptions::default())
 }
 
 /// Encode Bencodex to JSON with the given options.
-pub fn to_json_with_options(value: &BencodexValue, options: JsonEncodeOptions) -> String {
+///
+/// Texts are escaped as [RFC 8259] requires, so the result is always valid JSON.
+///
+/// [RFC 8259]: https://www.rfc-editor.org/rfc/rfc8259#section-7
+pub fn to_json_with_options(
+    value: &BencodexValue,
+    options: JsonEncodeOptions,
+) -> std::io::Result<String> {
     let mut buf: Vec<u8> = vec![];
-    to_json_value_impl(value, &options, &mut buf).ok();
+    to_json_writer(value, &mut buf, options)?;
+
+    String::from_utf8(buf).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+/// Encode Bencodex to JSON with the given options, writing it into the writer directly
+/// instead of building a [`String`].
+///
+/// # Examples
+///
+/// ```
+/// use bencodex::BencodexValue;
+/// use bencodex::json::{ to_json_writer, JsonEncodeOptions };
+///
+/// let value = BencodexValue::from(vec![BencodexValue::Null, "foo".into()]);
+/// let mut json = vec![];
+/// to_json_writer(&value, &mut json, JsonEncodeOptions::default()).unwrap();
+/// assert_eq!(json, "[null,\"\u{FEFF}foo\"]".as_bytes());
+/// ```
+pub fn to_json_writer(
+    value: &BencodexValue,
+    writer: &mut dyn std::io::Write,
+    options: JsonEncodeOptions,
+) -> std::io::Result<()> {
+    let overrides = root_overrides(&options);
+    to_json_value_impl(value, &options, &overrides, 0, writer)?;
+    if options.trailing_newline {
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+/// Reads an encoded Bencodex value from the reader and writes it as Bencodex JSON into the
+/// writer, event by event, without decoding the whole [`BencodexValue`].
+///
+/// It keeps only a binary or a text at a time in memory, so it can export values larger than
+/// the memory, besides the last key of each dictionary it is in. Dictionary entries are
+/// written in the order they are read, and the keys of each dictionary have to be in the
+/// canonical order without duplicates. If the value is malformed, or its keys are not, an
+/// [`std::io::Error`] of [`std::io::ErrorKind::InvalidData`] kind is returned as described in
+/// [`EventReader`], and the JSON written until then is incomplete.
+///
+/// # Examples
+///
+/// ```
+/// use bencodex::json::{ transcode_to_json, JsonEncodeOptions };
+///
+/// let mut json = vec![];
+/// transcode_to_json(&b"du1:ali1e1:\x01ee"[..], &mut json, JsonEncodeOptions::default()).unwrap();
+/// assert_eq!(json, "{\"\u{FEFF}a\":[\"1\",\"b64:AQ==\"]}".as_bytes());
+/// ```
+pub fn transcode_to_json<R: BufRead>(
+    reader: R,
+    writer: &mut dyn std::io::Write,
+    options: JsonEncodeOptions,
+) -> std::io::Result<()> {
+    // The number of elements written so far in each container, keys and values counted
+    // separately for dictionaries, and the overrides under each container.
+    let mut counts: Vec<(bool, usize)> = vec![];
+    let mut scopes: Vec<Overrides<'_>> = vec![];
+    // The binary keys read so far in each dictionary, as written in the plain mode, to find
+    // text keys colliding with them.
+    let mut plain_binary_keys: Vec<BTreeSet<String>> = vec![];
+    // The last key read in each dictionary, which the next key has to be greater than.
+    let mut last_keys: Vec<Option<BencodexKey>> = vec![];
+    let root = root_overrides(&options);
+    for event in EventReader::new(reader) {
+        let event = event?;
+        if event == Event::End {
+            let (is_dictionary, count) = counts.pop().expect("EventReader balances containers");
+            scopes.pop();
+            if is_dictionary {
+                plain_binary_keys.pop();
+                last_keys.pop();
+            }
+            if count > 0 {
+                write_newline(counts.len(), &options, writer)?;
+            }
+            writer.write_all(if is_dictionary { b"}" } else { b"]" })?;
+            continue;
+        }
+
+        let depth = counts.len();
+        let mut is_key = false;
+        let overrides = match (counts.last_mut(), scopes.last()) {
+            (Some((is_dictionary, count)), Some(scope)) => {
+                let index = *count;
+                if *is_dictionary && index % 2 == 1 {
+                    write_key_separator(&options, writer)?;
+                } else {
+                    if index > 0 {
+                        writer.write_all(b",")?;
+                    }
+                    write_newline(depth, &options, writer)?;
+                }
+                *count += 1;
+
+                if !*is_dictionary {
+                    descend(scope, |segment| segment == &PathSegment::Index(index))
+                } else if index % 2 == 1 {
+                    let key = last_keys.last().and_then(Option::as_ref);
+                    descend(
+                        scope,
+                        |segment| matches!((segment, key), (PathSegment::Key(k), Some(key)) if k == key),
+                    )
+                } else {
+                    is_key = true;
+                    vec![]
+                }
+            }
+            _ => root.clone(),
+        };
 
-    String::from_utf8(buf).unwrap()
+        match event {
+            Event::Null => writer.write_all(b"null")?,
+            Event::Boolean(b) => writer.write_all(if b { b"true" } else { b"false" })?,
+            Event::Number(n) => write_number(&n, &options, writer)?,
+            Event::Binary(_) | Event::Text(_) if is_key => {
+                let key = match event {
+                    Event::Binary(b) => BencodexKey::Binary(b),
+                    Event::Text(t) => BencodexKey::Text(t),
+                    _ => unreachable!(),
+                };
+                let last_key = last_keys
+                    .last_mut()
+                    .expect("keys are only read in dictionaries");
+                if last_key.as_ref().is_some_and(|last_key| last_key >= &key) {
+                    return Err(unordered_key_error(&key));
+                }
+                if options.mode == JsonMode::Plain {
+                    let keys = plain_binary_keys
+                        .last_mut()
+                        .expect("keys are only read in dictionaries");
+                    match &key {
+                        BencodexKey::Binary(b) => {
+                            keys.insert(binary_key_string(b, &options));
+                        }
+                        BencodexKey::Text(t) if keys.contains(t) => {
+                            return Err(colliding_key_error(t))
+                        }
+                        BencodexKey::Text(_) => {}
+                    }
+                }
+                to_json_key_impl(&key, &options, writer)?;
+                *last_key = Some(key);
+            }
+            Event::Binary(b) => write_binary(
+                &b,
+                overridden_encoding(&overrides).unwrap_or(options.binary_encoding),
+                writer,
+            )?,
+            Event::Text(t) => to_json_key_impl(&BencodexKey::Text(t), &options, writer)?,
+            Event::ListStart => {
+                writer.write_all(b"[")?;
+                counts.push((false, 0));
+                scopes.push(overrides);
+            }
+            Event::DictionaryStart => {
+                writer.write_all(b"{")?;
+                plain_binary_keys.push(BTreeSet::new());
+                last_keys.push(None);
+                counts.push((true, 0));
+                scopes.push(overrides);
+            }
+            Event::End => unreachable!(),
+        }
+    }
+
+    if options.trailing_newline {
+        writeln!(writer)?;
+    }
+
+    Ok(())
 }
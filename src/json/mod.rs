@@ -1,5 +1,16 @@
 mod decode;
 mod encode;
+mod plain;
 
-pub use decode::{from_json, from_json_string, JsonDecodeError};
-pub use encode::{to_json, to_json_with_options, BinaryEncoding, JsonEncodeOptions};
+pub use decode::{
+    from_json, from_json_string, from_json_string_with_options, from_json_with_options,
+    transcode_from_json, transcode_from_json_with_options, JsonDecodeError, JsonDecodeErrorReason,
+    JsonDecodeOptions,
+};
+#[cfg(feature = "schema")]
+pub(crate) use encode::write_serde_json;
+pub use encode::{
+    to_json, to_json_with_options, to_json_writer, transcode_to_json, BinaryEncoding,
+    JsonEncodeOptions, JsonMode, BENCODEX_JSON_SCHEMA,
+};
+pub use plain::{from_plain_json, PlainKeyType, PlainType};
@@ -0,0 +1,241 @@
+use std::collections::BTreeMap;
+use std::result::Result;
+use std::str::FromStr;
+
+use num_bigint::BigInt;
+use serde_json::Value;
+
+use super::decode::{from_json_key_impl, from_json_number_impl, Failure};
+use crate::{BencodexDictionary, BencodexKey, BencodexValue};
+
+use super::{JsonDecodeError, JsonDecodeErrorReason};
+
+/// A type hint to decode JSON written in [`JsonMode::Plain`](crate::json::JsonMode::Plain)
+/// back to Bencodex by [`from_plain_json`].
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum PlainType {
+    /// The type is inferred from the JSON: strings as texts, numbers as integers, arrays as
+    /// lists of [`PlainType::Any`], and objects as dictionaries of [`PlainType::Any`] with
+    /// text keys.
+    Any,
+    Null,
+    Boolean,
+    /// A JSON number, or a string of an integer which does not fit in [`i64`].
+    Integer,
+    /// A string of a binary, prefixed as [`BinaryEncoding`](crate::json::BinaryEncoding) writes.
+    Binary,
+    Text,
+    /// A list whose elements are all of the given type.
+    List(Box<PlainType>),
+    /// A dictionary whose keys are all of the given key type, and whose values are all of
+    /// the given type.
+    Dictionary(PlainKeyType, Box<PlainType>),
+    /// A dictionary with text keys, whose values are of the types given for each key, or of
+    /// [`PlainType::Any`] for the keys not given.
+    Record(BTreeMap<String, PlainType>),
+}
+
+/// The type of the keys of [`PlainType::Dictionary`].
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum PlainKeyType {
+    Binary,
+    Text,
+}
+
+fn from_plain_json_impl(value: &Value, hint: &PlainType) -> Result<BencodexValue, Failure> {
+    let unexpected = || Failure::new(JsonDecodeErrorReason::UnexpectedType);
+    match (hint, value) {
+        (PlainType::Null | PlainType::Any, Value::Null) => Ok(BencodexValue::Null),
+        (PlainType::Boolean | PlainType::Any, Value::Bool(b)) => Ok(BencodexValue::Boolean(*b)),
+        (PlainType::Integer | PlainType::Any, Value::Number(n)) => from_json_number_impl(n)
+            .map(BencodexValue::Number)
+            .map_err(Failure::new),
+        (PlainType::Integer, Value::String(s)) => BigInt::from_str(s)
+            .map(BencodexValue::Number)
+            .map_err(|_| Failure::new(JsonDecodeErrorReason::InvalidInteger)),
+        (PlainType::Binary, Value::String(s)) => match from_json_key_impl(s) {
+            Ok(BencodexKey::Binary(b)) => Ok(BencodexValue::Binary(b)),
+            Ok(BencodexKey::Text(_)) | Err(JsonDecodeErrorReason::MissingTextPrefix) => {
+                Err(unexpected())
+            }
+            Err(reason) => Err(Failure::new(reason)),
+        },
+        (PlainType::Text | PlainType::Any, Value::String(s)) => Ok(BencodexValue::Text(s.clone())),
+        (PlainType::List(_) | PlainType::Any, Value::Array(arr)) => {
+            let element = match hint {
+                PlainType::List(element) => element,
+                _ => &PlainType::Any,
+            };
+            let mut list = Vec::with_capacity(arr.len());
+            for (i, v) in arr.iter().enumerate() {
+                list.push(from_plain_json_impl(v, element).map_err(|f| f.at(i.to_string()))?);
+            }
+
+            Ok(BencodexValue::List(list))
+        }
+        (PlainType::Dictionary(..) | PlainType::Record(_) | PlainType::Any, Value::Object(obj)) => {
+            let mut map = BencodexDictionary::new();
+            for (k, v) in obj {
+                let (key, value_hint) = match hint {
+                    PlainType::Dictionary(PlainKeyType::Binary, value_hint) => {
+                        match from_json_key_impl(k) {
+                            Ok(key @ BencodexKey::Binary(_)) => (key, &**value_hint),
+                            Ok(BencodexKey::Text(_))
+                            | Err(JsonDecodeErrorReason::MissingTextPrefix) => {
+                                return Err(unexpected().at(k.clone()))
+                            }
+                            Err(reason) => return Err(Failure::new(reason).at(k.clone())),
+                        }
+                    }
+                    PlainType::Dictionary(PlainKeyType::Text, value_hint) => {
+                        (BencodexKey::Text(k.clone()), &**value_hint)
+                    }
+                    PlainType::Record(fields) => (
+                        BencodexKey::Text(k.clone()),
+                        fields.get(k).unwrap_or(&PlainType::Any),
+                    ),
+                    _ => (BencodexKey::Text(k.clone()), &PlainType::Any),
+                };
+                let value = from_plain_json_impl(v, value_hint).map_err(|f| f.at(k.clone()))?;
+                map.insert(key, value);
+            }
+
+            Ok(BencodexValue::Dictionary(map))
+        }
+        _ => Err(unexpected()),
+    }
+}
+
+/// Decode JSON written in [`JsonMode::Plain`](crate::json::JsonMode::Plain) to Bencodex
+/// value, with the given type hint.
+///
+/// As plain JSON loses the types of values, the hint tells which strings are binaries and
+/// which are texts, and which keys are binaries. Values not matching the hint are reported
+/// as [`JsonDecodeError::InvalidJson`] with [`JsonDecodeErrorReason::UnexpectedType`].
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use serde_json::json;
+/// use bencodex::{ BencodexDictionary, BencodexValue };
+/// use bencodex::json::{ from_plain_json, PlainType };
+///
+/// let hint = PlainType::Record(BTreeMap::from([
+///     ("signature".to_string(), PlainType::Binary),
+/// ]));
+/// let json = json!({ "nonce": 1, "signature": "0x0102", "memo": "0x03" });
+///
+/// let mut expected = BencodexDictionary::new();
+/// expected.insert("nonce".into(), 1.into());
+/// expected.insert("signature".into(), vec![1u8, 2].into());
+/// expected.insert("memo".into(), "0x03".into());
+/// assert_eq!(from_plain_json(&json, &hint), Ok(BencodexValue::Dictionary(expected)));
+/// ```
+pub fn from_plain_json(value: &Value, hint: &PlainType) -> Result<BencodexValue, JsonDecodeError> {
+    from_plain_json_impl(value, hint).map_err(|f| f.into_error(None, None))
+}
+
+#[cfg(test)]
+mod tests {
+    mod from_plain_json {
+        use super::super::*;
+        use crate::json::{to_json_with_options, JsonEncodeOptions, JsonMode};
+
+        fn plain_json(value: &BencodexValue) -> Value {
+            let options = JsonEncodeOptions::new().with_mode(JsonMode::Plain);
+            serde_json::from_str(&to_json_with_options(value, options).unwrap()).unwrap()
+        }
+
+        #[test]
+        fn should_roundtrip_with_hint() {
+            let mut entries = BencodexDictionary::new();
+            entries.insert(vec![1u8].into(), BencodexValue::Null);
+            entries.insert(vec![2u8].into(), true.into());
+            let mut value = BencodexDictionary::new();
+            value.insert("entries".into(), entries.into());
+            value.insert(
+                "big".into(),
+                BencodexValue::Number(BigInt::from(i64::MAX).pow(2)),
+            );
+            value.insert("small".into(), (-1).into());
+            value.insert(
+                "blobs".into(),
+                vec![BencodexValue::from(vec![0u8]), Vec::<u8>::new().into()].into(),
+            );
+            value.insert("name".into(), "0x00".into());
+            let value = BencodexValue::Dictionary(value);
+
+            let hint = PlainType::Record(BTreeMap::from([
+                (
+                    "entries".to_string(),
+                    PlainType::Dictionary(PlainKeyType::Binary, Box::new(PlainType::Any)),
+                ),
+                ("big".to_string(), PlainType::Integer),
+                (
+                    "blobs".to_string(),
+                    PlainType::List(Box::new(PlainType::Binary)),
+                ),
+            ]));
+            assert_eq!(
+                Ok(value.clone()),
+                from_plain_json(&plain_json(&value), &hint)
+            );
+        }
+
+        #[test]
+        fn should_infer_types_with_any() {
+            let json = serde_json::json!([null, true, 1, "0x01", {"a": []}]);
+            let mut dict = BencodexDictionary::new();
+            dict.insert("a".into(), BencodexValue::List(vec![]));
+            assert_eq!(
+                Ok(BencodexValue::List(vec![
+                    BencodexValue::Null,
+                    true.into(),
+                    1.into(),
+                    "0x01".into(),
+                    dict.into(),
+                ])),
+                from_plain_json(&json, &PlainType::Any)
+            );
+        }
+
+        #[test]
+        fn should_return_error_with_pointer() {
+            let hint = PlainType::List(Box::new(PlainType::Record(BTreeMap::from([(
+                "sig".to_string(),
+                PlainType::Binary,
+            )]))));
+            let error = |pointer: &str, reason| JsonDecodeError::InvalidJson {
+                pointer: pointer.to_string(),
+                line: None,
+                column: None,
+                reason,
+            };
+
+            assert_eq!(
+                Err(error("/1/sig", JsonDecodeErrorReason::UnexpectedType)),
+                from_plain_json(&serde_json::json!([{}, {"sig": "text"}]), &hint)
+            );
+            assert_eq!(
+                Err(error("/0/sig", JsonDecodeErrorReason::InvalidHex)),
+                from_plain_json(&serde_json::json!([{"sig": "0xZ"}]), &hint)
+            );
+            assert_eq!(
+                Err(error("/0/n", JsonDecodeErrorReason::InvalidInteger)),
+                from_plain_json(&serde_json::json!([{"n": 1.5}]), &hint)
+            );
+            assert_eq!(
+                Err(error("", JsonDecodeErrorReason::UnexpectedType)),
+                from_plain_json(&serde_json::json!({}), &hint)
+            );
+            assert_eq!(
+                Err(error("/a", JsonDecodeErrorReason::UnexpectedType)),
+                from_plain_json(
+                    &serde_json::json!({"a": null}),
+                    &PlainType::Dictionary(PlainKeyType::Binary, Box::new(PlainType::Any))
+                )
+            );
+        }
+    }
+}
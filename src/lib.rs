@@ -1,10 +1,44 @@
 pub mod codec;
+pub mod convert;
+pub mod diff;
+pub mod inspect;
+pub mod path;
 
 pub use codec::decode::{Decode, DecodeError};
 pub use codec::encode::Encode;
 pub use codec::types::{
-    BencodexDictionary, BencodexKey, BencodexList, BencodexValue, BENCODEX_NULL,
+    BencodexDictionary, BencodexKey, BencodexList, BencodexValue, ValueKind, BENCODEX_NULL,
 };
+pub use convert::{FromBencodex, FromBencodexError, ToBencodex};
+pub use path::{BencodexPath, PathSegment};
+
+/// The [`num_bigint`] crate, whose `BigInt` is the type of Bencodex integers, re-exported so
+/// that code using it, e.g., the code generated by `schema::generate_rust`, depends on the
+/// same version as this crate.
+pub use num_bigint;
+
+#[cfg(feature = "fingerprint")]
+pub mod fingerprint;
+#[cfg(feature = "fingerprint")]
+pub use fingerprint::Fingerprint;
 
 #[cfg(feature = "json")]
 pub mod json;
+
+#[cfg(feature = "log")]
+pub mod log;
+
+#[cfg(feature = "mmap")]
+pub mod mmap;
+
+#[cfg(feature = "offload")]
+pub mod offload;
+
+#[cfg(feature = "schema")]
+pub mod schema;
+
+#[cfg(feature = "store")]
+pub mod store;
+
+#[cfg(feature = "trie")]
+pub mod trie;
@@ -0,0 +1,459 @@
+//! An append-only file of Bencodex records.
+//!
+//! To use it, you should enable `log` feature.
+//!
+//! A [`BencodexLog`] keeps two files:
+//!
+//! - The *data file* at the given path, where each record is framed as its length in bytes
+//!   (`u32`, little-endian), the CRC-32 checksum of the record (`u32`, little-endian), and
+//!   the canonical encoding of the value itself.
+//! - The *index file* next to it, with `.idx` appended to its name, which holds the offset of
+//!   each record in the data file (`u64`, little-endian), so a record is found by its number
+//!   without scanning the data file.
+//!
+//! ```
+//! use bencodex::BencodexValue;
+//! use bencodex::log::BencodexLog;
+//!
+//! let path = std::env::temp_dir().join("bencodex-log-doctest.log");
+//! # let _ = std::fs::remove_file(&path);
+//! # let _ = std::fs::remove_file(BencodexLog::index_path(&path));
+//! let mut log = BencodexLog::open(&path).unwrap();
+//! log.append(&"first".into()).unwrap();
+//! log.append(&vec![1, 2, 3].into()).unwrap();
+//! log.sync().unwrap();
+//!
+//! let log = BencodexLog::open(&path).unwrap();
+//! assert_eq!(log.len(), 2);
+//! assert_eq!(log.get(1).unwrap(), Some(vec![1, 2, 3].into()));
+//! let values: Vec<BencodexValue> = log.iter().rev().map(Result::unwrap).collect();
+//! assert_eq!(values, vec![vec![1, 2, 3].into(), "first".into()]);
+//! # std::fs::remove_file(&path).unwrap();
+//! # std::fs::remove_file(BencodexLog::index_path(&path)).unwrap();
+//! ```
+
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{BencodexValue, Decode, Encode};
+
+/// The length of the frame before each record: its length and its checksum.
+const HEADER_LENGTH: u64 = 8;
+
+/// The length of each offset in the index file.
+const INDEX_ENTRY_LENGTH: u64 = 8;
+
+fn open_file(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+}
+
+/// Reads exactly enough bytes to fill the buffer from the offset, without moving the cursor
+/// of the file.
+#[cfg(unix)]
+fn read_exact_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+    std::os::unix::fs::FileExt::read_exact_at(file, buf, offset)
+}
+
+/// Reads exactly enough bytes to fill the buffer from the offset.
+#[cfg(windows)]
+fn read_exact_at(file: &File, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+    use std::os::windows::fs::FileExt;
+
+    while !buf.is_empty() {
+        match file.seek_read(buf, offset) {
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(read) => {
+                buf = &mut buf[read..];
+                offset += read as u64;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Reads exactly enough bytes to fill the buffer from the offset, by seeking the file first.
+///
+/// It moves the cursor of the file, which is fine as appends seek before writing.
+#[cfg(not(any(unix, windows)))]
+fn read_exact_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+    use std::io::Read;
+
+    let mut file = file;
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(buf)
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+/// An append-only file of Bencodex records. See the [module documentation](self) for its
+/// file format.
+///
+/// When opened, it recovers from a crash in the middle of an append: a torn record at the
+/// end of the data file is truncated, and complete records missing from the index are
+/// indexed again. How many bytes were truncated is reported by
+/// [`dropped_bytes`](BencodexLog::dropped_bytes).
+///
+/// Reading records only needs a shared reference, as records are read at their offsets
+/// without moving the cursors of the files.
+#[derive(Debug)]
+pub struct BencodexLog {
+    data: File,
+    index: File,
+    len: u64,
+    data_len: u64,
+    dropped_bytes: u64,
+}
+
+impl BencodexLog {
+    /// Opens the log at the given path, creating its files if they do not exist.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<BencodexLog> {
+        let path = path.as_ref();
+        let data = open_file(path)?;
+        let index = open_file(&BencodexLog::index_path(path))?;
+        let mut log = BencodexLog {
+            data_len: data.metadata()?.len(),
+            len: index.metadata()?.len() / INDEX_ENTRY_LENGTH,
+            data,
+            index,
+            dropped_bytes: 0,
+        };
+        log.dropped_bytes = log.recover()?;
+
+        Ok(log)
+    }
+
+    /// Returns the path of the index file of the log at the given path.
+    pub fn index_path<P: AsRef<Path>>(path: P) -> PathBuf {
+        let mut name = OsString::from(path.as_ref().as_os_str());
+        name.push(".idx");
+        PathBuf::from(name)
+    }
+
+    /// Returns the number of records.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of bytes of torn or corrupted records which were truncated from the
+    /// end of the data file when the log was opened. It is zero if the log was closed cleanly.
+    pub fn dropped_bytes(&self) -> u64 {
+        self.dropped_bytes
+    }
+
+    /// Appends a record, and returns its number.
+    ///
+    /// The record is not guaranteed to be on the disk until [`sync`](BencodexLog::sync).
+    pub fn append(&mut self, value: &BencodexValue) -> io::Result<u64> {
+        let mut payload = vec![];
+        value.encode(&mut payload)?;
+        let length = u32::try_from(payload.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "the record is too large"))?;
+
+        let mut frame = Vec::with_capacity(HEADER_LENGTH as usize + payload.len());
+        frame.extend_from_slice(&length.to_le_bytes());
+        frame.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+        frame.extend_from_slice(&payload);
+
+        let offset = self.data_len;
+        self.data.seek(SeekFrom::Start(offset))?;
+        self.data.write_all(&frame)?;
+        self.data_len += frame.len() as u64;
+        self.write_index_entry(self.len, offset)?;
+        self.len += 1;
+
+        Ok(self.len - 1)
+    }
+
+    /// Flushes the data file and the index file to the disk.
+    pub fn sync(&self) -> io::Result<()> {
+        self.data.sync_data()?;
+        self.index.sync_data()
+    }
+
+    /// Returns the record of the given number, or [`None`] if there is no such record.
+    ///
+    /// It fails with [`io::ErrorKind::InvalidData`] if the record is corrupted.
+    pub fn get(&self, number: u64) -> io::Result<Option<BencodexValue>> {
+        if number >= self.len {
+            return Ok(None);
+        }
+
+        let offset = self.read_index_entry(number)?;
+        let payload = self
+            .read_record(offset)?
+            .ok_or_else(|| invalid_data(format!("the record {} is corrupted", number)))?;
+        payload.decode().map(Some).map_err(invalid_data)
+    }
+
+    /// Returns an iterator over the records from the first one, which can be reversed with
+    /// [`Iterator::rev`] to iterate from the last one.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            front: 0,
+            back: self.len,
+            log: self,
+        }
+    }
+
+    fn read_index_entry(&self, number: u64) -> io::Result<u64> {
+        let mut entry = [0; INDEX_ENTRY_LENGTH as usize];
+        read_exact_at(&self.index, &mut entry, number * INDEX_ENTRY_LENGTH)?;
+        Ok(u64::from_le_bytes(entry))
+    }
+
+    fn write_index_entry(&mut self, number: u64, offset: u64) -> io::Result<()> {
+        self.index
+            .seek(SeekFrom::Start(number * INDEX_ENTRY_LENGTH))?;
+        self.index.write_all(&offset.to_le_bytes())
+    }
+
+    /// Reads the payload of the record at the offset, or returns [`None`] if the record is
+    /// incomplete, empty, or does not match its checksum.
+    ///
+    /// No record is empty, as no value is encoded in zero bytes, so an empty record is a tail
+    /// of zeros, which the file system may leave after a crash, rather than a record.
+    fn read_record(&self, offset: u64) -> io::Result<Option<Vec<u8>>> {
+        if offset + HEADER_LENGTH > self.data_len {
+            return Ok(None);
+        }
+
+        let mut header = [0; HEADER_LENGTH as usize];
+        read_exact_at(&self.data, &mut header, offset)?;
+        let length = u32::from_le_bytes(header[..4].try_into().unwrap()) as u64;
+        let checksum = u32::from_le_bytes(header[4..].try_into().unwrap());
+        if length == 0 || offset + HEADER_LENGTH + length > self.data_len {
+            return Ok(None);
+        }
+
+        let mut payload = vec![0; length as usize];
+        read_exact_at(&self.data, &mut payload, offset + HEADER_LENGTH)?;
+        if crc32fast::hash(&payload) != checksum {
+            return Ok(None);
+        }
+
+        Ok(Some(payload))
+    }
+
+    /// Recovers from a torn append, and returns the number of bytes truncated from the data
+    /// file.
+    fn recover(&mut self) -> io::Result<u64> {
+        // Drops index entries of torn records, which were indexed but not completely written.
+        let mut end = 0;
+        while self.len > 0 {
+            let offset = self.read_index_entry(self.len - 1)?;
+            if let Some(payload) = self.read_record(offset)? {
+                end = offset + HEADER_LENGTH + payload.len() as u64;
+                break;
+            }
+            self.len -= 1;
+        }
+
+        // Indexes complete records which were written but not indexed.
+        while let Some(payload) = self.read_record(end)? {
+            self.write_index_entry(self.len, end)?;
+            self.len += 1;
+            end += HEADER_LENGTH + payload.len() as u64;
+        }
+
+        let dropped_bytes = self.data_len - end;
+        self.data_len = end;
+        self.data.set_len(end)?;
+        self.index.set_len(self.len * INDEX_ENTRY_LENGTH)?;
+
+        Ok(dropped_bytes)
+    }
+}
+
+/// An iterator over the records of a [`BencodexLog`], returned by [`BencodexLog::iter`].
+#[derive(Debug)]
+pub struct Iter<'a> {
+    log: &'a BencodexLog,
+    front: u64,
+    back: u64,
+}
+
+impl<'a> Iter<'a> {
+    fn read(&mut self, number: u64) -> io::Result<BencodexValue> {
+        self.log
+            .get(number)?
+            .ok_or_else(|| invalid_data(format!("the record {} is missing", number)))
+    }
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = io::Result<BencodexValue>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+
+        self.front += 1;
+        Some(self.read(self.front - 1))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.back - self.front) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a> DoubleEndedIterator for Iter<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front >= self.back {
+            return None;
+        }
+
+        self.back -= 1;
+        Some(self.read(self.back))
+    }
+}
+
+impl<'a> ExactSizeIterator for Iter<'a> {}
+
+#[cfg(test)]
+mod tests {
+    mod bencodex_log {
+        use super::super::*;
+        use std::fs;
+
+        fn values() -> Vec<BencodexValue> {
+            vec![
+                BencodexValue::Null,
+                "foo".into(),
+                vec![1, 2, 3].into(),
+                BencodexValue::Binary(vec![0; 300]),
+            ]
+        }
+
+        fn open_with(dir: &tempfile::TempDir, values: &[BencodexValue]) -> PathBuf {
+            let path = dir.path().join("records.log");
+            let mut log = BencodexLog::open(&path).unwrap();
+            for (number, value) in values.iter().enumerate() {
+                assert_eq!(number as u64, log.append(value).unwrap());
+            }
+            log.sync().unwrap();
+            path
+        }
+
+        #[test]
+        fn should_get_appended_records() {
+            let dir = tempfile::tempdir().unwrap();
+            let path = open_with(&dir, &values());
+
+            let log = BencodexLog::open(&path).unwrap();
+            assert_eq!(4, log.len());
+            assert_eq!(0, log.dropped_bytes());
+            for (number, value) in values().into_iter().enumerate() {
+                assert_eq!(Some(value), log.get(number as u64).unwrap());
+            }
+            assert_eq!(None, log.get(4).unwrap());
+        }
+
+        #[test]
+        fn should_iterate_both_ways() {
+            let dir = tempfile::tempdir().unwrap();
+            let path = open_with(&dir, &values());
+
+            let log = BencodexLog::open(&path).unwrap();
+            let forward: Vec<_> = log.iter().map(Result::unwrap).collect();
+            assert_eq!(values(), forward);
+            let mut backward: Vec<_> = log.iter().rev().map(Result::unwrap).collect();
+            backward.reverse();
+            assert_eq!(values(), backward);
+
+            let mut iter = log.iter();
+            assert_eq!(4, iter.len());
+            assert_eq!(BencodexValue::Null, iter.next().unwrap().unwrap());
+            assert_eq!(
+                BencodexValue::Binary(vec![0; 300]),
+                iter.next_back().unwrap().unwrap()
+            );
+            assert_eq!(2, iter.len());
+        }
+
+        #[test]
+        fn should_truncate_torn_tail() {
+            let dir = tempfile::tempdir().unwrap();
+            let path = open_with(&dir, &values());
+            let data_len = fs::metadata(&path).unwrap().len();
+            let file = OpenOptions::new().write(true).open(&path).unwrap();
+            file.set_len(data_len - 10).unwrap();
+
+            let mut log = BencodexLog::open(&path).unwrap();
+            assert_eq!(3, log.len());
+            // The last record, a 300-byte binary, took 8 + 4 + 300 bytes before it was torn.
+            assert_eq!(312 - 10, log.dropped_bytes());
+            assert_eq!(None, log.get(3).unwrap());
+            assert_eq!(3, log.append(&"bar".into()).unwrap());
+            assert_eq!(Some("bar".into()), log.get(3).unwrap());
+            assert_eq!(
+                32,
+                fs::metadata(BencodexLog::index_path(&path)).unwrap().len()
+            );
+        }
+
+        #[test]
+        fn should_truncate_zero_filled_tail() {
+            let dir = tempfile::tempdir().unwrap();
+            let path = open_with(&dir, &values());
+            let data_len = fs::metadata(&path).unwrap().len();
+            let file = OpenOptions::new().write(true).open(&path).unwrap();
+            file.set_len(data_len + 64).unwrap();
+
+            let log = BencodexLog::open(&path).unwrap();
+            assert_eq!(4, log.len());
+            assert_eq!(64, log.dropped_bytes());
+            assert_eq!(data_len, fs::metadata(&path).unwrap().len());
+            let all: Vec<_> = log.iter().map(Result::unwrap).collect();
+            assert_eq!(values(), all);
+        }
+
+        #[test]
+        fn should_index_unindexed_records() {
+            let dir = tempfile::tempdir().unwrap();
+            let path = open_with(&dir, &values());
+            let index = OpenOptions::new()
+                .write(true)
+                .open(BencodexLog::index_path(&path))
+                .unwrap();
+            index.set_len(13).unwrap();
+
+            let log = BencodexLog::open(&path).unwrap();
+            assert_eq!(4, log.len());
+            assert_eq!(0, log.dropped_bytes());
+            let all: Vec<_> = log.iter().map(Result::unwrap).collect();
+            assert_eq!(values(), all);
+        }
+
+        #[test]
+        fn should_detect_corruption() {
+            let dir = tempfile::tempdir().unwrap();
+            let path = open_with(&dir, &values());
+            let mut data = fs::read(&path).unwrap();
+            // The payload of the second record, "foo", starts after the first record (n).
+            data[9 + HEADER_LENGTH as usize] = b'x';
+            fs::write(&path, data).unwrap();
+
+            let log = BencodexLog::open(&path).unwrap();
+            assert_eq!(4, log.len());
+            assert_eq!(io::ErrorKind::InvalidData, log.get(1).unwrap_err().kind());
+            assert_eq!(Some(vec![1, 2, 3].into()), log.get(2).unwrap());
+        }
+    }
+}
@@ -1,7 +1,13 @@
-use bencodex::json::{from_json, to_json_with_options, BinaryEncoding, JsonEncodeOptions};
-use bencodex::{Decode, Encode};
-use clap::Parser;
+use bencodex::json::{
+    to_json_writer, transcode_from_json_with_options, transcode_to_json, BinaryEncoding,
+    JsonDecodeOptions, JsonEncodeOptions, JsonMode,
+};
+use bencodex::mmap::MappedFile;
+use bencodex::schema::{infer_schema, to_json_schema_writer};
+use bencodex::{BencodexValue, Decode};
+use clap::{Parser, Subcommand};
 use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 
 /// A program to encode and decode between Bencodex and JSON.
@@ -10,75 +16,246 @@ use std::process::ExitCode;
 struct Args {
     /// Encode Bencodex Binary by base64 string.
     /// If not given, it will encode as hexadecimal string.
-    #[arg(short, long)]
+    #[arg(short, long, conflicts_with_all = ["base64url", "base58"], global = true)]
     base64: bool,
 
+    /// Encode Bencodex Binary by URL-safe base64 string without padding.
+    #[arg(long, conflicts_with = "base58", global = true)]
+    base64url: bool,
+
+    /// Encode Bencodex Binary by base58 string.
+    #[arg(long, global = true)]
+    base58: bool,
+
+    /// Pretty-print JSON with 2 spaces of indentation, unless --indent is given.
+    #[arg(short, long, global = true)]
+    pretty: bool,
+
+    /// Pretty-print JSON with the given number of spaces of indentation.
+    #[arg(long, value_name = "N", global = true)]
+    indent: Option<usize>,
+
+    /// Escape non-ASCII characters in JSON as \uXXXX.
+    #[arg(long, global = true)]
+    ascii: bool,
+
+    /// Do not end JSON with a newline.
+    #[arg(long, global = true)]
+    no_trailing_newline: bool,
+
+    /// Encode to plain JSON for consumers not aware of Bencodex JSON, losing the types
+    /// of values: texts without the prefix, and integers as numbers if they fit in 64 bits.
+    /// It fails if a text key and a binary key of a dictionary would be written the same.
+    #[arg(long, global = true)]
+    plain: bool,
+
     /// Decode to Bencodex from JSON.
     #[arg(short, long)]
     decode: bool,
+
+    /// Accept JSON written by hand when decoding: JSON numbers as integers, and strings
+    /// without the prefix as texts.
+    #[arg(long, requires = "decode")]
+    lenient: bool,
+
+    /// Decode strings starting with 0x as texts instead of hexadecimal binaries when
+    /// decoding, so binaries have to be given with the other prefixes, e.g., b64:.
+    #[arg(long, requires = "decode")]
+    hex_as_text: bool,
+
+    /// Fail to decode objects having several keys for the same Bencodex key.
+    #[arg(long, requires = "decode")]
+    reject_duplicate_keys: bool,
+
+    /// Read the input from the file instead of the standard input.
+    /// The file is memory-mapped, and transcoded to JSON as it is read without decoding the
+    /// whole value into memory.
+    file: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Infer the tightest schema which all the given Bencodex values are valid against,
+    /// and print it as JSON.
+    InferSchema(InferSchemaArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct InferSchemaArgs {
+    /// Take the items of each input as the values, instead of the input itself.
+    /// Each input has to be a list then.
+    #[arg(long)]
+    list: bool,
+
+    /// Print the schema as a JSON Schema document validating the Bencodex JSON of the
+    /// values, instead of the Bencodex representation of the schema.
+    #[arg(long, conflicts_with = "plain")]
+    json_schema: bool,
+
+    /// Read the inputs from the files instead of the standard input.
+    /// The files are memory-mapped instead of being read into buffers, but the values are
+    /// decoded into memory as a whole to infer the schema.
+    files: Vec<PathBuf>,
+}
+
+enum Input {
+    Stdin(Vec<u8>),
+    Mapped(MappedFile),
+}
+
+impl Input {
+    fn read(file: Option<&Path>) -> std::io::Result<Input> {
+        match file {
+            // SAFETY: the file is expected not to be modified while this program runs.
+            Some(path) => unsafe { MappedFile::open(path) }.map(Input::Mapped),
+            None => {
+                let mut buf = Vec::new();
+                std::io::stdin().read_to_end(&mut buf)?;
+                Ok(Input::Stdin(buf))
+            }
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            Input::Stdin(buf) => buf,
+            Input::Mapped(file) => file.as_bytes(),
+        }
+    }
 }
 
 fn main() -> ExitCode {
     let args = Args::parse();
 
-    if !args.decode {
+    if let Some(Command::InferSchema(infer_args)) = &args.command {
+        infer(&args, infer_args)
+    } else if !args.decode {
         encode(&args)
     } else {
-        decode()
+        decode(&args)
     }
 }
 
-fn decode() -> ExitCode {
-    let mut buf = Vec::new();
-    if let Err(err) = std::io::stdin().read_to_end(&mut buf) {
-        eprintln!("{:?}", err);
-        return ExitCode::FAILURE;
-    }
-
-    let json = match serde_json::from_slice(&buf) {
-        Ok(x) => x,
-        Err(_) => return ExitCode::FAILURE,
+fn decode(args: &Args) -> ExitCode {
+    let options = JsonDecodeOptions {
+        allow_numbers: args.lenient,
+        allow_unprefixed_texts: args.lenient,
+        hex_as_text: args.hex_as_text,
+        reject_duplicate_keys: args.reject_duplicate_keys,
     };
-
-    let bencodex_value = match from_json(&json) {
-        Ok(x) => x,
-        Err(_) => return ExitCode::FAILURE,
+    let mut stdout = std::io::BufWriter::new(std::io::stdout().lock());
+    let result = match &args.file {
+        Some(path) => Input::read(Some(path)).and_then(|input| {
+            transcode_from_json_with_options(input.as_bytes(), &mut stdout, options)
+        }),
+        None => transcode_from_json_with_options(std::io::stdin().lock(), &mut stdout, options),
     };
 
-    buf = Vec::new();
-    if bencodex_value.encode(&mut buf).is_err() {
-        return ExitCode::FAILURE;
+    match result.and_then(|_| stdout.flush()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            let _ = stdout.flush();
+            eprintln!("Failed to decode. {}", err);
+            ExitCode::FAILURE
+        }
     }
+}
 
-    let _ = std::io::stdout().write_all(&buf);
-
-    ExitCode::SUCCESS
+fn json_encode_options(args: &Args) -> JsonEncodeOptions {
+    JsonEncodeOptions::new()
+        .with_binary_encoding(if args.base64 {
+            BinaryEncoding::Base64
+        } else if args.base64url {
+            BinaryEncoding::Base64Url
+        } else if args.base58 {
+            BinaryEncoding::Base58
+        } else {
+            BinaryEncoding::Hex
+        })
+        .with_mode(if args.plain {
+            JsonMode::Plain
+        } else {
+            JsonMode::Bencodex
+        })
+        .with_indent(args.indent.or(args.pretty.then_some(2)))
+        .with_trailing_newline(!args.no_trailing_newline)
+        .with_ascii_only(args.ascii)
 }
 
 fn encode(args: &Args) -> ExitCode {
-    let mut buf = Vec::new();
-    if let Err(err) = std::io::stdin().read_to_end(&mut buf) {
-        eprintln!("{:?}", err);
-        return ExitCode::FAILURE;
-    }
+    let json_encode_options = json_encode_options(args);
+    let mut stdout = std::io::BufWriter::new(std::io::stdout().lock());
+    let result = match &args.file {
+        Some(path) => Input::read(Some(path)).and_then(|input| {
+            transcode_to_json(input.as_bytes(), &mut stdout, json_encode_options)
+        }),
+        None => transcode_to_json(std::io::stdin().lock(), &mut stdout, json_encode_options),
+    };
 
-    let decoded = match buf.decode() {
-        Ok(value) => value,
+    match result.and_then(|_| stdout.flush()) {
+        Ok(()) => ExitCode::SUCCESS,
         Err(err) => {
-            eprintln!("Failed to decode. {:?}", err);
-            return ExitCode::FAILURE;
+            let _ = stdout.flush();
+            if err.kind() == std::io::ErrorKind::InvalidData {
+                eprintln!("Failed to decode. {}", err);
+            } else {
+                eprintln!("Failed to encode. {:?}", err);
+            }
+            ExitCode::FAILURE
         }
-    };
+    }
+}
 
-    let json_encode_options = JsonEncodeOptions {
-        binary_encoding: if args.base64 {
-            BinaryEncoding::Base64
-        } else {
-            BinaryEncoding::Hex
-        },
+fn infer(args: &Args, infer_args: &InferSchemaArgs) -> ExitCode {
+    let files: Vec<Option<&Path>> = if infer_args.files.is_empty() {
+        vec![None]
+    } else {
+        infer_args
+            .files
+            .iter()
+            .map(|file| Some(file.as_path()))
+            .collect()
     };
 
-    println!("{}", to_json_with_options(&decoded, json_encode_options));
+    let mut samples = Vec::new();
+    for file in files {
+        let input = match Input::read(file) {
+            Ok(input) => input,
+            Err(err) => {
+                eprintln!("{:?}", err);
+                return ExitCode::FAILURE;
+            }
+        };
+
+        match input.as_bytes().decode() {
+            Ok(BencodexValue::List(items)) if infer_args.list => samples.extend(items),
+            Ok(value) if infer_args.list => {
+                eprintln!("Expected a list, but got {:?}.", value.kind());
+                return ExitCode::FAILURE;
+            }
+            Ok(value) => samples.push(value),
+            Err(err) => {
+                eprintln!("Failed to decode. {:?}", err);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let schema = infer_schema(&samples);
+    let options = json_encode_options(args);
+    let mut stdout = std::io::stdout().lock();
+    let result = if infer_args.json_schema {
+        to_json_schema_writer(&schema, &mut stdout, options)
+    } else {
+        to_json_writer(&schema.to_value(), &mut stdout, options)
+    };
+    if let Err(err) = result {
+        eprintln!("Failed to encode. {:?}", err);
+        return ExitCode::FAILURE;
+    }
 
     ExitCode::SUCCESS
 }
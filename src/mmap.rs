@@ -0,0 +1,111 @@
+//! Decoding Bencodex files through memory maps, without reading them into memory first.
+//!
+//! To use it, you should enable `mmap` feature.
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use crate::codec::borrowed::{decode_borrowed, BorrowedValue, LazyValue};
+use crate::DecodeError;
+
+/// A file mapped into memory, whose contents are decoded as borrowed Bencodex values.
+///
+/// Binaries and texts of the values decoded from it point into the map, so the file is
+/// paged in by the operating system as it is read, and nothing is copied. With
+/// [`MappedFile::lazy`], only the parts of the file which are navigated to are read at all.
+///
+/// # Examples
+///
+/// ```
+/// use bencodex::BencodexPath;
+/// use bencodex::mmap::MappedFile;
+///
+/// let path = std::env::temp_dir().join("bencodex-mmap-doctest.dat");
+/// std::fs::write(&path, b"du6:blocksl3:abc3:defee").unwrap();
+///
+/// // SAFETY: the file is not modified while it is mapped.
+/// let file = unsafe { MappedFile::open(&path) }.unwrap();
+/// let block = file
+///     .lazy()
+///     .get_path(&BencodexPath::root().key("blocks").index(1))
+///     .unwrap()
+///     .unwrap();
+/// assert_eq!(block.as_binary().unwrap(), Some(&b"def"[..]));
+/// # drop(file);
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct MappedFile {
+    map: Mmap,
+}
+
+impl MappedFile {
+    /// Maps the file at the given path into memory for reading.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated, by this process or others, while it is
+    /// mapped. Otherwise the values borrowed from it may change under their references, or
+    /// reading them may crash the process. See [`Mmap::map`] for details.
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> io::Result<MappedFile> {
+        let file = File::open(path)?;
+        Ok(MappedFile {
+            map: Mmap::map(&file)?,
+        })
+    }
+
+    /// Returns the contents of the file.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.map
+    }
+
+    /// Decodes the whole value in the file, borrowing its binaries and texts from the map.
+    pub fn decode(&self) -> Result<BorrowedValue<'_>, DecodeError> {
+        decode_borrowed(self.as_bytes())
+    }
+
+    /// Returns the value in the file to navigate lazily.
+    pub fn lazy(&self) -> LazyValue<'_> {
+        LazyValue::new(self.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod mapped_file {
+        use super::super::*;
+        use crate::BencodexValue;
+
+        #[test]
+        fn should_decode_file() {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("value.dat");
+            std::fs::write(&path, b"lu3:fooi1ee").unwrap();
+
+            let file = unsafe { MappedFile::open(&path) }.unwrap();
+            let value = file.decode().unwrap();
+            assert_eq!(
+                BencodexValue::from(vec![BencodexValue::from("foo"), 1.into()]),
+                value.to_bencodex()
+            );
+            if let BorrowedValue::List(list) = value {
+                if let BorrowedValue::Text(text) = list[0] {
+                    assert_eq!(file.as_bytes()[4..].as_ptr(), text.as_ptr());
+                }
+            }
+        }
+
+        #[test]
+        fn should_map_empty_file() {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("empty.dat");
+            std::fs::write(&path, b"").unwrap();
+
+            let file = unsafe { MappedFile::open(&path) }.unwrap();
+            assert_eq!(Err(DecodeError::InvalidBencodexValueError), file.decode());
+        }
+    }
+}
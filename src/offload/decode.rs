@@ -0,0 +1,322 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::result::Result;
+
+use super::encode::INDIRECT_PREFIX;
+use super::store::ValueStore;
+use crate::codec::decode::decode_impl;
+use crate::{BencodexKey, BencodexValue, DecodeError, Fingerprint};
+
+/// A Bencodex value decoded by [`decode_with_offload`], whose offloaded subvalues are not loaded yet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OffloadedValue {
+    /// A value which is neither a list nor a dictionary.
+    Value(BencodexValue),
+    List(Vec<OffloadedValue>),
+    Dictionary(BTreeMap<BencodexKey, OffloadedValue>),
+    /// A placeholder of an offloaded value, which can be loaded from a [`ValueStore`].
+    Indirect(Fingerprint),
+}
+
+impl OffloadedValue {
+    /// Returns the element at the given index if this is a list.
+    pub fn index(&self, index: usize) -> Option<&OffloadedValue> {
+        match self {
+            OffloadedValue::List(list) => list.get(index),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the given key if this is a dictionary.
+    pub fn get(&self, key: &BencodexKey) -> Option<&OffloadedValue> {
+        match self {
+            OffloadedValue::Dictionary(dict) => dict.get(key),
+            _ => None,
+        }
+    }
+
+    /// Loads the offloaded subvalues from the given store, and returns the whole value.
+    ///
+    /// Offloaded values are loaded recursively, as they can have offloaded subvalues too.
+    /// It fails with [`io::ErrorKind::NotFound`] if the store does not have an offloaded value,
+    /// and with [`io::ErrorKind::InvalidData`] if the store gives a value which fails to decode,
+    /// refers to itself, or whose fingerprint differs from the requested one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bencodex::{ BencodexKey, BencodexValue, BencodexDictionary };
+    /// use bencodex::offload::{
+    ///     decode_with_offload, encode_with_offload, MemoryValueStore, OffloadOptions, OffloadedValue,
+    /// };
+    ///
+    /// let mut dict = BencodexDictionary::new();
+    /// dict.insert("payload".into(), vec![0u8; 1024].into());
+    /// dict.insert("type".into(), "transfer".into());
+    /// let value = BencodexValue::Dictionary(dict);
+    ///
+    /// let mut store = MemoryValueStore::new();
+    /// let mut buf = vec![];
+    /// encode_with_offload(&value, &mut buf, &OffloadOptions { threshold: 64 }, &mut store).unwrap();
+    ///
+    /// let decoded = decode_with_offload(&buf).unwrap();
+    /// assert!(matches!(decoded.get(&"payload".into()), Some(OffloadedValue::Indirect(_))));
+    /// assert_eq!(
+    ///     decoded.get(&"type".into()),
+    ///     Some(&OffloadedValue::Value("transfer".into()))
+    /// );
+    /// assert_eq!(decoded.resolve(&store).unwrap(), value);
+    /// ```
+    pub fn resolve(&self, store: &dyn ValueStore) -> io::Result<BencodexValue> {
+        self.resolve_impl(store, &mut Vec::new())
+    }
+
+    // resolving has the fingerprints of the offloaded values being resolved, to refuse cycles.
+    fn resolve_impl(
+        &self,
+        store: &dyn ValueStore,
+        resolving: &mut Vec<Fingerprint>,
+    ) -> io::Result<BencodexValue> {
+        Ok(match self {
+            OffloadedValue::Value(value) => value.clone(),
+            OffloadedValue::List(list) => BencodexValue::List(
+                list.iter()
+                    .map(|item| item.resolve_impl(store, resolving))
+                    .collect::<io::Result<_>>()?,
+            ),
+            OffloadedValue::Dictionary(dict) => BencodexValue::Dictionary(
+                dict.iter()
+                    .map(|(key, value)| Ok((key.clone(), value.resolve_impl(store, resolving)?)))
+                    .collect::<io::Result<_>>()?,
+            ),
+            OffloadedValue::Indirect(fingerprint) => {
+                let encoded = store.load(fingerprint)?.ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("no offloaded value for {:?}", fingerprint),
+                    )
+                })?;
+                if resolving.contains(fingerprint) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("the offloaded value {:?} refers to itself", fingerprint),
+                    ));
+                }
+
+                let offloaded = decode_with_offload(&encoded).map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("failed to decode {:?}: {:?}", fingerprint, e),
+                    )
+                })?;
+                resolving.push(fingerprint.clone());
+                let value = offloaded.resolve_impl(store, resolving)?;
+                resolving.pop();
+                if &value.fingerprint() != fingerprint {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("the loaded value does not match {:?}", fingerprint),
+                    ));
+                }
+
+                value
+            }
+        })
+    }
+}
+
+// start must be on '*'
+fn decode_indirect_impl(
+    vector: &[u8],
+    start: usize,
+) -> Result<(OffloadedValue, usize), DecodeError> {
+    match decode_impl(vector, start + 1)? {
+        (BencodexValue::Binary(serialized), size) => Ok((
+            OffloadedValue::Indirect(Fingerprint::deserialize(&serialized)?),
+            size + 1,
+        )),
+        _ => Err(DecodeError::UnexpectedTokenError {
+            token: vector[start + 1],
+            point: start + 1,
+        }),
+    }
+}
+
+fn decode_offloaded_impl(
+    vector: &[u8],
+    start: usize,
+) -> Result<(OffloadedValue, usize), DecodeError> {
+    match vector.get(start) {
+        Some(b'l') => {
+            let mut tsize = 1;
+            let mut list = Vec::new();
+            while *vector
+                .get(start + tsize)
+                .ok_or(DecodeError::InvalidBencodexValueError)?
+                != b'e'
+            {
+                let (value, size) = decode_offloaded_impl(vector, start + tsize)?;
+                list.push(value);
+                tsize += size;
+            }
+
+            Ok((OffloadedValue::List(list), tsize + 1))
+        }
+        Some(b'd') => {
+            let mut tsize = 1;
+            let mut map = BTreeMap::new();
+            while *vector
+                .get(start + tsize)
+                .ok_or(DecodeError::InvalidBencodexValueError)?
+                != b'e'
+            {
+                let (key, size) = decode_impl(vector, start + tsize)?;
+                let key = match key {
+                    BencodexValue::Text(s) => BencodexKey::Text(s),
+                    BencodexValue::Binary(b) => BencodexKey::Binary(b),
+                    _ => return Err(DecodeError::InvalidBencodexValueError),
+                };
+                tsize += size;
+
+                let (value, size) = decode_offloaded_impl(vector, start + tsize)?;
+                if map.insert(key, value).is_some() {
+                    return Err(DecodeError::InvalidBencodexValueError);
+                }
+                tsize += size;
+            }
+
+            Ok((OffloadedValue::Dictionary(map), tsize + 1))
+        }
+        Some(&INDIRECT_PREFIX) => decode_indirect_impl(vector, start),
+        _ => decode_impl(vector, start).map(|(value, size)| (OffloadedValue::Value(value), size)),
+    }
+}
+
+/// Decodes a Bencodex value encoded by [`encode_with_offload`](super::encode_with_offload).
+///
+/// Offloaded subvalues are decoded into [`OffloadedValue::Indirect`] placeholders, which are
+/// loaded by [`OffloadedValue::resolve`] on demand. Values encoded without offloading are
+/// decoded as well.
+pub fn decode_with_offload(vector: &[u8]) -> Result<OffloadedValue, DecodeError> {
+    Ok(decode_offloaded_impl(vector, 0)?.0)
+}
+
+#[cfg(test)]
+mod tests {
+    mod decode_with_offload {
+        use super::super::*;
+        use crate::offload::{encode_with_offload, MemoryValueStore, OffloadOptions, ValueStore};
+        use crate::Encode;
+
+        #[test]
+        fn should_decode_plain_encoding() {
+            assert_eq!(
+                Ok(OffloadedValue::List(vec![
+                    OffloadedValue::Value(1.into()),
+                    OffloadedValue::Dictionary(BTreeMap::new()),
+                ])),
+                decode_with_offload(b"li1edee")
+            );
+        }
+
+        #[test]
+        fn should_return_error_with_invalid_source() {
+            assert_eq!(
+                Err(DecodeError::InvalidBencodexValueError),
+                decode_with_offload(b"li1e")
+            );
+            assert_eq!(
+                Err(DecodeError::InvalidBencodexValueError),
+                decode_with_offload(b"du1:anu1:ane")
+            );
+            assert_eq!(
+                Err(DecodeError::UnexpectedTokenError {
+                    token: b'n',
+                    point: 2
+                }),
+                decode_with_offload(b"l*ne")
+            );
+            assert_eq!(
+                Err(DecodeError::InvalidBencodexValueError),
+                decode_with_offload(b"l*2:\x05\x00e")
+            );
+        }
+
+        #[test]
+        fn should_round_trip() {
+            let large = BencodexValue::Text("a".repeat(100));
+            let mut dict = crate::BencodexDictionary::new();
+            dict.insert("large".into(), large.clone());
+            dict.insert("list".into(), vec![1, 2, 3].into());
+            let value = BencodexValue::Dictionary(dict);
+
+            let mut store = MemoryValueStore::new();
+            let mut buf = vec![];
+            let options = OffloadOptions { threshold: 64 };
+            encode_with_offload(&value, &mut buf, &options, &mut store).unwrap();
+
+            let decoded = decode_with_offload(&buf).unwrap();
+            let indirect = OffloadedValue::Indirect(large.fingerprint());
+            assert_eq!(Some(&indirect), decoded.get(&"large".into()));
+            assert_eq!(
+                Some(&OffloadedValue::Value(2.into())),
+                decoded.get(&"list".into()).and_then(|list| list.index(1))
+            );
+            assert_eq!(value, decoded.resolve(&store).unwrap());
+        }
+
+        #[test]
+        fn should_fail_to_resolve_missing_value() {
+            let large = BencodexValue::Binary(vec![0; 100]);
+            let mut buf = b"l*".to_vec();
+            large.fingerprint().serialize().encode(&mut buf).unwrap();
+            buf.push(b'e');
+
+            let decoded = decode_with_offload(&buf).unwrap();
+            let store = MemoryValueStore::new();
+            assert_eq!(
+                io::ErrorKind::NotFound,
+                decoded.resolve(&store).unwrap_err().kind()
+            );
+        }
+
+        #[test]
+        fn should_resolve_nested_offloaded_values() {
+            let large = BencodexValue::Binary(vec![0; 100]);
+            let nested: BencodexValue = vec![large.clone(), large.clone()].into();
+            let value: BencodexValue = vec![nested.clone(), large].into();
+
+            let mut store = MemoryValueStore::new();
+            let mut buf = vec![];
+            let options = OffloadOptions { threshold: 64 };
+            encode_with_offload(&value, &mut buf, &options, &mut store).unwrap();
+
+            let decoded = decode_with_offload(&buf).unwrap();
+            let indirect = OffloadedValue::Indirect(nested.fingerprint());
+            assert_eq!(Some(&indirect), decoded.index(0));
+            assert_eq!(value, decoded.resolve(&store).unwrap());
+        }
+
+        #[test]
+        fn should_fail_to_resolve_invalid_value() {
+            let large = BencodexValue::Binary(vec![0; 100]);
+            let mut buf = b"l*".to_vec();
+            large.fingerprint().serialize().encode(&mut buf).unwrap();
+            buf.push(b'e');
+            let decoded = decode_with_offload(&buf).unwrap();
+
+            let mut store = MemoryValueStore::new();
+            store.store(&large.fingerprint(), b"u3:foo").unwrap();
+            assert_eq!(
+                io::ErrorKind::InvalidData,
+                decoded.resolve(&store).unwrap_err().kind()
+            );
+
+            store.store(&large.fingerprint(), &buf).unwrap();
+            assert_eq!(
+                io::ErrorKind::InvalidData,
+                decoded.resolve(&store).unwrap_err().kind()
+            );
+        }
+    }
+}
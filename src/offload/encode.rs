@@ -0,0 +1,255 @@
+use std::io;
+
+use super::store::ValueStore;
+use crate::codec::encode::key_encoding_length;
+use crate::{BencodexValue, Encode};
+
+/// Options used by [`encode_with_offload`] to decide which values are offloaded.
+///
+/// # Examples
+///
+/// If you want to offload values whose encoding is longer than 1 KiB, you can use like below:
+///
+/// ```
+/// use bencodex::offload::OffloadOptions;
+///
+/// OffloadOptions {
+///   threshold: 1024,
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OffloadOptions {
+    /// Elements of lists and values of dictionaries whose canonical encoding is longer than
+    /// this many bytes are offloaded.
+    pub threshold: u64,
+}
+
+/// The byte which starts an offloaded value in place of the value.
+pub(crate) const INDIRECT_PREFIX: u8 = b'*';
+
+/// The length of the canonical encoding of a subvalue, and the number of its descendants.
+struct Measure {
+    length: u64,
+    descendants: usize,
+}
+
+/// Measures the value and all its subvalues at once, in pre-order.
+fn measure(value: &BencodexValue, measures: &mut Vec<Measure>) -> u64 {
+    let index = measures.len();
+    measures.push(Measure {
+        length: 0,
+        descendants: 0,
+    });
+    let length = match value {
+        BencodexValue::List(list) => {
+            2 + list.iter().map(|item| measure(item, measures)).sum::<u64>()
+        }
+        BencodexValue::Dictionary(dict) => {
+            2 + dict
+                .iter()
+                .map(|(key, value)| key_encoding_length(key) + measure(value, measures))
+                .sum::<u64>()
+        }
+        value => value.encoding_length(),
+    };
+    measures[index] = Measure {
+        length,
+        descendants: measures.len() - index - 1,
+    };
+    length
+}
+
+/// Encodes a value bottom-up, visiting its subvalues in the same pre-order as [`measure`].
+struct Offloader<'a> {
+    measures: Vec<Measure>,
+    next: usize,
+    options: &'a OffloadOptions,
+    store: &'a mut dyn ValueStore,
+}
+
+impl Offloader<'_> {
+    /// Encodes the value in place, offloading its large subvalues.
+    fn encode(&mut self, value: &BencodexValue, writer: &mut dyn io::Write) -> io::Result<()> {
+        self.next += 1;
+        match value {
+            BencodexValue::List(list) => {
+                writer.write_all(b"l")?;
+                for item in list {
+                    self.encode_element(item, writer)?;
+                }
+                writer.write_all(b"e")
+            }
+            BencodexValue::Dictionary(dict) => {
+                writer.write_all(b"d")?;
+                for (key, value) in dict {
+                    key.encode(writer)?;
+                    self.encode_element(value, writer)?;
+                }
+                writer.write_all(b"e")
+            }
+            value => value.encode(writer),
+        }
+    }
+
+    /// Encodes an element of a list or a value of a dictionary, offloading it if it is large.
+    fn encode_element(
+        &mut self,
+        value: &BencodexValue,
+        writer: &mut dyn io::Write,
+    ) -> io::Result<()> {
+        let Measure {
+            length,
+            descendants,
+        } = self.measures[self.next];
+        if length <= self.options.threshold {
+            return self.encode(value, writer);
+        }
+
+        let fingerprint = value.fingerprint();
+        if self.store.contains(&fingerprint)? {
+            self.next += 1 + descendants;
+        } else {
+            // The large subvalues of the value are offloaded on their own, and only referred
+            // to from the stored value, so no value is stored twice.
+            let mut payload = vec![];
+            self.encode(value, &mut payload)?;
+            self.store.store(&fingerprint, &payload)?;
+        }
+
+        writer.write_all(&[INDIRECT_PREFIX])?;
+        fingerprint.serialize().encode(writer)
+    }
+}
+
+/// Encodes a Bencodex value, offloading its large subvalues to the given [`ValueStore`].
+///
+/// Each element of a list and each value of a dictionary whose canonical encoding is longer
+/// than [`OffloadOptions::threshold`] is put into the store, and only its [`Fingerprint`] is
+/// written in its place: `*` followed by the serialized fingerprint as a Bencodex binary
+/// (see [`Fingerprint::serialize`]). Lists and dictionaries are offloaded bottom-up: they are
+/// stored in the same encoding, with their own large subvalues offloaded and referred to by
+/// fingerprints, so every value is stored once. The given value itself and dictionary keys
+/// are never offloaded.
+///
+/// The encoding lengths of all the subvalues are measured in a single pass, and only the
+/// offloaded values are fingerprinted. The same value is put into the store only once, even
+/// if it appears many times.
+///
+/// [`Fingerprint`]: crate::Fingerprint
+/// [`Fingerprint::serialize`]: crate::Fingerprint::serialize
+///
+/// # Examples
+///
+/// ```
+/// use bencodex::BencodexValue;
+/// use bencodex::offload::{ encode_with_offload, MemoryValueStore, OffloadOptions };
+///
+/// let payload = BencodexValue::Binary(vec![0; 1024]);
+/// let value: BencodexValue = vec![payload.clone(), payload, BencodexValue::Null].into();
+///
+/// let mut store = MemoryValueStore::new();
+/// let mut buf = vec![];
+/// encode_with_offload(&value, &mut buf, &OffloadOptions { threshold: 64 }, &mut store).unwrap();
+///
+/// assert_eq!(store.len(), 1);
+/// assert!(buf.len() < 100);
+/// ```
+pub fn encode_with_offload(
+    value: &BencodexValue,
+    writer: &mut dyn io::Write,
+    options: &OffloadOptions,
+    store: &mut dyn ValueStore,
+) -> io::Result<()> {
+    let mut measures = Vec::new();
+    measure(value, &mut measures);
+    Offloader {
+        measures,
+        next: 0,
+        options,
+        store,
+    }
+    .encode(value, writer)
+}
+
+#[cfg(test)]
+mod tests {
+    mod encode_with_offload {
+        use super::super::*;
+        use crate::offload::{MemoryValueStore, ValueStore};
+        use crate::BencodexDictionary;
+
+        #[test]
+        fn should_encode_as_usual_without_large_values() {
+            let mut dict = BencodexDictionary::new();
+            dict.insert("a".into(), vec![1, 2, 3].into());
+            dict.insert(vec![0u8].into(), "foo".into());
+            let value = BencodexValue::Dictionary(dict);
+
+            let mut store = MemoryValueStore::new();
+            let mut buf = vec![];
+            let options = OffloadOptions { threshold: 16 };
+            encode_with_offload(&value, &mut buf, &options, &mut store).unwrap();
+
+            let mut expected = vec![];
+            (&value).encode(&mut expected).unwrap();
+            assert_eq!(expected, buf);
+            assert!(store.is_empty());
+        }
+
+        #[test]
+        fn should_not_offload_root() {
+            let value = BencodexValue::Binary(vec![0; 100]);
+
+            let mut store = MemoryValueStore::new();
+            let mut buf = vec![];
+            let options = OffloadOptions { threshold: 0 };
+            encode_with_offload(&value, &mut buf, &options, &mut store).unwrap();
+
+            assert_eq!(104, buf.len());
+            assert!(store.is_empty());
+        }
+
+        #[test]
+        fn should_offload_large_values() {
+            let large = BencodexValue::Binary(vec![0; 100]);
+            let nested: BencodexValue = vec![large.clone(), BencodexValue::Null].into();
+            let value: BencodexValue = vec![large.clone(), nested.clone()].into();
+
+            let mut store = MemoryValueStore::new();
+            let mut buf = vec![];
+            let options = OffloadOptions { threshold: 64 };
+            encode_with_offload(&value, &mut buf, &options, &mut store).unwrap();
+
+            let mut large_reference = b"*".to_vec();
+            large
+                .fingerprint()
+                .serialize()
+                .encode(&mut large_reference)
+                .unwrap();
+            let mut nested_reference = b"*".to_vec();
+            nested
+                .fingerprint()
+                .serialize()
+                .encode(&mut nested_reference)
+                .unwrap();
+            assert_eq!(
+                [b"l", &large_reference[..], &nested_reference, b"e"].concat(),
+                buf
+            );
+
+            // Each payload is stored exactly once: the nested list refers to the large binary
+            // instead of containing it.
+            assert_eq!(2, store.len());
+            let mut large_encoding = vec![];
+            (&large).encode(&mut large_encoding).unwrap();
+            assert_eq!(
+                Some(large_encoding),
+                store.load(&large.fingerprint()).unwrap()
+            );
+            assert_eq!(
+                Some([b"l", &large_reference[..], b"ne"].concat()),
+                store.load(&nested.fingerprint()).unwrap()
+            );
+        }
+    }
+}
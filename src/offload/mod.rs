@@ -0,0 +1,11 @@
+//! Offloading large subvalues of Bencodex values into a [`ValueStore`] by their fingerprints.
+//!
+//! To use it, you should enable `offload` feature.
+
+mod decode;
+mod encode;
+mod store;
+
+pub use decode::{decode_with_offload, OffloadedValue};
+pub use encode::{encode_with_offload, OffloadOptions};
+pub use store::{MemoryValueStore, ValueStore};
@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+use std::io;
+
+use crate::Fingerprint;
+
+/// `ValueStore` is a trait for a storage which keeps offloaded values by their [`Fingerprint`].
+///
+/// It is given by the caller to [`encode_with_offload`](super::encode_with_offload) to put
+/// offloaded values into, and to [`OffloadedValue::resolve`](super::OffloadedValue::resolve)
+/// to load them back from. Values are kept in the encoding of
+/// [`encode_with_offload`](super::encode_with_offload), so their own large subvalues are
+/// stored separately and referred to by fingerprints.
+pub trait ValueStore {
+    /// Loads the encoding of the value of the given fingerprint. Returns [`None`] if there is
+    /// no such value.
+    fn load(&self, fingerprint: &Fingerprint) -> io::Result<Option<Vec<u8>>>;
+
+    /// Stores the encoding of a value under the given fingerprint, which is the fingerprint of
+    /// the value.
+    fn store(&mut self, fingerprint: &Fingerprint, encoded: &[u8]) -> io::Result<()>;
+
+    /// Returns whether the value of the given fingerprint is stored.
+    ///
+    /// The encoder checks it before storing a value, so the same value is stored only once.
+    fn contains(&self, fingerprint: &Fingerprint) -> io::Result<bool> {
+        Ok(self.load(fingerprint)?.is_some())
+    }
+}
+
+/// A [`ValueStore`] which keeps values in memory. It is mainly useful for tests.
+///
+/// ```
+/// use bencodex::BencodexValue;
+/// use bencodex::offload::{ MemoryValueStore, ValueStore };
+///
+/// let mut store = MemoryValueStore::new();
+/// let value = BencodexValue::from("foo");
+/// store.store(&value.fingerprint(), b"u3:foo").unwrap();
+///
+/// assert_eq!(store.load(&value.fingerprint()).unwrap(), Some(b"u3:foo".to_vec()));
+/// assert_eq!(store.len(), 1);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct MemoryValueStore {
+    values: HashMap<Fingerprint, Vec<u8>>,
+}
+
+impl MemoryValueStore {
+    pub fn new() -> MemoryValueStore {
+        MemoryValueStore::default()
+    }
+
+    /// Returns the number of stored values.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl ValueStore for MemoryValueStore {
+    fn load(&self, fingerprint: &Fingerprint) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.values.get(fingerprint).cloned())
+    }
+
+    fn store(&mut self, fingerprint: &Fingerprint, encoded: &[u8]) -> io::Result<()> {
+        self.values.insert(fingerprint.clone(), encoded.to_vec());
+        Ok(())
+    }
+
+    fn contains(&self, fingerprint: &Fingerprint) -> io::Result<bool> {
+        Ok(self.values.contains_key(fingerprint))
+    }
+}
@@ -0,0 +1,211 @@
+use std::fmt;
+
+use crate::{BencodexKey, BencodexValue};
+
+/// A step from a list or a dictionary to one of its subvalues.
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Clone)]
+pub enum PathSegment {
+    /// The element at the index of a list.
+    Index(usize),
+    /// The value of the key of a dictionary.
+    Key(BencodexKey),
+}
+
+impl From<usize> for PathSegment {
+    fn from(index: usize) -> Self {
+        PathSegment::Index(index)
+    }
+}
+
+impl From<BencodexKey> for PathSegment {
+    fn from(key: BencodexKey) -> Self {
+        PathSegment::Key(key)
+    }
+}
+
+impl fmt::Display for PathSegment {
+    /// Formats the segment as a subscript, with keys in the Bencodex Inspect notation.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathSegment::Index(index) => write!(f, "[{}]", index),
+            PathSegment::Key(key) => write!(f, "[{}]", key),
+        }
+    }
+}
+
+/// A path from a Bencodex value to one of its subvalues. The empty path refers to the value
+/// itself.
+///
+/// # Examples
+///
+/// ```
+/// use bencodex::{ BencodexDictionary, BencodexPath, BencodexValue };
+///
+/// let mut dict = BencodexDictionary::new();
+/// dict.insert("txs".into(), vec![BencodexValue::from(vec![0x01u8])].into());
+/// let value = BencodexValue::Dictionary(dict);
+///
+/// let path = BencodexPath::root().key("txs").index(0);
+/// assert_eq!(path.to_string(), r#"$["txs"][0]"#);
+/// assert_eq!(value.get_path(&path), Some(&BencodexValue::from(vec![0x01u8])));
+/// assert_eq!(value.get_path(&path.clone().index(0)), None);
+/// ```
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Clone, Default)]
+pub struct BencodexPath {
+    segments: Vec<PathSegment>,
+}
+
+impl BencodexPath {
+    /// Returns the empty path, which refers to the value itself.
+    pub fn root() -> BencodexPath {
+        BencodexPath::default()
+    }
+
+    /// Returns this path followed by the given list index.
+    pub fn index(mut self, index: usize) -> BencodexPath {
+        self.push(PathSegment::Index(index));
+        self
+    }
+
+    /// Returns this path followed by the given dictionary key.
+    pub fn key<K: Into<BencodexKey>>(mut self, key: K) -> BencodexPath {
+        self.push(PathSegment::Key(key.into()));
+        self
+    }
+
+    pub fn push(&mut self, segment: PathSegment) {
+        self.segments.push(segment);
+    }
+
+    pub fn pop(&mut self) -> Option<PathSegment> {
+        self.segments.pop()
+    }
+
+    /// Returns the path without its last segment, or [`None`] if this is the empty path.
+    pub fn parent(&self) -> Option<BencodexPath> {
+        self.segments.split_last().map(|(_, rest)| BencodexPath {
+            segments: rest.to_vec(),
+        })
+    }
+
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+}
+
+impl From<Vec<PathSegment>> for BencodexPath {
+    fn from(segments: Vec<PathSegment>) -> Self {
+        BencodexPath { segments }
+    }
+}
+
+impl FromIterator<PathSegment> for BencodexPath {
+    fn from_iter<I: IntoIterator<Item = PathSegment>>(iter: I) -> Self {
+        BencodexPath {
+            segments: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a BencodexPath {
+    type Item = &'a PathSegment;
+    type IntoIter = std::slice::Iter<'a, PathSegment>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.segments.iter()
+    }
+}
+
+impl fmt::Display for BencodexPath {
+    /// Formats the path as `$` followed by its segments, e.g., `$["txs"][0][b"\x01"]`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("$")?;
+        for segment in &self.segments {
+            write!(f, "{}", segment)?;
+        }
+        Ok(())
+    }
+}
+
+impl BencodexValue {
+    /// Returns the subvalue at the given segment, or [`None`] if there is no such subvalue.
+    pub fn get_segment(&self, segment: &PathSegment) -> Option<&BencodexValue> {
+        match (self, segment) {
+            (BencodexValue::List(list), PathSegment::Index(index)) => list.get(*index),
+            (BencodexValue::Dictionary(dict), PathSegment::Key(key)) => dict.get(key),
+            _ => None,
+        }
+    }
+
+    /// Returns the subvalue at the given path, or [`None`] if there is no such subvalue.
+    pub fn get_path(&self, path: &BencodexPath) -> Option<&BencodexValue> {
+        path.into_iter()
+            .try_fold(self, |value, segment| value.get_segment(segment))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod bencodex_path {
+        use super::super::*;
+
+        #[test]
+        fn fmt() {
+            assert_eq!("$", BencodexPath::root().to_string());
+            assert_eq!(
+                r#"$["a"][3][b"\x01\x02"]"#,
+                BencodexPath::root()
+                    .key("a")
+                    .index(3)
+                    .key(vec![1u8, 2])
+                    .to_string()
+            );
+        }
+
+        #[test]
+        fn parent() {
+            assert_eq!(None, BencodexPath::root().parent());
+            assert_eq!(
+                Some(BencodexPath::root().key("a")),
+                BencodexPath::root().key("a").index(0).parent()
+            );
+        }
+    }
+
+    mod get_path {
+        use super::super::*;
+        use crate::BencodexDictionary;
+
+        #[test]
+        fn should_walk_into_subvalues() {
+            let mut dict = BencodexDictionary::new();
+            dict.insert(vec![0u8].into(), vec![1, 2, 3].into());
+            let value = BencodexValue::Dictionary(dict);
+
+            assert_eq!(Some(&value), value.get_path(&BencodexPath::root()));
+            assert_eq!(
+                Some(&BencodexValue::from(3)),
+                value.get_path(&BencodexPath::root().key(vec![0u8]).index(2))
+            );
+        }
+
+        #[test]
+        fn should_return_none_on_mismatch() {
+            let value: BencodexValue = vec![1, 2, 3].into();
+            assert_eq!(None, value.get_path(&BencodexPath::root().index(3)));
+            assert_eq!(None, value.get_path(&BencodexPath::root().key("a")));
+            assert_eq!(
+                None,
+                value.get_path(&BencodexPath::root().index(0).index(0))
+            );
+        }
+    }
+}
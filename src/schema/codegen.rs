@@ -0,0 +1,588 @@
+use std::collections::BTreeSet;
+
+use num_bigint::BigInt;
+
+use crate::BencodexKey;
+
+use super::Schema;
+
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let",
+    "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+    "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+    "virtual", "where", "while", "yield",
+];
+
+/// Keywords which cannot be raw identifiers either.
+const RESERVED: &[&str] = &["crate", "self", "super", "Self"];
+
+const DERIVE: &str = "#[derive(PartialEq, Eq, Debug, Clone)]";
+
+/// Converts a key into a snake case identifier, e.g., `recipientAddress` into
+/// `recipient_address`, without escaping keywords.
+fn snake_case(s: &str) -> String {
+    let mut result = String::new();
+    let mut previous: Option<char> = None;
+    for c in s.chars() {
+        if c.is_ascii_alphanumeric() {
+            if c.is_ascii_uppercase()
+                && previous.is_some_and(|p| p.is_ascii_lowercase() || p.is_ascii_digit())
+            {
+                result.push('_');
+            }
+            result.push(c.to_ascii_lowercase());
+        } else if !result.is_empty() && !result.ends_with('_') {
+            result.push('_');
+        }
+        previous = Some(c);
+    }
+
+    let result = result.trim_end_matches('_');
+    match result.chars().next() {
+        None => "key".to_string(),
+        Some(c) if c.is_ascii_digit() => format!("key_{}", result),
+        Some(_) => result.to_string(),
+    }
+}
+
+fn pascal_case(snake_case: &str) -> String {
+    snake_case
+        .split('_')
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|c| c.to_ascii_uppercase())
+                .into_iter()
+                .chain(chars)
+        })
+        .collect()
+}
+
+fn field_name(key: &BencodexKey) -> String {
+    match key {
+        BencodexKey::Text(t) => snake_case(t),
+        BencodexKey::Binary(b)
+            if b.iter().all(|c| c.is_ascii_graphic()) && b.iter().any(u8::is_ascii_alphabetic) =>
+        {
+            snake_case(&String::from_utf8_lossy(b))
+        }
+        BencodexKey::Binary(b) if b.is_empty() => "key".to_string(),
+        BencodexKey::Binary(b) => format!("key_{}", hex_string(b)),
+    }
+}
+
+fn hex_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Escapes the name if it is a keyword.
+fn identifier(name: &str) -> String {
+    if RESERVED.contains(&name) {
+        format!("{}_", name)
+    } else if KEYWORDS.contains(&name) {
+        format!("r#{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Returns the expression of the key, keeping whether it is a binary or a text.
+fn key_expression(key: &BencodexKey) -> String {
+    match key {
+        BencodexKey::Text(t) => format!("::bencodex::BencodexKey::Text({:?}.to_string())", t),
+        BencodexKey::Binary(b) => format!(
+            "::bencodex::BencodexKey::Binary(b\"{}\".to_vec())",
+            b.escape_ascii()
+        ),
+    }
+}
+
+fn integer_type(min: &Option<BigInt>, max: &Option<BigInt>) -> &'static str {
+    match (min, max) {
+        (Some(min), Some(max)) if i64::try_from(min).is_ok() && i64::try_from(max).is_ok() => "i64",
+        (Some(min), Some(max)) if u64::try_from(min).is_ok() && u64::try_from(max).is_ok() => "u64",
+        _ => "::bencodex::num_bigint::BigInt",
+    }
+}
+
+fn variant_name(schema: &Schema) -> &'static str {
+    match schema {
+        Schema::Any => "Any",
+        Schema::Null => "Null",
+        Schema::Boolean => "Boolean",
+        Schema::Integer { .. } => "Integer",
+        Schema::Binary { .. } => "Binary",
+        Schema::Text { .. } => "Text",
+        Schema::List(_) => "List",
+        Schema::Tuple(_) => "Tuple",
+        Schema::Dictionary { .. } => "Dictionary",
+        Schema::Union(_) => "Union",
+    }
+}
+
+const FROM_BENCODEX_SIGNATURE: &str = "    fn from_bencodex(
+        value: &::bencodex::BencodexValue,
+    ) -> Result<Self, ::bencodex::FromBencodexError> {\n";
+
+fn to_bencodex_impl(name: &str, body: &str) -> String {
+    format!(
+        "impl ::bencodex::ToBencodex for {} {{
+    fn to_bencodex(&self) -> ::bencodex::BencodexValue {{
+{}    }}
+}}\n",
+        name, body
+    )
+}
+
+fn from_bencodex_impl(name: &str, body: &str) -> String {
+    format!(
+        "impl ::bencodex::FromBencodex for {} {{
+{}{}    }}
+}}\n",
+        name, FROM_BENCODEX_SIGNATURE, body
+    )
+}
+
+fn unique(names: &mut BTreeSet<String>, name: String) -> String {
+    let mut candidate = name.clone();
+    let mut count = 1;
+    while names.contains(&candidate) {
+        count += 1;
+        candidate = format!("{}{}", name, count);
+    }
+    names.insert(candidate.clone());
+    candidate
+}
+
+struct Generator {
+    items: Vec<String>,
+    type_names: BTreeSet<String>,
+}
+
+impl Generator {
+    /// Returns the Rust type of the schema, generating a type named after the hint if needed.
+    fn rust_type(&mut self, schema: &Schema, hint: &str) -> String {
+        match schema {
+            Schema::Any => "::bencodex::BencodexValue".to_string(),
+            Schema::Null => "()".to_string(),
+            Schema::Boolean => "bool".to_string(),
+            Schema::Integer { min, max } => integer_type(min, max).to_string(),
+            Schema::Binary { .. } => "Vec<u8>".to_string(),
+            Schema::Text { .. } => "String".to_string(),
+            Schema::List(item) => {
+                format!("Vec<{}>", self.rust_type(item, &format!("{}Item", hint)))
+            }
+            Schema::Tuple(_) | Schema::Dictionary { .. } | Schema::Union(_) => {
+                let name = unique(&mut self.type_names, hint.to_string());
+                self.generate(schema, &name);
+                name
+            }
+        }
+    }
+
+    /// Generates the type of the given name for the schema, along with the types it needs.
+    fn generate(&mut self, schema: &Schema, name: &str) {
+        // Reserves the place before the types it needs, so that types come before their parts.
+        let index = self.items.len();
+        self.items.push(String::new());
+        self.items[index] = match schema {
+            Schema::Tuple(items) => self.generate_tuple(items, name),
+            Schema::Dictionary {
+                required,
+                optional,
+                additional,
+            } => {
+                let fields = required
+                    .iter()
+                    .map(|(key, schema)| (key, schema, true))
+                    .chain(optional.iter().map(|(key, schema)| (key, schema, false)));
+                self.generate_struct(fields.collect(), additional.as_deref(), name)
+            }
+            Schema::Union(variants) => self.generate_enum(variants, name),
+            _ => format!("pub type {} = {};\n", name, self.rust_type(schema, name)),
+        };
+    }
+
+    fn generate_tuple(&mut self, items: &[Schema], name: &str) -> String {
+        let types: Vec<_> = items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| self.rust_type(item, &format!("{}{}", name, i)))
+            .collect();
+
+        let mut code = format!("{}\npub struct {}(", DERIVE, name);
+        code += &types
+            .iter()
+            .map(|t| format!("pub {}", t))
+            .collect::<Vec<_>>()
+            .join(", ");
+        code += ");\n\n";
+
+        let mut to_body = "        ::bencodex::BencodexValue::List(vec![\n".to_string();
+        for i in 0..items.len() {
+            to_body += &format!(
+                "            ::bencodex::ToBencodex::to_bencodex(&self.{}),\n",
+                i
+            );
+        }
+        to_body += "        ])\n";
+        code += &to_bencodex_impl(name, &to_body);
+        code += "\n";
+
+        let from_body = if items.is_empty() {
+            format!(
+                "        ::bencodex::convert::as_tuple(value, 0)?;\n        Ok({}())\n",
+                name
+            )
+        } else {
+            let mut body = format!(
+                "        let list = ::bencodex::convert::as_tuple(value, {})?;\n        Ok({}(\n",
+                items.len(),
+                name
+            );
+            for i in 0..items.len() {
+                body += &format!("            ::bencodex::convert::item(list, {})?,\n", i);
+            }
+            body += "        ))\n";
+            body
+        };
+        code += &from_bencodex_impl(name, &from_body);
+        code
+    }
+
+    fn generate_struct(
+        &mut self,
+        fields: Vec<(&BencodexKey, &Schema, bool)>,
+        additional: Option<&Schema>,
+        name: &str,
+    ) -> String {
+        let mut field_names = BTreeSet::new();
+        let mut code = format!("{}\npub struct {} {{\n", DERIVE, name);
+        let mut to_body = String::new();
+        let mut from_keys = String::new();
+        let mut from_fields = String::new();
+        for (index, (key, schema, required)) in fields.iter().enumerate() {
+            let field = unique(&mut field_names, field_name(key));
+            let rust_type = self.rust_type(schema, &format!("{}{}", name, pascal_case(&field)));
+            let field = identifier(&field);
+            let key_expression = key_expression(key);
+            if *required {
+                code += &format!("    /// The value of `{}`.\n", key);
+                code += &format!("    pub {}: {},\n", field, rust_type);
+                to_body += &format!(
+                    "        dict.insert(
+            {},
+            ::bencodex::ToBencodex::to_bencodex(&self.{}),
+        );\n",
+                    key_expression, field
+                );
+                from_fields += &format!(
+                    "            {}: ::bencodex::convert::required(dict, &keys[{}])?,\n",
+                    field, index
+                );
+            } else {
+                code += &format!("    /// The value of `{}`, if any.\n", key);
+                code += &format!("    pub {}: Option<{}>,\n", field, rust_type);
+                to_body += &format!(
+                    "        if let Some(value) = &self.{} {{
+            dict.insert(
+                {},
+                ::bencodex::ToBencodex::to_bencodex(value),
+            );
+        }}\n",
+                    field, key_expression
+                );
+                from_fields += &format!(
+                    "            {}: ::bencodex::convert::optional(dict, &keys[{}])?,\n",
+                    field, index
+                );
+            }
+            from_keys += &format!("            {},\n", key_expression);
+        }
+
+        let mut from_body =
+            "        let dict = ::bencodex::convert::as_dictionary(value)?;\n".to_string();
+        from_body += &format!(
+            "        let keys: [::bencodex::BencodexKey; {}] = [\n{}        ];\n",
+            fields.len(),
+            from_keys
+        );
+        match additional {
+            Some(schema) => {
+                let field = unique(&mut field_names, "additional".to_string());
+                let rust_type = self.rust_type(schema, &format!("{}{}", name, pascal_case(&field)));
+                code += "    /// The values of the other keys.\n";
+                code += &format!(
+                    "    pub {}: ::std::collections::BTreeMap<::bencodex::BencodexKey, {}>,\n",
+                    field, rust_type
+                );
+                // The other keys are inserted first not to overwrite the known keys.
+                to_body = format!(
+                    "        for (key, value) in &self.{} {{
+            dict.insert(key.clone(), ::bencodex::ToBencodex::to_bencodex(value));
+        }}\n{}",
+                    field, to_body
+                );
+                from_fields += &format!(
+                    "            {}: ::bencodex::convert::additional(dict, &keys)?,\n",
+                    field
+                );
+            }
+            None => {
+                from_body += "        ::bencodex::convert::reject_unexpected_keys(dict, &keys)?;\n";
+            }
+        }
+        code += "}\n\n";
+
+        let dict = if to_body.is_empty() {
+            "dict"
+        } else {
+            "mut dict"
+        };
+        let to_body = format!(
+            "        let {} = ::bencodex::BencodexDictionary::new();
+{}        ::bencodex::BencodexValue::Dictionary(dict)\n",
+            dict, to_body
+        );
+        code += &to_bencodex_impl(name, &to_body);
+        code += "\n";
+
+        from_body += &format!("        Ok({} {{\n{}        }})\n", name, from_fields);
+        code += &from_bencodex_impl(name, &from_body);
+        code
+    }
+
+    fn generate_enum(&mut self, variants: &[Schema], name: &str) -> String {
+        let mut variant_names = BTreeSet::new();
+        let mut code = format!("{}\npub enum {} {{\n", DERIVE, name);
+        let mut to_body = "        match self {\n".to_string();
+        let mut from_body = String::new();
+        for schema in variants {
+            let variant = unique(&mut variant_names, variant_name(schema).to_string());
+            if let Schema::Null = schema {
+                code += &format!("    {},\n", variant);
+                to_body += &format!(
+                    "            {}::{} => ::bencodex::BencodexValue::Null,\n",
+                    name, variant
+                );
+                from_body += &format!(
+                    "        match <() as ::bencodex::FromBencodex>::from_bencodex(value) {{
+            Ok(()) => return Ok({}::{}),
+            Err(e) => errors.push(e),
+        }}\n",
+                    name, variant
+                );
+            } else {
+                let rust_type = self.rust_type(schema, &format!("{}{}", name, variant));
+                code += &format!("    {}({}),\n", variant, rust_type);
+                to_body += &format!(
+                    "            {}::{}(value) => ::bencodex::ToBencodex::to_bencodex(value),\n",
+                    name, variant
+                );
+                from_body += &format!(
+                    "        match ::bencodex::FromBencodex::from_bencodex(value) {{
+            Ok(value) => return Ok({}::{}(value)),
+            Err(e) => errors.push(e),
+        }}\n",
+                    name, variant
+                );
+            }
+        }
+        code += "}\n\n";
+
+        if variants.is_empty() {
+            code += &to_bencodex_impl(name, "        match *self {}\n");
+            code += "\n";
+            code += &from_bencodex_impl(
+                name,
+                "        let _ = value;
+        Err(::bencodex::convert::no_matching_variant(Vec::new()))\n",
+            );
+        } else {
+            to_body += "        }\n";
+            code += &to_bencodex_impl(name, &to_body);
+            code += "\n";
+            from_body = format!(
+                "        let mut errors = Vec::new();
+{}        Err(::bencodex::convert::no_matching_variant(errors))\n",
+                from_body
+            );
+            code += &from_bencodex_impl(name, &from_body);
+        }
+        code
+    }
+}
+
+/// Generates the Rust source code of a type named `name` for the values valid against the
+/// schema, along with the types it needs, which implement
+/// [`ToBencodex`](crate::ToBencodex) and [`FromBencodex`](crate::FromBencodex).
+///
+/// Dictionaries are generated as structs, with a field per key: `Option`s for optional keys,
+/// and a `BTreeMap` named `additional` for the other keys if allowed. The fields are named
+/// after the keys in snake case, and they are converted from and into exactly the keys of
+/// the schema, binary keys into binary keys and text keys into text keys, even if a binary
+/// key and a text key are spelled the same. Tuples are generated as tuple structs, and
+/// unions as enums with a variant per schema, named after its type. The other types are
+/// named after the types they belong to, e.g., `TransferMemo` for the `memo` key of
+/// `Transfer`.
+///
+/// The other schemas are mapped to existing types: [`BencodexValue`](crate::BencodexValue)
+/// for any value, `()` for null, `bool`, `i64` or `u64` for integers bounded within them and
+/// [`num_bigint::BigInt`](crate::num_bigint::BigInt) for other integers, `Vec<u8>` for binaries, `String` for texts, and
+/// `Vec` for lists. The bounds and the patterns are not checked by the generated code, so
+/// check them with [`validate`](super::validate) if needed.
+///
+/// The code refers to the items by their absolute paths, e.g., `::bencodex::ToBencodex`, so
+/// that it can be included anywhere, e.g., from a build script:
+///
+/// ```no_run
+/// // build.rs
+/// use bencodex::Decode;
+/// use bencodex::schema::{ generate_rust, Schema };
+///
+/// let value = std::fs::read("schemas/transfer.dat").unwrap().decode().unwrap();
+/// let schema = Schema::from_value(&value).unwrap();
+/// let out_dir = std::env::var("OUT_DIR").unwrap();
+/// std::fs::write(format!("{}/transfer.rs", out_dir), generate_rust(&schema, "Transfer")).unwrap();
+///
+/// // src/lib.rs
+/// // include!(concat!(env!("OUT_DIR"), "/transfer.rs"));
+/// ```
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use bencodex::schema::{ generate_rust, Schema };
+///
+/// let schema = Schema::Dictionary {
+///     required: BTreeMap::from([("amount".into(), Schema::Integer {
+///         min: Some(0.into()),
+///         max: Some(u32::MAX.into()),
+///     })]),
+///     optional: BTreeMap::from([(b"memo".to_vec().into(), Schema::Text { pattern: None })]),
+///     additional: None,
+/// };
+/// let code = generate_rust(&schema, "Transfer");
+/// assert!(code.contains("pub struct Transfer {"));
+/// assert!(code.contains("pub amount: i64,"));
+/// assert!(code.contains("pub memo: Option<String>,"));
+/// assert!(code.contains(r#"::bencodex::BencodexKey::Text("amount".to_string())"#));
+/// assert!(code.contains(r#"::bencodex::BencodexKey::Binary(b"memo".to_vec())"#));
+/// ```
+pub fn generate_rust(schema: &Schema, name: &str) -> String {
+    let mut generator = Generator {
+        items: Vec::new(),
+        type_names: BTreeSet::from([name.to_string()]),
+    };
+    generator.generate(schema, name);
+
+    let mut code = "// Generated by bencodex::schema::generate_rust. Do not edit.\n".to_string();
+    for item in generator.items {
+        code += "\n";
+        code += &item;
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    mod field_name {
+        use super::super::*;
+
+        #[test]
+        fn should_convert_keys_into_snake_case() {
+            assert_eq!("recipient_address", field_name(&"recipientAddress".into()));
+            assert_eq!("tx_id", field_name(&"tx-id".into()));
+            assert_eq!("key_0", field_name(&"0".into()));
+            assert_eq!("key", field_name(&"".into()));
+            assert_eq!("a", field_name(&b"a".to_vec().into()));
+            assert_eq!("key_00ff", field_name(&vec![0x00u8, 0xff].into()));
+        }
+    }
+
+    mod identifier {
+        use super::super::*;
+
+        #[test]
+        fn should_escape_keywords() {
+            assert_eq!("amount", identifier("amount"));
+            assert_eq!("r#type", identifier("type"));
+            assert_eq!("self_", identifier("self"));
+        }
+    }
+
+    mod generate_rust {
+        use std::collections::BTreeMap;
+
+        use super::super::*;
+
+        #[test]
+        fn should_generate_type_alias_for_primitive_schema() {
+            let code = generate_rust(
+                &Schema::List(Box::new(Schema::Integer {
+                    min: Some(0.into()),
+                    max: Some(u64::MAX.into()),
+                })),
+                "Amounts",
+            );
+            assert!(code.contains("pub type Amounts = Vec<u64>;\n"));
+            let code = generate_rust(
+                &Schema::Integer {
+                    min: None,
+                    max: None,
+                },
+                "Amount",
+            );
+            assert!(code.contains("pub type Amount = ::bencodex::num_bigint::BigInt;\n"));
+        }
+
+        #[test]
+        fn should_keep_binary_and_text_keys_apart() {
+            let schema = Schema::Dictionary {
+                required: BTreeMap::from([
+                    (b"a".to_vec().into(), Schema::Null),
+                    ("a".into(), Schema::Boolean),
+                ]),
+                optional: BTreeMap::new(),
+                additional: Some(Box::new(Schema::Any)),
+            };
+            let code = generate_rust(&schema, "Pair");
+            assert!(code.contains("    /// The value of `b\"\\x61\"`.\n    pub a: (),\n"));
+            assert!(code.contains("    /// The value of `\"a\"`.\n    pub a2: bool,\n"));
+            assert!(code.contains(
+                "pub additional: ::std::collections::BTreeMap<::bencodex::BencodexKey, \
+                 ::bencodex::BencodexValue>,"
+            ));
+            assert!(code.contains("::bencodex::BencodexKey::Binary(b\"a\".to_vec())"));
+            assert!(code.contains("::bencodex::BencodexKey::Text(\"a\".to_string())"));
+        }
+
+        #[test]
+        fn should_name_nested_types_after_parents() {
+            let schema = Schema::Union(vec![
+                Schema::Null,
+                Schema::Tuple(vec![Schema::Boolean]),
+                Schema::Dictionary {
+                    required: BTreeMap::new(),
+                    optional: BTreeMap::new(),
+                    additional: None,
+                },
+                Schema::Dictionary {
+                    required: BTreeMap::new(),
+                    optional: BTreeMap::new(),
+                    additional: None,
+                },
+            ]);
+            let code = generate_rust(&schema, "Payload");
+            assert!(code.contains(
+                "pub enum Payload {\n    Null,\n    Tuple(PayloadTuple),\n    \
+                 Dictionary(PayloadDictionary),\n    Dictionary2(PayloadDictionary2),\n}\n"
+            ));
+            assert!(code.contains("pub struct PayloadTuple(pub bool);\n"));
+            assert!(code.find("pub enum Payload") < code.find("pub struct PayloadTuple"));
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::BTreeMap;
+
+use crate::{BencodexKey, BencodexValue, ValueKind};
+
+use super::Schema;
+
+fn infer_dictionary(dicts: &[&BTreeMap<BencodexKey, BencodexValue>]) -> Schema {
+    let mut values: BTreeMap<&BencodexKey, Vec<&BencodexValue>> = BTreeMap::new();
+    for dict in dicts {
+        for (key, value) in dict.iter() {
+            values.entry(key).or_default().push(value);
+        }
+    }
+
+    let mut required = BTreeMap::new();
+    let mut optional = BTreeMap::new();
+    for (key, values) in values {
+        let schemas = if values.len() == dicts.len() {
+            &mut required
+        } else {
+            &mut optional
+        };
+        schemas.insert(key.clone(), infer(&values));
+    }
+
+    Schema::Dictionary {
+        required,
+        optional,
+        additional: None,
+    }
+}
+
+fn infer_kind(kind: ValueKind, samples: &[&BencodexValue]) -> Schema {
+    match kind {
+        ValueKind::Null => Schema::Null,
+        ValueKind::Boolean => Schema::Boolean,
+        ValueKind::Integer => {
+            let numbers = samples.iter().filter_map(|sample| match sample {
+                BencodexValue::Number(n) => Some(n),
+                _ => None,
+            });
+            Schema::Integer {
+                min: numbers.clone().min().cloned(),
+                max: numbers.max().cloned(),
+            }
+        }
+        ValueKind::Binary => {
+            let lengths = samples.iter().filter_map(|sample| match sample {
+                BencodexValue::Binary(b) => Some(b.len()),
+                _ => None,
+            });
+            Schema::Binary {
+                min_length: lengths.clone().min(),
+                max_length: lengths.max(),
+            }
+        }
+        ValueKind::Text => Schema::Text { pattern: None },
+        ValueKind::List => {
+            let items: Vec<_> = samples
+                .iter()
+                .filter_map(|sample| match sample {
+                    BencodexValue::List(list) => Some(list),
+                    _ => None,
+                })
+                .flatten()
+                .collect();
+            Schema::List(Box::new(infer(&items)))
+        }
+        ValueKind::Dictionary => {
+            let dicts: Vec<_> = samples
+                .iter()
+                .filter_map(|sample| match sample {
+                    BencodexValue::Dictionary(dict) => Some(dict),
+                    _ => None,
+                })
+                .collect();
+            infer_dictionary(&dicts)
+        }
+    }
+}
+
+fn infer(samples: &[&BencodexValue]) -> Schema {
+    let mut by_kind: BTreeMap<ValueKind, Vec<&BencodexValue>> = BTreeMap::new();
+    for sample in samples {
+        by_kind.entry(sample.kind()).or_default().push(sample);
+    }
+
+    let mut variants: Vec<_> = by_kind
+        .into_iter()
+        .map(|(kind, samples)| infer_kind(kind, &samples))
+        .collect();
+    if variants.len() == 1 {
+        variants.remove(0)
+    } else {
+        Schema::Union(variants)
+    }
+}
+
+/// Infers the tightest schema which all the samples are valid against.
+///
+/// Integers are bounded by the least and the greatest of them, and binaries by the shortest
+/// and the longest of them. Texts are not constrained by patterns. The items of all the lists
+/// are inferred together, so lists are inferred as [`Schema::List`], not [`Schema::Tuple`].
+/// The keys of dictionaries are required if all the dictionaries have them and optional
+/// otherwise, and other keys are not allowed. Samples of different kinds are inferred as a
+/// [`Schema::Union`] of a schema per kind.
+///
+/// If there are no samples, an empty [`Schema::Union`] is returned, which no value is
+/// valid against. So are the items of lists which are all empty.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use bencodex::{ BencodexDictionary, BencodexValue };
+/// use bencodex::schema::{ infer_schema, validate, Schema };
+///
+/// let mut first = BencodexDictionary::new();
+/// first.insert("amount".into(), 10.into());
+/// first.insert("memo".into(), "hi".into());
+/// let mut second = BencodexDictionary::new();
+/// second.insert("amount".into(), 3.into());
+/// second.insert("memo".into(), BencodexValue::Null);
+/// let samples = [first, second].map(BencodexValue::Dictionary);
+///
+/// let schema = infer_schema(&samples);
+/// assert_eq!(
+///     schema,
+///     Schema::Dictionary {
+///         required: BTreeMap::from([
+///             ("amount".into(), Schema::Integer { min: Some(3.into()), max: Some(10.into()) }),
+///             ("memo".into(), Schema::Union(vec![Schema::Null, Schema::Text { pattern: None }])),
+///         ]),
+///         optional: BTreeMap::new(),
+///         additional: None,
+///     }
+/// );
+/// assert!(samples.iter().all(|sample| validate(sample, &schema).is_empty()));
+/// ```
+pub fn infer_schema<'a>(samples: impl IntoIterator<Item = &'a BencodexValue>) -> Schema {
+    let samples: Vec<_> = samples.into_iter().collect();
+    infer(&samples)
+}
+
+#[cfg(test)]
+mod tests {
+    mod infer_schema {
+        use super::super::*;
+        use crate::schema::validate;
+        use crate::BencodexDictionary;
+
+        #[test]
+        fn should_return_empty_union_without_samples() {
+            assert_eq!(Schema::Union(vec![]), infer_schema([]));
+            assert_eq!(
+                Schema::List(Box::new(Schema::Union(vec![]))),
+                infer_schema(&[BencodexValue::List(vec![])])
+            );
+        }
+
+        #[test]
+        fn should_infer_ranges() {
+            let samples: Vec<BencodexValue> = vec![
+                5.into(),
+                (-2).into(),
+                vec![0u8; 4].into(),
+                vec![0u8; 32].into(),
+                true.into(),
+                BencodexValue::Number(3.into()),
+            ];
+            assert_eq!(
+                Schema::Union(vec![
+                    Schema::Boolean,
+                    Schema::Integer {
+                        min: Some((-2).into()),
+                        max: Some(5.into()),
+                    },
+                    Schema::Binary {
+                        min_length: Some(4),
+                        max_length: Some(32),
+                    },
+                ]),
+                infer_schema(&samples)
+            );
+        }
+
+        #[test]
+        fn should_infer_optional_keys_and_nested_values() {
+            let mut first = BencodexDictionary::new();
+            first.insert("a".into(), vec![1, 2].into());
+            first.insert(vec![0u8].into(), BencodexValue::Null);
+            let mut second = BencodexDictionary::new();
+            second.insert("a".into(), vec![BencodexValue::from(vec![1u8])].into());
+            second.insert("b".into(), true.into());
+            let samples = [first, second].map(BencodexValue::Dictionary);
+
+            let schema = infer_schema(&samples);
+            assert_eq!(
+                Schema::Dictionary {
+                    required: BTreeMap::from([(
+                        "a".into(),
+                        Schema::List(Box::new(Schema::Union(vec![
+                            Schema::Integer {
+                                min: Some(1.into()),
+                                max: Some(2.into()),
+                            },
+                            Schema::Binary {
+                                min_length: Some(1),
+                                max_length: Some(1),
+                            },
+                        ])))
+                    )]),
+                    optional: BTreeMap::from([
+                        (vec![0u8].into(), Schema::Null),
+                        ("b".into(), Schema::Boolean),
+                    ]),
+                    additional: None,
+                },
+                schema
+            );
+            for sample in &samples {
+                assert!(validate(sample, &schema).is_empty());
+            }
+        }
+    }
+}
@@ -0,0 +1,358 @@
+use serde_json::{json, Map, Value};
+
+use crate::json::{
+    to_json_with_options, write_serde_json, BinaryEncoding, JsonEncodeOptions, BENCODEX_JSON_SCHEMA,
+};
+use crate::{BencodexKey, BencodexValue};
+
+use super::Schema;
+
+fn reference(name: &str) -> Value {
+    json!({ "$ref": format!("#/$defs/{}", name) })
+}
+
+/// Describes the bounds which JSON Schema cannot check on the strings of Bencodex JSON.
+fn describe_bounds(
+    what: &str,
+    min: Option<impl ToString>,
+    max: Option<impl ToString>,
+) -> Option<String> {
+    match (min.map(|m| m.to_string()), max.map(|m| m.to_string())) {
+        (None, None) => None,
+        (Some(min), None) => Some(format!("{} at least {}.", what, min)),
+        (None, Some(max)) => Some(format!("{} at most {}.", what, max)),
+        (Some(min), Some(max)) => Some(format!("{} between {} and {}.", what, min, max)),
+    }
+}
+
+fn with_description(mut schema: Value, description: Option<String>) -> Value {
+    if let Some(description) = description {
+        schema["description"] = Value::String(description);
+    }
+    schema
+}
+
+fn json_key(key: &BencodexKey, binary_encoding: BinaryEncoding) -> String {
+    let options = JsonEncodeOptions::new().with_binary_encoding(binary_encoding);
+    let value = match key {
+        BencodexKey::Binary(b) => BencodexValue::Binary(b.clone()),
+        BencodexKey::Text(t) => BencodexValue::Text(t.clone()),
+    };
+    let json = to_json_with_options(&value, options).expect("writing into a Vec never fails");
+    serde_json::from_str(&json).expect("to_json writes a JSON string for a key")
+}
+
+fn to_json_schema_impl(schema: &Schema, binary_encoding: BinaryEncoding) -> Value {
+    match schema {
+        Schema::Any => reference("value"),
+        Schema::Null => reference("null"),
+        Schema::Boolean => reference("boolean"),
+        Schema::Integer { min, max } => with_description(
+            reference("integer"),
+            describe_bounds("An integer", min.as_ref(), max.as_ref()),
+        ),
+        Schema::Binary {
+            min_length,
+            max_length,
+        } => with_description(
+            reference("binary"),
+            describe_bounds("A binary of length", *min_length, *max_length),
+        ),
+        Schema::Text { pattern: None } => reference("text"),
+        Schema::Text {
+            pattern: Some(pattern),
+        } => {
+            // The pattern is matched against the text after the prefix.
+            let pattern = pattern.to_ecma_after_prefix('\u{FEFF}');
+            json!({ "allOf": [reference("text"), { "pattern": pattern }] })
+        }
+        Schema::List(item) => json!({
+            "type": "array",
+            "items": to_json_schema_impl(item, binary_encoding),
+        }),
+        Schema::Tuple(items) => json!({
+            "type": "array",
+            "prefixItems": items
+                .iter()
+                .map(|item| to_json_schema_impl(item, binary_encoding))
+                .collect::<Vec<_>>(),
+            "minItems": items.len(),
+            "maxItems": items.len(),
+        }),
+        Schema::Dictionary {
+            required,
+            optional,
+            additional,
+        } => {
+            let mut properties = Map::new();
+            for (key, schema) in required.iter().chain(optional) {
+                properties.insert(
+                    json_key(key, binary_encoding),
+                    to_json_schema_impl(schema, binary_encoding),
+                );
+            }
+            json!({
+                "type": "object",
+                "properties": properties,
+                "required": required
+                    .keys()
+                    .map(|key| json_key(key, binary_encoding))
+                    .collect::<Vec<_>>(),
+                "propertyNames": { "anyOf": [reference("binary"), reference("text")] },
+                "additionalProperties": match additional {
+                    Some(additional) => to_json_schema_impl(additional, binary_encoding),
+                    None => Value::Bool(false),
+                },
+            })
+        }
+        Schema::Union(variants) => json!({
+            "anyOf": variants
+                .iter()
+                .map(|variant| to_json_schema_impl(variant, binary_encoding))
+                .collect::<Vec<_>>(),
+        }),
+    }
+}
+
+/// Exports the schema as a [JSON Schema] document, which validates the Bencodex JSON of the
+/// values valid against the schema.
+///
+/// The keys of dictionaries are written with the given encoding, so it should be the
+/// [`JsonEncodeOptions::binary_encoding`] the JSON is written with. As the integers and the
+/// binaries of Bencodex JSON are strings, their bounds are given as descriptions only, and
+/// the JSON valid against the document may not be valid against the schema. The patterns of
+/// texts are rewritten to match after the U+FEFF prefix of Bencodex JSON texts, with the
+/// same meaning as [`Pattern`](super::Pattern) has.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use bencodex::json::BinaryEncoding;
+/// use bencodex::schema::{ to_json_schema, Schema };
+///
+/// let schema = Schema::Dictionary {
+///     required: BTreeMap::from([("name".into(), Schema::Text { pattern: None })]),
+///     optional: BTreeMap::new(),
+///     additional: None,
+/// };
+/// let json_schema = to_json_schema(&schema, BinaryEncoding::Base64);
+/// assert_eq!(json_schema["required"], serde_json::json!(["\u{FEFF}name"]));
+/// assert_eq!(json_schema["properties"]["\u{FEFF}name"]["$ref"], "#/$defs/text");
+/// ```
+///
+/// [JSON Schema]: https://json-schema.org/
+pub fn to_json_schema(schema: &Schema, binary_encoding: BinaryEncoding) -> Value {
+    let base: Value =
+        serde_json::from_str(BENCODEX_JSON_SCHEMA).expect("BENCODEX_JSON_SCHEMA is valid JSON");
+    let mut document = json!({
+        "$schema": base["$schema"],
+        "$defs": base["$defs"],
+    });
+    match to_json_schema_impl(schema, binary_encoding) {
+        Value::Object(root) => document
+            .as_object_mut()
+            .expect("the document is an object")
+            .extend(root),
+        _ => unreachable!("every schema is exported as an object"),
+    }
+
+    document
+}
+
+/// Writes the schema as a [JSON Schema] document from [`to_json_schema`] into the writer,
+/// formatted with the options as [`to_json_writer`](crate::json::to_json_writer) formats
+/// Bencodex JSON. The keys of dictionaries are written with the options'
+/// [`binary_encoding`](JsonEncodeOptions::binary_encoding).
+///
+/// # Examples
+///
+/// ```
+/// use bencodex::json::JsonEncodeOptions;
+/// use bencodex::schema::{ to_json_schema_writer, Schema };
+///
+/// let mut json = vec![];
+/// let options = JsonEncodeOptions::new().with_indent(Some(2)).with_trailing_newline(true);
+/// to_json_schema_writer(&Schema::Null, &mut json, options).unwrap();
+/// let json = String::from_utf8(json).unwrap();
+/// assert!(json.starts_with("{\n  \"$defs\": {\n"));
+/// assert!(json.contains("\n  \"$ref\": \"#/$defs/null\",\n"));
+/// assert!(json.ends_with("\n}\n"));
+/// ```
+///
+/// [JSON Schema]: https://json-schema.org/
+pub fn to_json_schema_writer(
+    schema: &Schema,
+    writer: &mut dyn std::io::Write,
+    options: JsonEncodeOptions,
+) -> std::io::Result<()> {
+    let document = to_json_schema(schema, options.binary_encoding);
+    write_serde_json(&document, &options, writer)
+}
+
+#[cfg(test)]
+mod tests {
+    mod to_json_schema_writer {
+        use super::super::*;
+
+        fn schema() -> Schema {
+            Schema::List(Box::new(Schema::Text {
+                pattern: Some(crate::schema::Pattern::new("^\u{AC00}").unwrap()),
+            }))
+        }
+
+        fn write(options: JsonEncodeOptions) -> String {
+            let mut json = vec![];
+            to_json_schema_writer(&schema(), &mut json, options).unwrap();
+            String::from_utf8(json).unwrap()
+        }
+
+        #[test]
+        fn should_format_as_serde_json() {
+            let document = to_json_schema(&schema(), BinaryEncoding::Hex);
+            let options = || JsonEncodeOptions::new().with_binary_encoding(BinaryEncoding::Hex);
+            assert_eq!(write(options()), serde_json::to_string(&document).unwrap());
+            assert_eq!(
+                write(options().with_indent(Some(2)).with_trailing_newline(true)),
+                serde_json::to_string_pretty(&document).unwrap() + "\n"
+            );
+        }
+
+        #[test]
+        fn should_escape_non_ascii_with_ascii_only() {
+            let json = write(JsonEncodeOptions::new().with_ascii_only(true));
+            assert!(json.is_ascii());
+            assert!(
+                json.contains(r#""pattern":"^\ufeff(?:\uac00)""#),
+                "{}",
+                json
+            );
+            let document: Value = serde_json::from_str(&json).unwrap();
+            assert_eq!(document, to_json_schema(&schema(), BinaryEncoding::Base64));
+        }
+    }
+
+    mod to_json_schema {
+        use std::collections::BTreeMap;
+
+        use super::super::*;
+        use crate::schema::Pattern;
+
+        #[test]
+        fn should_export_dictionary() {
+            let schema = Schema::Dictionary {
+                required: BTreeMap::from([(vec![1u8].into(), Schema::Null)]),
+                optional: BTreeMap::from([(
+                    "ids".into(),
+                    Schema::List(Box::new(Schema::Integer {
+                        min: Some(0.into()),
+                        max: None,
+                    })),
+                )]),
+                additional: Some(Box::new(Schema::Any)),
+            };
+            let document = to_json_schema(&schema, BinaryEncoding::Hex);
+
+            assert_eq!(document["$defs"]["null"], json!({ "type": "null" }));
+            assert_eq!(document["type"], "object");
+            assert_eq!(document["required"], json!(["0x01"]));
+            assert_eq!(document["properties"]["0x01"], reference("null"));
+            assert_eq!(
+                document["properties"]["\u{FEFF}ids"],
+                json!({
+                    "type": "array",
+                    "items": {
+                        "$ref": "#/$defs/integer",
+                        "description": "An integer at least 0.",
+                    },
+                })
+            );
+            assert_eq!(document["additionalProperties"], reference("value"));
+        }
+
+        #[test]
+        fn should_export_tuple_and_union() {
+            let schema = Schema::Tuple(vec![
+                Schema::Union(vec![Schema::Null, Schema::Boolean]),
+                Schema::Text {
+                    pattern: Some(Pattern::new("^[a-z]+$").unwrap()),
+                },
+            ]);
+            let document = to_json_schema(&schema, BinaryEncoding::Base64);
+
+            assert_eq!(document["minItems"], 2);
+            assert_eq!(document["maxItems"], 2);
+            assert_eq!(
+                document["prefixItems"],
+                json!([
+                    { "anyOf": [reference("null"), reference("boolean")] },
+                    { "allOf": [reference("text"), { "pattern": "^\u{FEFF}(?:[a-z]+$)" }] },
+                ])
+            );
+        }
+
+        #[test]
+        fn should_export_patterns_matching_as_validate() {
+            let schema: Value = serde_json::from_str(BENCODEX_JSON_SCHEMA).unwrap();
+            let texts = [
+                "",
+                "a",
+                "b",
+                "ab",
+                "ba",
+                "abc",
+                "cab",
+                "123",
+                "a1",
+                "\u{FEFF}b",
+            ];
+            for pattern in [
+                "",
+                "b",
+                "^b",
+                "b$",
+                "^a|b",
+                "a|^b$",
+                "^[a-z]+$|^[0-9]+$",
+                "[^a]",
+            ] {
+                let pattern = Pattern::new(pattern).unwrap();
+                let document = to_json_schema(
+                    &Schema::Text {
+                        pattern: Some(pattern.clone()),
+                    },
+                    BinaryEncoding::Base64,
+                );
+                assert_eq!(document["$defs"], schema["$defs"]);
+                let validator = jsonschema::draft202012::new(&document).unwrap();
+                for text in texts {
+                    let json: Value =
+                        serde_json::from_str(&crate::json::to_json(&text.into()).unwrap()).unwrap();
+                    assert_eq!(
+                        validator.is_valid(&json),
+                        pattern.is_match(text),
+                        "{:?} against {:?}",
+                        text,
+                        pattern
+                    );
+                }
+            }
+        }
+
+        #[test]
+        fn should_anchor_alternatives_after_prefix() {
+            let export = |pattern| {
+                to_json_schema(
+                    &Schema::Text {
+                        pattern: Some(Pattern::new(pattern).unwrap()),
+                    },
+                    BinaryEncoding::Base64,
+                )["allOf"][1]["pattern"]
+                    .clone()
+            };
+            assert_eq!(export("b"), "^\u{FEFF}(?:[\\s\\S]*?b)");
+            assert_eq!(export("^a|b$"), "^\u{FEFF}(?:a|[\\s\\S]*?b$)");
+            assert_eq!(export("^"), "^\u{FEFF}(?:)");
+        }
+    }
+}
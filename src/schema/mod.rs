@@ -0,0 +1,106 @@
+//! A declarative schema language for Bencodex values, and a validator for it.
+//!
+//! A [`Schema`] constrains the kind of a value, and further the range of an integer, the
+//! length of a binary, the pattern of a text, the items of a list, and the keys of a
+//! dictionary. [`validate`] checks a value against a schema and returns every
+//! [`Violation`] with the path where it is found, instead of stopping at the first one.
+//!
+//! Schemas are expressible as Bencodex values too, so they can be stored alongside the values
+//! they describe, e.g., on-chain. See [`Schema::to_value`] for the representation. With
+//! `json` feature, they are exported as JSON Schema documents by `to_json_schema`, or written
+//! by `to_json_schema_writer`, which validate the Bencodex JSON of the values.
+//!
+//! A schema of undocumented values can be inferred from samples of them by [`infer_schema`].
+//! Rust types for the values of a schema are generated by [`generate_rust`], so that they
+//! stay in sync with the schema.
+//!
+//! ```
+//! use std::collections::BTreeMap;
+//! use bencodex::{ BencodexDictionary, BencodexPath, BencodexValue };
+//! use bencodex::schema::{ validate, Pattern, Schema, ViolationKind };
+//!
+//! let schema = Schema::Dictionary {
+//!     required: BTreeMap::from([
+//!         ("amount".into(), Schema::Integer { min: Some(0.into()), max: None }),
+//!         ("recipient".into(), Schema::Binary { min_length: Some(20), max_length: Some(20) }),
+//!     ]),
+//!     optional: BTreeMap::from([(
+//!         "memo".into(),
+//!         Schema::Text { pattern: Some(Pattern::new("^[a-z ]*$").unwrap()) },
+//!     )]),
+//!     additional: None,
+//! };
+//!
+//! let mut action = BencodexDictionary::new();
+//! action.insert("amount".into(), (-1).into());
+//! action.insert("memo".into(), "hello".into());
+//! let violations = validate(&BencodexValue::Dictionary(action), &schema);
+//!
+//! assert_eq!(violations.len(), 2);
+//! assert_eq!(violations[0].path, BencodexPath::root().key("amount"));
+//! assert_eq!(violations[0].kind, ViolationKind::IntegerOutOfRange);
+//! assert_eq!(violations[1].path, BencodexPath::root().key("recipient"));
+//! assert_eq!(violations[1].kind, ViolationKind::MissingKey);
+//! ```
+
+mod codegen;
+mod infer;
+#[cfg(feature = "json")]
+mod json;
+mod pattern;
+mod validate;
+mod value;
+
+use std::collections::BTreeMap;
+
+use num_bigint::BigInt;
+
+use crate::BencodexKey;
+
+pub use codegen::generate_rust;
+pub use infer::infer_schema;
+#[cfg(feature = "json")]
+pub use json::{to_json_schema, to_json_schema_writer};
+pub use pattern::Pattern;
+pub use validate::{validate, Violation, ViolationKind};
+pub use value::SchemaDecodeError;
+
+/// A schema which a [`BencodexValue`](crate::BencodexValue) is validated against by
+/// [`validate`].
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Schema {
+    /// Any value.
+    Any,
+    Null,
+    Boolean,
+    /// An integer between `min` and `max`, both inclusive. An absent bound is not checked.
+    Integer {
+        min: Option<BigInt>,
+        max: Option<BigInt>,
+    },
+    /// A binary whose length in bytes is between `min_length` and `max_length`, both
+    /// inclusive. An absent bound is not checked.
+    Binary {
+        min_length: Option<usize>,
+        max_length: Option<usize>,
+    },
+    /// A text which matches the `pattern` if given.
+    Text {
+        pattern: Option<Pattern>,
+    },
+    /// A list whose items are all valid against the schema.
+    List(Box<Schema>),
+    /// A list of exactly as many items as the schemas, each valid against the schema at the
+    /// same index.
+    Tuple(Vec<Schema>),
+    /// A dictionary which has all the `required` keys, and whose values are valid against
+    /// the schemas of their keys. Keys neither `required` nor `optional` are allowed only if
+    /// `additional` is given, and their values are valid against it.
+    Dictionary {
+        required: BTreeMap<BencodexKey, Schema>,
+        optional: BTreeMap<BencodexKey, Schema>,
+        additional: Option<Box<Schema>>,
+    },
+    /// A value valid against at least one of the schemas.
+    Union(Vec<Schema>),
+}
@@ -0,0 +1,302 @@
+use std::fmt;
+
+use regex::Regex;
+use regex_syntax::ast::{
+    self, AssertionKind, Ast, ClassSet, ClassSetItem, GroupKind, HexLiteralKind, LiteralKind,
+    RepetitionKind, SpecialLiteralKind,
+};
+
+/// A regular expression a text is matched against by [`Schema::Text`](super::Schema::Text).
+///
+/// It is matched anywhere in a text unless anchored with `^` and `$`. Two patterns are equal
+/// if they are written the same.
+///
+/// As patterns are exported to JSON Schema by `to_json_schema`, they are written in the
+/// subset of the syntax of the [`regex`] crate which [ECMA-262] regular expressions share the
+/// meaning of:
+///
+/// - literal characters other than `]`, `{` and `}`, `\t`, `\n`, `\v`, `\f`, `\r`, `\xHH`,
+///   `\uHHHH`, and the escaped syntax characters `^$\.*+?()[]{}|/`;
+/// - bracketed classes of characters and ranges of them, e.g., `[^a-z_\-]`, where `]` is
+///   escaped;
+/// - capturing groups without names, and non-capturing groups without flags;
+/// - the repetitions `?`, `*`, `+`, `{n}`, `{n,}`, and `{n,m}`, greedy or lazy;
+/// - `$`, and `^` only at the start of the pattern or of an alternative at the top level,
+///   e.g., `^a|^b`.
+///
+/// `.`, the classes like `\d`, `\w`, `\s` and `\pL`, word boundaries, and flags are not in
+/// the subset, as they match different characters in ECMA-262. The characters are Unicode
+/// scalar values, as they are in the Unicode mode of ECMA-262.
+///
+/// ```
+/// use bencodex::schema::Pattern;
+///
+/// let pattern = Pattern::new("^[a-z]+$|^[0-9]+$").unwrap();
+/// assert!(pattern.is_match("abc"));
+/// assert!(!pattern.is_match("abc123"));
+/// assert!(Pattern::new("^.+$").is_err());
+/// ```
+///
+/// [ECMA-262]: https://tc39.es/ecma262/#sec-regexp-regular-expression-objects
+#[derive(Clone)]
+pub struct Pattern(Regex);
+
+impl Pattern {
+    /// Compiles the pattern, which fails if it is not a valid regular expression or not in
+    /// the subset shared with ECMA-262.
+    pub fn new(pattern: &str) -> Result<Pattern, regex::Error> {
+        let regex = Regex::new(pattern)?;
+        let ast = parse(pattern);
+        for alternative in alternatives(&ast) {
+            let items: &[Ast] = match alternative {
+                Ast::Concat(concat) => &concat.asts,
+                ast => std::slice::from_ref(ast),
+            };
+            for (position, item) in items.iter().enumerate() {
+                if position == 0 && is_start(item) {
+                    continue;
+                }
+                check_subset(pattern, item).map_err(|(span, what)| {
+                    regex::Error::Syntax(format!(
+                        "{} at {}..{} of {:?} is not in the subset shared with ECMA-262",
+                        what, span.start.offset, span.end.offset, pattern,
+                    ))
+                })?;
+            }
+        }
+
+        Ok(Pattern(regex))
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.0.is_match(text)
+    }
+
+    /// Writes the pattern as an ECMA-262 regular expression which matches a text prefixed
+    /// with the `prefix` if and only if the pattern matches the text.
+    ///
+    /// Each alternative at the top level is anchored after the prefix: those starting with
+    /// `^` right after it, and the others anywhere after it.
+    #[cfg(feature = "json")]
+    pub(crate) fn to_ecma_after_prefix(&self, prefix: char) -> String {
+        let pattern = self.as_str();
+        let ast = parse(pattern);
+        let alternatives = alternatives(&ast)
+            .into_iter()
+            .map(|alternative| {
+                let span = alternative.span();
+                let first = match alternative {
+                    Ast::Concat(concat) => concat.asts.first(),
+                    ast => Some(ast),
+                };
+                match first {
+                    Some(first) if is_start(first) => {
+                        pattern[first.span().end.offset..span.end.offset].to_string()
+                    }
+                    _ => format!(r"[\s\S]*?{}", &pattern[span.start.offset..span.end.offset]),
+                }
+            })
+            .collect::<Vec<_>>();
+        format!("^{}(?:{})", prefix, alternatives.join("|"))
+    }
+}
+
+fn parse(pattern: &str) -> Ast {
+    ast::parse::Parser::new()
+        .parse(pattern)
+        .expect("a pattern the regex crate compiles is parsed")
+}
+
+fn alternatives(ast: &Ast) -> Vec<&Ast> {
+    match ast {
+        Ast::Alternation(alternation) => alternation.asts.iter().collect(),
+        ast => vec![ast],
+    }
+}
+
+fn is_start(ast: &Ast) -> bool {
+    matches!(ast, Ast::Assertion(assertion) if assertion.kind == AssertionKind::StartLine)
+}
+
+/// Checks that the regular expression is in the subset shared with ECMA-262, or returns the
+/// span and the description of what is not.
+fn check_subset(pattern: &str, ast: &Ast) -> Result<(), (ast::Span, &'static str)> {
+    let check = |ast| check_subset(pattern, ast);
+    match ast {
+        Ast::Empty(_) => Ok(()),
+        Ast::Flags(flags) => Err((flags.span, "Flags")),
+        Ast::Literal(literal) => check_literal(literal, false),
+        Ast::Dot(span) => Err((**span, "`.`")),
+        Ast::Assertion(assertion) => match assertion.kind {
+            AssertionKind::EndLine => Ok(()),
+            AssertionKind::StartLine => Err((
+                assertion.span,
+                "`^` not at the start of an alternative at the top level",
+            )),
+            _ => Err((assertion.span, "The assertion")),
+        },
+        Ast::ClassUnicode(class) => Err((class.span, "The Unicode class")),
+        Ast::ClassPerl(class) => Err((class.span, "The Perl class")),
+        Ast::ClassBracketed(class) => check_class_set(&class.kind),
+        Ast::Repetition(repetition) => {
+            let op = repetition.op.span;
+            if let RepetitionKind::Range(_) = repetition.op.kind {
+                if !is_counted(&pattern[op.start.offset..op.end.offset]) {
+                    return Err((op, "The repetition"));
+                }
+            }
+            check(&repetition.ast)
+        }
+        Ast::Group(group) => match &group.kind {
+            GroupKind::CaptureIndex(_) => check(&group.ast),
+            GroupKind::NonCapturing(flags) if flags.items.is_empty() => check(&group.ast),
+            _ => Err((group.span, "The named or flagged group")),
+        },
+        Ast::Alternation(alternation) => alternation.asts.iter().try_for_each(check),
+        Ast::Concat(concat) => concat.asts.iter().try_for_each(check),
+    }
+}
+
+/// Whether the counted repetition is written as `{n}`, `{n,}` or `{n,m}`, optionally followed
+/// by `?`. The regex crate also accepts `{,m}` and spaces, which are literals in ECMA-262.
+fn is_counted(op: &str) -> bool {
+    let op = op.strip_suffix('?').unwrap_or(op);
+    let Some(counts) = op.strip_prefix('{').and_then(|op| op.strip_suffix('}')) else {
+        return false;
+    };
+    let (min, max) = counts.split_once(',').unwrap_or((counts, ""));
+    let is_decimal = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    !min.is_empty() && is_decimal(min) && is_decimal(max)
+}
+
+/// Checks the literal, which is in a bracketed class if `in_class`.
+///
+/// Only the syntax characters are escaped, and `]`, `{` and `}` are escaped outside classes,
+/// as the Unicode mode of ECMA-262 rejects the others.
+fn check_literal(literal: &ast::Literal, in_class: bool) -> Result<(), (ast::Span, &'static str)> {
+    match literal.kind {
+        LiteralKind::Verbatim if in_class || !"]{}".contains(literal.c) => Ok(()),
+        LiteralKind::Verbatim => Err((literal.span, "Unescaped bracket")),
+        LiteralKind::Meta | LiteralKind::Superfluous
+            if r"^$\.*+?()[]{}|/".contains(literal.c) || (in_class && literal.c == '-') =>
+        {
+            Ok(())
+        }
+        LiteralKind::HexFixed(HexLiteralKind::X | HexLiteralKind::UnicodeShort) => Ok(()),
+        LiteralKind::Special(
+            SpecialLiteralKind::Tab
+            | SpecialLiteralKind::LineFeed
+            | SpecialLiteralKind::VerticalTab
+            | SpecialLiteralKind::FormFeed
+            | SpecialLiteralKind::CarriageReturn,
+        ) => Ok(()),
+        _ => Err((literal.span, "The escape")),
+    }
+}
+
+fn check_class_set(set: &ClassSet) -> Result<(), (ast::Span, &'static str)> {
+    let item = match set {
+        ClassSet::Item(item) => item,
+        ClassSet::BinaryOp(op) => return Err((op.span, "The class operation")),
+    };
+    let items = match item {
+        ClassSetItem::Union(union) => union.items.iter().collect(),
+        item => vec![item],
+    };
+    for item in items {
+        match item {
+            ClassSetItem::Empty(_) => {}
+            // `[]a]` is a class of `]` and `a` in the regex crate, but an empty class
+            // followed by `a]` in ECMA-262.
+            ClassSetItem::Literal(literal)
+                if literal.c == ']' && literal.kind == LiteralKind::Verbatim =>
+            {
+                return Err((literal.span, "Unescaped `]`"));
+            }
+            ClassSetItem::Literal(literal) => check_literal(literal, true)?,
+            ClassSetItem::Range(range) => {
+                check_literal(&range.start, true)?;
+                check_literal(&range.end, true)?;
+            }
+            ClassSetItem::Ascii(class) => return Err((class.span, "The ASCII class")),
+            ClassSetItem::Unicode(class) => return Err((class.span, "The Unicode class")),
+            ClassSetItem::Perl(class) => return Err((class.span, "The Perl class")),
+            ClassSetItem::Bracketed(class) => return Err((class.span, "The nested class")),
+            ClassSetItem::Union(union) => return Err((union.span, "The nested class")),
+        }
+    }
+
+    Ok(())
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Pattern {}
+
+impl fmt::Debug for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Pattern").field(&self.as_str()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod new {
+        use super::super::*;
+
+        #[test]
+        fn should_accept_subset_shared_with_ecma() {
+            for pattern in [
+                "",
+                "b",
+                "^[a-z]+$",
+                "^a|^b|c$",
+                r"[^a-z_\-]",
+                r"\.\x41A\t\/",
+                "(?:a)(b)*?",
+                "a{2}b{2,}?c{2,3}",
+            ] {
+                assert!(Pattern::new(pattern).is_ok(), "{:?}", pattern);
+            }
+        }
+
+        #[test]
+        fn should_reject_outside_subset() {
+            for pattern in [
+                "^.+$",
+                r"\d",
+                r"[\w]",
+                r"\pL",
+                "[[:alpha:]]",
+                "(?i)a",
+                "(?i:a)",
+                "(?P<name>a)",
+                r"\bword",
+                r"\Aa",
+                "a^b",
+                "(^a)",
+                "a|(?:^b)",
+                "a{,3}",
+                "[]a]",
+                "a]",
+                r"\#",
+                r"\x{41}",
+                "[a&&b]",
+            ] {
+                assert!(
+                    matches!(Pattern::new(pattern), Err(regex::Error::Syntax(_))),
+                    "{:?}",
+                    pattern
+                );
+            }
+        }
+    }
+}
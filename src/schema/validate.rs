@@ -0,0 +1,429 @@
+use crate::{BencodexPath, BencodexValue, PathSegment, ValueKind};
+
+use super::Schema;
+
+/// A violation of a [`Schema`] found by [`validate`].
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Violation {
+    /// The path to the violating value. For [`ViolationKind::MissingKey`] and
+    /// [`ViolationKind::UnexpectedKey`], it is the path to the value of the key.
+    pub path: BencodexPath,
+    pub kind: ViolationKind,
+}
+
+/// What is wrong with a value, given by [`Violation`].
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum ViolationKind {
+    /// The value is not of the kind the schema expects.
+    UnexpectedKind {
+        expected: ValueKind,
+        actual: ValueKind,
+    },
+    /// The value is not valid against any schema of a [`Schema::Union`], and no single
+    /// schema of the union expects its kind, so the violations against each of them are
+    /// not reported.
+    NoMatchingVariant,
+    /// The integer is out of the range of [`Schema::Integer`].
+    IntegerOutOfRange,
+    /// The length of the binary is out of the range of [`Schema::Binary`].
+    BinaryLengthOutOfRange,
+    /// The text does not match the pattern of [`Schema::Text`].
+    PatternMismatch,
+    /// The number of the items of the list differs from the schemas of [`Schema::Tuple`].
+    TupleLengthMismatch { expected: usize, actual: usize },
+    /// The required key of [`Schema::Dictionary`] is missing.
+    MissingKey,
+    /// The key is not allowed by [`Schema::Dictionary`].
+    UnexpectedKey,
+}
+
+/// Returns the kind of the values the schema expects, or [`None`] if it expects several.
+fn expected_kind(schema: &Schema) -> Option<ValueKind> {
+    match schema {
+        Schema::Any | Schema::Union(_) => None,
+        Schema::Null => Some(ValueKind::Null),
+        Schema::Boolean => Some(ValueKind::Boolean),
+        Schema::Integer { .. } => Some(ValueKind::Integer),
+        Schema::Binary { .. } => Some(ValueKind::Binary),
+        Schema::Text { .. } => Some(ValueKind::Text),
+        Schema::List(_) | Schema::Tuple(_) => Some(ValueKind::List),
+        Schema::Dictionary { .. } => Some(ValueKind::Dictionary),
+    }
+}
+
+fn out_of_range<T: PartialOrd>(value: &T, min: Option<&T>, max: Option<&T>) -> bool {
+    min.is_some_and(|min| value < min) || max.is_some_and(|max| value > max)
+}
+
+fn validate_impl(
+    value: &BencodexValue,
+    schema: &Schema,
+    path: &mut BencodexPath,
+    violations: &mut Vec<Violation>,
+) {
+    let mut violate = |path: &BencodexPath, kind| {
+        violations.push(Violation {
+            path: path.clone(),
+            kind,
+        })
+    };
+
+    match (schema, value) {
+        (Schema::Any, _) | (Schema::Null, BencodexValue::Null) => {}
+        (Schema::Boolean, BencodexValue::Boolean(_)) => {}
+        (Schema::Integer { min, max }, BencodexValue::Number(n)) => {
+            if out_of_range(n, min.as_ref(), max.as_ref()) {
+                violate(path, ViolationKind::IntegerOutOfRange);
+            }
+        }
+        (
+            Schema::Binary {
+                min_length,
+                max_length,
+            },
+            BencodexValue::Binary(b),
+        ) => {
+            if out_of_range(&b.len(), min_length.as_ref(), max_length.as_ref()) {
+                violate(path, ViolationKind::BinaryLengthOutOfRange);
+            }
+        }
+        (Schema::Text { pattern }, BencodexValue::Text(t)) => {
+            if pattern.as_ref().is_some_and(|pattern| !pattern.is_match(t)) {
+                violate(path, ViolationKind::PatternMismatch);
+            }
+        }
+        (Schema::List(item), BencodexValue::List(list)) => {
+            for (i, value) in list.iter().enumerate() {
+                path.push(PathSegment::Index(i));
+                validate_impl(value, item, path, violations);
+                path.pop();
+            }
+        }
+        (Schema::Tuple(items), BencodexValue::List(list)) => {
+            if items.len() != list.len() {
+                violate(
+                    path,
+                    ViolationKind::TupleLengthMismatch {
+                        expected: items.len(),
+                        actual: list.len(),
+                    },
+                );
+            }
+            for (i, (value, item)) in list.iter().zip(items).enumerate() {
+                path.push(PathSegment::Index(i));
+                validate_impl(value, item, path, violations);
+                path.pop();
+            }
+        }
+        (
+            Schema::Dictionary {
+                required,
+                optional,
+                additional,
+            },
+            BencodexValue::Dictionary(dict),
+        ) => {
+            // Visit the keys in order, so the violations are ordered by their paths.
+            let missing = required.keys().filter(|key| !dict.contains_key(*key));
+            let mut missing = missing.peekable();
+            for (key, value) in dict {
+                while let Some(missing_key) = missing.next_if(|missing_key| *missing_key < key) {
+                    path.push(PathSegment::Key(missing_key.clone()));
+                    violations.push(Violation {
+                        path: path.clone(),
+                        kind: ViolationKind::MissingKey,
+                    });
+                    path.pop();
+                }
+
+                path.push(PathSegment::Key(key.clone()));
+                match required
+                    .get(key)
+                    .or_else(|| optional.get(key))
+                    .or(additional.as_deref())
+                {
+                    Some(schema) => validate_impl(value, schema, path, violations),
+                    None => violations.push(Violation {
+                        path: path.clone(),
+                        kind: ViolationKind::UnexpectedKey,
+                    }),
+                }
+                path.pop();
+            }
+            for missing_key in missing {
+                path.push(PathSegment::Key(missing_key.clone()));
+                violations.push(Violation {
+                    path: path.clone(),
+                    kind: ViolationKind::MissingKey,
+                });
+                path.pop();
+            }
+        }
+        (Schema::Union(variants), _) => {
+            let mut candidates = vec![];
+            for variant in variants {
+                if expected_kind(variant).is_some_and(|kind| kind != value.kind()) {
+                    continue;
+                }
+                let mut variant_violations = vec![];
+                validate_impl(value, variant, path, &mut variant_violations);
+                if variant_violations.is_empty() {
+                    return;
+                }
+                candidates.push(variant_violations);
+            }
+
+            match <[_; 1]>::try_from(candidates) {
+                Ok([candidate]) => violations.extend(candidate),
+                Err(_) => violate(path, ViolationKind::NoMatchingVariant),
+            }
+        }
+        (schema, value) => violate(
+            path,
+            ViolationKind::UnexpectedKind {
+                expected: expected_kind(schema).expect("Any and Union match any value"),
+                actual: value.kind(),
+            },
+        ),
+    }
+}
+
+/// Validates the value against the schema, and returns all the violations ordered by their
+/// paths, or an empty [`Vec`] if the value is valid.
+///
+/// # Examples
+///
+/// ```
+/// use bencodex::{ BencodexPath, BencodexValue, ValueKind };
+/// use bencodex::schema::{ validate, Schema, Violation, ViolationKind };
+///
+/// let schema = Schema::List(Box::new(Schema::Union(vec![
+///     Schema::Null,
+///     Schema::Binary { min_length: None, max_length: Some(2) },
+/// ])));
+/// let value = BencodexValue::from(vec![
+///     BencodexValue::Null,
+///     BencodexValue::from(vec![1u8, 2, 3]),
+///     BencodexValue::from(true),
+/// ]);
+///
+/// assert_eq!(
+///     validate(&value, &schema),
+///     vec![
+///         Violation {
+///             path: BencodexPath::root().index(1),
+///             kind: ViolationKind::BinaryLengthOutOfRange,
+///         },
+///         Violation {
+///             path: BencodexPath::root().index(2),
+///             kind: ViolationKind::NoMatchingVariant,
+///         },
+///     ]
+/// );
+/// ```
+pub fn validate(value: &BencodexValue, schema: &Schema) -> Vec<Violation> {
+    let mut violations = vec![];
+    validate_impl(value, schema, &mut BencodexPath::root(), &mut violations);
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    mod validate {
+        use std::collections::BTreeMap;
+
+        use super::super::*;
+        use crate::schema::Pattern;
+        use crate::BencodexDictionary;
+
+        fn violation(path: BencodexPath, kind: ViolationKind) -> Violation {
+            Violation { path, kind }
+        }
+
+        #[test]
+        fn should_accept_valid_scalars() {
+            let cases: Vec<(BencodexValue, Schema)> = vec![
+                (BencodexValue::Null, Schema::Null),
+                (true.into(), Schema::Boolean),
+                (
+                    5.into(),
+                    Schema::Integer {
+                        min: Some(5.into()),
+                        max: Some(5.into()),
+                    },
+                ),
+                (
+                    vec![0u8; 3].into(),
+                    Schema::Binary {
+                        min_length: Some(3),
+                        max_length: None,
+                    },
+                ),
+                (
+                    "abc".into(),
+                    Schema::Text {
+                        pattern: Some(Pattern::new("b").unwrap()),
+                    },
+                ),
+                ("abc".into(), Schema::Any),
+            ];
+            for (value, schema) in cases {
+                assert_eq!(Vec::<Violation>::new(), validate(&value, &schema));
+            }
+        }
+
+        #[test]
+        fn should_report_scalar_violations() {
+            let root = BencodexPath::root;
+            assert_eq!(
+                vec![violation(
+                    root(),
+                    ViolationKind::UnexpectedKind {
+                        expected: ValueKind::Integer,
+                        actual: ValueKind::Text,
+                    }
+                )],
+                validate(
+                    &"1".into(),
+                    &Schema::Integer {
+                        min: None,
+                        max: None
+                    }
+                )
+            );
+            assert_eq!(
+                vec![violation(root(), ViolationKind::IntegerOutOfRange)],
+                validate(
+                    &(-1).into(),
+                    &Schema::Integer {
+                        min: Some(0.into()),
+                        max: None
+                    }
+                )
+            );
+            assert_eq!(
+                vec![violation(root(), ViolationKind::BinaryLengthOutOfRange)],
+                validate(
+                    &vec![0u8; 3].into(),
+                    &Schema::Binary {
+                        min_length: None,
+                        max_length: Some(2)
+                    }
+                )
+            );
+            assert_eq!(
+                vec![violation(root(), ViolationKind::PatternMismatch)],
+                validate(
+                    &"abc".into(),
+                    &Schema::Text {
+                        pattern: Some(Pattern::new("^b").unwrap())
+                    }
+                )
+            );
+        }
+
+        #[test]
+        fn should_report_tuple_violations() {
+            let schema = Schema::Tuple(vec![Schema::Boolean, Schema::Null]);
+            let value = BencodexValue::from(vec![BencodexValue::Null]);
+            assert_eq!(
+                vec![
+                    violation(
+                        BencodexPath::root(),
+                        ViolationKind::TupleLengthMismatch {
+                            expected: 2,
+                            actual: 1
+                        }
+                    ),
+                    violation(
+                        BencodexPath::root().index(0),
+                        ViolationKind::UnexpectedKind {
+                            expected: ValueKind::Boolean,
+                            actual: ValueKind::Null,
+                        }
+                    ),
+                ],
+                validate(&value, &schema)
+            );
+        }
+
+        #[test]
+        fn should_report_dictionary_violations_in_order() {
+            let schema = Schema::Dictionary {
+                required: BTreeMap::from([
+                    ("a".into(), Schema::Null),
+                    ("c".into(), Schema::Null),
+                    ("e".into(), Schema::Null),
+                ]),
+                optional: BTreeMap::from([("b".into(), Schema::Boolean)]),
+                additional: None,
+            };
+            let mut dict = BencodexDictionary::new();
+            dict.insert("b".into(), BencodexValue::Null);
+            dict.insert("d".into(), BencodexValue::Null);
+            dict.insert("e".into(), BencodexValue::Null);
+
+            let root = BencodexPath::root;
+            assert_eq!(
+                vec![
+                    violation(root().key("a"), ViolationKind::MissingKey),
+                    violation(
+                        root().key("b"),
+                        ViolationKind::UnexpectedKind {
+                            expected: ValueKind::Boolean,
+                            actual: ValueKind::Null,
+                        }
+                    ),
+                    violation(root().key("c"), ViolationKind::MissingKey),
+                    violation(root().key("d"), ViolationKind::UnexpectedKey),
+                ],
+                validate(&BencodexValue::Dictionary(dict.clone()), &schema)
+            );
+
+            let schema = Schema::Dictionary {
+                required: BTreeMap::new(),
+                optional: BTreeMap::new(),
+                additional: Some(Box::new(Schema::Null)),
+            };
+            dict.insert(vec![0u8].into(), true.into());
+            assert_eq!(
+                vec![violation(
+                    root().key(vec![0u8]),
+                    ViolationKind::UnexpectedKind {
+                        expected: ValueKind::Null,
+                        actual: ValueKind::Boolean,
+                    }
+                )],
+                validate(&BencodexValue::Dictionary(dict), &schema)
+            );
+        }
+
+        #[test]
+        fn should_report_union_violations() {
+            let schema = Schema::Union(vec![
+                Schema::Null,
+                Schema::Integer {
+                    min: Some(0.into()),
+                    max: None,
+                },
+                Schema::Text { pattern: None },
+            ]);
+            assert!(validate(&BencodexValue::Null, &schema).is_empty());
+            assert!(validate(&"".into(), &schema).is_empty());
+            assert_eq!(
+                vec![violation(
+                    BencodexPath::root(),
+                    ViolationKind::IntegerOutOfRange
+                )],
+                validate(&(-1).into(), &schema)
+            );
+            assert_eq!(
+                vec![violation(
+                    BencodexPath::root(),
+                    ViolationKind::NoMatchingVariant
+                )],
+                validate(&true.into(), &schema)
+            );
+        }
+    }
+}
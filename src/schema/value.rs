@@ -0,0 +1,405 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+use num_bigint::BigInt;
+
+use crate::{BencodexDictionary, BencodexKey, BencodexPath, BencodexValue, PathSegment};
+
+use super::{Pattern, Schema};
+
+/// The error type which is returned from reading a [`Schema`] from a Bencodex value through
+/// [`Schema::from_value`].
+#[derive(Debug, PartialEq)]
+pub enum SchemaDecodeError {
+    /// This should be used when the value at the given path of the schema value is not
+    /// what a schema expects there, e.g., a missing `type`, or a negative length.
+    InvalidSchemaError { path: BencodexPath },
+    /// This should be used when the `type` at the given path is not a known type.
+    ///
+    /// ```
+    /// use bencodex::{ BencodexDictionary, BencodexPath, BencodexValue };
+    /// use bencodex::schema::{ Schema, SchemaDecodeError };
+    ///
+    /// let mut value = BencodexDictionary::new();
+    /// value.insert("type".into(), "float".into());
+    /// assert_eq!(
+    ///     Schema::from_value(&BencodexValue::Dictionary(value)),
+    ///     Err(SchemaDecodeError::UnknownTypeError {
+    ///         path: BencodexPath::root().key("type"),
+    ///         name: "float".to_string(),
+    ///     })
+    /// );
+    /// ```
+    UnknownTypeError { path: BencodexPath, name: String },
+    /// This should be used when the `pattern` at the given path is not a valid regular
+    /// expression.
+    InvalidPatternError { path: BencodexPath, message: String },
+}
+
+impl fmt::Display for SchemaDecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl Error for SchemaDecodeError {}
+
+fn schemas_to_value(schemas: &BTreeMap<BencodexKey, Schema>) -> BencodexValue {
+    BencodexValue::Dictionary(
+        schemas
+            .iter()
+            .map(|(key, schema)| (key.clone(), schema.to_value()))
+            .collect(),
+    )
+}
+
+impl Schema {
+    /// Returns the schema as a Bencodex value, which is read back by [`Schema::from_value`].
+    ///
+    /// A schema is a dictionary with a `type` text, one of `any`, `null`, `boolean`,
+    /// `integer`, `binary`, `text`, `list`, `tuple`, `dictionary` and `union`, and the
+    /// other keys for the type:
+    ///
+    /// - `integer` has optional `min` and `max` integers.
+    /// - `binary` has optional `min_length` and `max_length` integers.
+    /// - `text` has an optional `pattern` text.
+    /// - `list` has an `items` schema, and `tuple` has an `items` list of schemas.
+    /// - `dictionary` has `required` and `optional` dictionaries from keys to schemas, and
+    ///   an optional `additional` schema.
+    /// - `union` has a `variants` list of schemas.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bencodex::{ BencodexDictionary, BencodexValue };
+    /// use bencodex::schema::Schema;
+    ///
+    /// let schema = Schema::List(Box::new(Schema::Integer { min: Some(0.into()), max: None }));
+    ///
+    /// let mut items = BencodexDictionary::new();
+    /// items.insert("type".into(), "integer".into());
+    /// items.insert("min".into(), 0.into());
+    /// let mut expected = BencodexDictionary::new();
+    /// expected.insert("type".into(), "list".into());
+    /// expected.insert("items".into(), items.into());
+    /// assert_eq!(schema.to_value(), BencodexValue::Dictionary(expected));
+    /// assert_eq!(Schema::from_value(&schema.to_value()), Ok(schema));
+    /// ```
+    pub fn to_value(&self) -> BencodexValue {
+        let mut dict = BencodexDictionary::new();
+        let type_name = match self {
+            Schema::Any => "any",
+            Schema::Null => "null",
+            Schema::Boolean => "boolean",
+            Schema::Integer { min, max } => {
+                let bounds = [("min", min), ("max", max)];
+                for (name, bound) in bounds {
+                    if let Some(bound) = bound {
+                        dict.insert(name.into(), BencodexValue::Number(bound.clone()));
+                    }
+                }
+                "integer"
+            }
+            Schema::Binary {
+                min_length,
+                max_length,
+            } => {
+                let bounds = [("min_length", min_length), ("max_length", max_length)];
+                for (name, bound) in bounds {
+                    if let Some(bound) = bound {
+                        let bound = BencodexValue::Number(BigInt::from(*bound));
+                        dict.insert(name.into(), bound);
+                    }
+                }
+                "binary"
+            }
+            Schema::Text { pattern } => {
+                if let Some(pattern) = pattern {
+                    dict.insert("pattern".into(), pattern.as_str().into());
+                }
+                "text"
+            }
+            Schema::List(item) => {
+                dict.insert("items".into(), item.to_value());
+                "list"
+            }
+            Schema::Tuple(items) => {
+                let items = items.iter().map(Schema::to_value).collect();
+                dict.insert("items".into(), BencodexValue::List(items));
+                "tuple"
+            }
+            Schema::Dictionary {
+                required,
+                optional,
+                additional,
+            } => {
+                dict.insert("required".into(), schemas_to_value(required));
+                dict.insert("optional".into(), schemas_to_value(optional));
+                if let Some(additional) = additional {
+                    dict.insert("additional".into(), additional.to_value());
+                }
+                "dictionary"
+            }
+            Schema::Union(variants) => {
+                let variants = variants.iter().map(Schema::to_value).collect();
+                dict.insert("variants".into(), BencodexValue::List(variants));
+                "union"
+            }
+        };
+        dict.insert("type".into(), type_name.into());
+
+        BencodexValue::Dictionary(dict)
+    }
+
+    /// Reads a schema from a Bencodex value written by [`Schema::to_value`].
+    ///
+    /// Keys not used by the type are ignored, so schemas can be annotated, e.g., with
+    /// descriptions.
+    pub fn from_value(value: &BencodexValue) -> Result<Schema, SchemaDecodeError> {
+        from_value_impl(value, &mut BencodexPath::root())
+    }
+}
+
+/// Reads the values of a schema dictionary, pushing the path to each value while reading it.
+struct Reader<'a> {
+    dict: &'a BencodexDictionary,
+    path: &'a mut BencodexPath,
+}
+
+impl Reader<'_> {
+    fn invalid(&self) -> SchemaDecodeError {
+        SchemaDecodeError::InvalidSchemaError {
+            path: self.path.clone(),
+        }
+    }
+
+    /// Calls the function with the value of the key if it exists, with the path to it.
+    fn with<T>(
+        &mut self,
+        name: &str,
+        f: impl FnOnce(&BencodexValue, &mut BencodexPath) -> Result<T, SchemaDecodeError>,
+    ) -> Result<Option<T>, SchemaDecodeError> {
+        let key = BencodexKey::from(name);
+        let Some(value) = self.dict.get(&key) else {
+            return Ok(None);
+        };
+        self.path.push(PathSegment::Key(key));
+        let result = f(value, self.path);
+        self.path.pop();
+        result.map(Some)
+    }
+
+    fn required<T>(
+        &mut self,
+        name: &str,
+        f: impl FnOnce(&BencodexValue, &mut BencodexPath) -> Result<T, SchemaDecodeError>,
+    ) -> Result<T, SchemaDecodeError> {
+        match self.with(name, f)? {
+            Some(value) => Ok(value),
+            None => Err(self.invalid()),
+        }
+    }
+}
+
+fn invalid(path: &BencodexPath) -> SchemaDecodeError {
+    SchemaDecodeError::InvalidSchemaError { path: path.clone() }
+}
+
+fn integer(value: &BencodexValue, path: &mut BencodexPath) -> Result<BigInt, SchemaDecodeError> {
+    match value {
+        BencodexValue::Number(n) => Ok(n.clone()),
+        _ => Err(invalid(path)),
+    }
+}
+
+fn length(value: &BencodexValue, path: &mut BencodexPath) -> Result<usize, SchemaDecodeError> {
+    usize::try_from(&integer(value, path)?).map_err(|_| invalid(path))
+}
+
+fn schema_list(
+    value: &BencodexValue,
+    path: &mut BencodexPath,
+) -> Result<Vec<Schema>, SchemaDecodeError> {
+    let BencodexValue::List(list) = value else {
+        return Err(invalid(path));
+    };
+    let mut schemas = Vec::with_capacity(list.len());
+    for (i, item) in list.iter().enumerate() {
+        path.push(PathSegment::Index(i));
+        schemas.push(from_value_impl(item, path)?);
+        path.pop();
+    }
+
+    Ok(schemas)
+}
+
+fn schema_map(
+    value: &BencodexValue,
+    path: &mut BencodexPath,
+) -> Result<BTreeMap<BencodexKey, Schema>, SchemaDecodeError> {
+    let BencodexValue::Dictionary(dict) = value else {
+        return Err(invalid(path));
+    };
+    let mut schemas = BTreeMap::new();
+    for (key, item) in dict {
+        path.push(PathSegment::Key(key.clone()));
+        schemas.insert(key.clone(), from_value_impl(item, path)?);
+        path.pop();
+    }
+
+    Ok(schemas)
+}
+
+fn from_value_impl(
+    value: &BencodexValue,
+    path: &mut BencodexPath,
+) -> Result<Schema, SchemaDecodeError> {
+    let BencodexValue::Dictionary(dict) = value else {
+        return Err(invalid(path));
+    };
+    let mut reader = Reader { dict, path };
+    let type_name = reader.required("type", |value, path| match value {
+        BencodexValue::Text(name) => Ok(name.clone()),
+        _ => Err(invalid(path)),
+    })?;
+
+    Ok(match type_name.as_str() {
+        "any" => Schema::Any,
+        "null" => Schema::Null,
+        "boolean" => Schema::Boolean,
+        "integer" => Schema::Integer {
+            min: reader.with("min", integer)?,
+            max: reader.with("max", integer)?,
+        },
+        "binary" => Schema::Binary {
+            min_length: reader.with("min_length", length)?,
+            max_length: reader.with("max_length", length)?,
+        },
+        "text" => Schema::Text {
+            pattern: reader.with("pattern", |value, path| match value {
+                BencodexValue::Text(pattern) => {
+                    Pattern::new(pattern).map_err(|e| SchemaDecodeError::InvalidPatternError {
+                        path: path.clone(),
+                        message: e.to_string(),
+                    })
+                }
+                _ => Err(invalid(path)),
+            })?,
+        },
+        "list" => Schema::List(Box::new(reader.required("items", from_value_impl)?)),
+        "tuple" => Schema::Tuple(reader.required("items", schema_list)?),
+        "dictionary" => Schema::Dictionary {
+            required: reader.with("required", schema_map)?.unwrap_or_default(),
+            optional: reader.with("optional", schema_map)?.unwrap_or_default(),
+            additional: reader.with("additional", from_value_impl)?.map(Box::new),
+        },
+        "union" => Schema::Union(reader.required("variants", schema_list)?),
+        _ => {
+            return Err(SchemaDecodeError::UnknownTypeError {
+                path: reader.path.clone().key("type"),
+                name: type_name,
+            })
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    mod from_value {
+        use super::super::*;
+
+        fn schema_value(entries: Vec<(&str, BencodexValue)>) -> BencodexValue {
+            BencodexValue::Dictionary(
+                entries
+                    .into_iter()
+                    .map(|(name, value)| (name.into(), value))
+                    .collect(),
+            )
+        }
+
+        #[test]
+        fn should_roundtrip() {
+            let schemas = vec![
+                Schema::Any,
+                Schema::Null,
+                Schema::Boolean,
+                Schema::Integer {
+                    min: Some((-1).into()),
+                    max: None,
+                },
+                Schema::Binary {
+                    min_length: None,
+                    max_length: Some(32),
+                },
+                Schema::Text {
+                    pattern: Some(Pattern::new("^[0-9a-f]+$").unwrap()),
+                },
+                Schema::List(Box::new(Schema::Null)),
+                Schema::Tuple(vec![Schema::Boolean, Schema::Any]),
+                Schema::Dictionary {
+                    required: BTreeMap::from([("a".into(), Schema::Null)]),
+                    optional: BTreeMap::from([(vec![0u8].into(), Schema::Boolean)]),
+                    additional: Some(Box::new(Schema::Any)),
+                },
+                Schema::Union(vec![Schema::Null, Schema::Text { pattern: None }]),
+            ];
+            for schema in schemas {
+                assert_eq!(Ok(schema.clone()), Schema::from_value(&schema.to_value()));
+            }
+        }
+
+        #[test]
+        fn should_ignore_unknown_keys() {
+            let value = schema_value(vec![
+                ("type", "null".into()),
+                ("description", "nothing".into()),
+            ]);
+            assert_eq!(Ok(Schema::Null), Schema::from_value(&value));
+        }
+
+        #[test]
+        fn should_return_error_with_path() {
+            let invalid = |path| Err(SchemaDecodeError::InvalidSchemaError { path });
+            let root = BencodexPath::root;
+
+            assert_eq!(invalid(root()), Schema::from_value(&BencodexValue::Null));
+            assert_eq!(
+                invalid(root()),
+                Schema::from_value(&schema_value(vec![("type", "list".into())]))
+            );
+            assert_eq!(
+                invalid(root().key("type")),
+                Schema::from_value(&schema_value(vec![("type", 1.into())]))
+            );
+            assert_eq!(
+                invalid(root().key("max_length")),
+                Schema::from_value(&schema_value(vec![
+                    ("type", "binary".into()),
+                    ("max_length", (-1).into()),
+                ]))
+            );
+            assert_eq!(
+                invalid(root().key("variants").index(1)),
+                Schema::from_value(&schema_value(vec![
+                    ("type", "union".into()),
+                    (
+                        "variants",
+                        vec![
+                            schema_value(vec![("type", "null".into())]),
+                            BencodexValue::Null
+                        ]
+                        .into()
+                    ),
+                ]))
+            );
+            assert!(matches!(
+                Schema::from_value(&schema_value(vec![
+                    ("type", "text".into()),
+                    ("pattern", "(".into()),
+                ])),
+                Err(SchemaDecodeError::InvalidPatternError { path, .. }) if path == root().key("pattern")
+            ));
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use super::{ContentStore, Digest};
+
+/// A [`ContentStore`] which keeps each node in its own file under a directory.
+///
+/// A node is stored at `<dir>/<first 2 hex digits>/<remaining 62 hex digits>` of its digest.
+/// Files are written to a temporary file first and then renamed, so a node file is never
+/// seen half-written.
+///
+/// ```
+/// use bencodex::BencodexValue;
+/// use bencodex::store::{ ContentStore, FileContentStore };
+///
+/// let dir = std::env::temp_dir().join("bencodex-file-content-store-doctest");
+/// let mut store = FileContentStore::open(&dir).unwrap();
+/// let value: BencodexValue = vec![BencodexValue::from(vec![1]), "foo".into()].into();
+/// let digest = store.put_value(&value).unwrap();
+///
+/// let store = FileContentStore::open(&dir).unwrap();
+/// assert_eq!(store.get_value(&digest).unwrap(), Some(value));
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct FileContentStore {
+    dir: PathBuf,
+}
+
+impl FileContentStore {
+    /// Opens a store in the given directory, creating the directory if it does not exist.
+    pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<FileContentStore> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(FileContentStore {
+            dir: dir.as_ref().to_path_buf(),
+        })
+    }
+
+    fn path(&self, digest: &Digest) -> PathBuf {
+        let hex = digest.to_string();
+        self.dir.join(&hex[..2]).join(&hex[2..])
+    }
+}
+
+impl ContentStore for FileContentStore {
+    fn get(&self, digest: &Digest) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(self.path(digest)) {
+            Ok(node) => Ok(Some(node)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn put(&mut self, digest: &Digest, node: &[u8]) -> io::Result<()> {
+        let path = self.path(digest);
+        let parent = path.parent().expect("node paths always have a parent");
+        fs::create_dir_all(parent)?;
+
+        let temp_path = path.with_extension("tmp");
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(node)?;
+        file.sync_all()?;
+        fs::rename(temp_path, path)
+    }
+
+    fn contains(&self, digest: &Digest) -> io::Result<bool> {
+        Ok(self.path(digest).is_file())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    mod file_content_store {
+        use super::super::*;
+        use crate::BencodexValue;
+
+        #[test]
+        fn should_persist_nodes() {
+            let dir = tempfile::tempdir().unwrap();
+            let value: BencodexValue =
+                vec![BencodexValue::from(vec![1, 2]), vec![1, 2].into()].into();
+
+            let mut store = FileContentStore::open(dir.path()).unwrap();
+            let digest = store.put_value(&value).unwrap();
+            assert!(store.contains(&digest).unwrap());
+
+            let hex = digest.to_string();
+            assert!(dir.path().join(&hex[..2]).join(&hex[2..]).is_file());
+
+            let store = FileContentStore::open(dir.path()).unwrap();
+            assert_eq!(Some(value), store.get_value(&digest).unwrap());
+        }
+
+        #[test]
+        fn should_return_none_for_unknown_digest() {
+            let dir = tempfile::tempdir().unwrap();
+            let store = FileContentStore::open(dir.path()).unwrap();
+            let digest = Digest::from([0; 32]);
+            assert!(!store.contains(&digest).unwrap());
+            assert_eq!(None, store.get(&digest).unwrap());
+        }
+    }
+}
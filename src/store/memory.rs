@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+use std::io;
+
+use super::{ContentStore, Digest};
+
+/// A [`ContentStore`] which keeps nodes in memory. It is mainly useful for tests.
+///
+/// ```
+/// use bencodex::BencodexValue;
+/// use bencodex::store::{ ContentStore, MemoryContentStore };
+///
+/// let mut store = MemoryContentStore::new();
+/// let value: BencodexValue = vec![1, 2, 3].into();
+/// let digest = store.put_value(&value).unwrap();
+///
+/// assert_eq!(store.get(&digest).unwrap(), Some(b"li1ei2ei3ee".to_vec()));
+/// assert_eq!(store.len(), 1);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct MemoryContentStore {
+    nodes: HashMap<Digest, Vec<u8>>,
+}
+
+impl MemoryContentStore {
+    pub fn new() -> MemoryContentStore {
+        MemoryContentStore::default()
+    }
+
+    /// Returns the number of stored nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+}
+
+impl ContentStore for MemoryContentStore {
+    fn get(&self, digest: &Digest) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.nodes.get(digest).cloned())
+    }
+
+    fn put(&mut self, digest: &Digest, node: &[u8]) -> io::Result<()> {
+        self.nodes.insert(digest.clone(), node.to_vec());
+        Ok(())
+    }
+
+    fn contains(&self, digest: &Digest) -> io::Result<bool> {
+        Ok(self.nodes.contains_key(digest))
+    }
+}
@@ -0,0 +1,397 @@
+//! A content-addressed store, which persists Bencodex values as Merkle DAGs.
+//!
+//! To use it, you should enable `store` feature.
+//!
+//! Every list and dictionary in a value is stored as its own *node*, under the [`Digest`] of
+//! its canonical encoding, so values which share subtrees, like versioned state snapshots,
+//! share their nodes in the store too. The stored bytes of a node are its canonical encoding,
+//! except that each list or dictionary directly inside it is replaced by a reference: `*`
+//! followed by a 64-byte Bencodex binary of the child's [`Digest`] and its *node digest*, the
+//! SHA-256 hash of the child node's bytes (e.g., `*64:...`). Other values stay inline.
+//!
+//! As a [`Digest`] is the SHA-256 hash of the canonical encoding, it identifies the same value
+//! whether it is in a store or not; see [`digest`]. As each node holds the node digests of its
+//! children, the node digests make a Merkle DAG, against which [`prove`] makes compact proofs
+//! that a subvalue is part of a value; see [`node_digest`].
+//!
+//! ```
+//! use bencodex::{ BencodexDictionary, BencodexValue };
+//! use bencodex::store::{ ContentStore, MemoryContentStore };
+//!
+//! let mut accounts = BencodexDictionary::new();
+//! accounts.insert("alice".into(), 100.into());
+//! let mut state = BencodexDictionary::new();
+//! state.insert("accounts".into(), BencodexValue::Dictionary(accounts));
+//! state.insert("height".into(), 1.into());
+//!
+//! let mut store = MemoryContentStore::new();
+//! let first = store.put_value(&BencodexValue::Dictionary(state.clone())).unwrap();
+//!
+//! state.insert("height".into(), 2.into());
+//! let second = store.put_value(&BencodexValue::Dictionary(state.clone())).unwrap();
+//!
+//! // The two snapshots share the node of "accounts".
+//! assert_eq!(store.len(), 3);
+//! assert_eq!(
+//!     store.get_value(&second).unwrap(),
+//!     Some(BencodexValue::Dictionary(state))
+//! );
+//! assert_ne!(first, second);
+//! ```
+
+mod file;
+mod memory;
+pub(crate) mod node;
+mod proof;
+
+use std::fmt;
+use std::io;
+
+use sha2::{Digest as _, Sha256};
+
+use crate::{BencodexValue, Decode, Encode};
+
+pub use file::FileContentStore;
+pub use memory::MemoryContentStore;
+pub use proof::{prove, verify_proof, MerkleProof};
+
+/// The SHA-256 digest of the canonical encoding of a value, which addresses its node in a
+/// [`ContentStore`].
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Clone)]
+pub struct Digest([u8; 32]);
+
+impl Digest {
+    /// The length of a digest in bytes.
+    pub const LENGTH: usize = 32;
+
+    /// Computes the digest of the given bytes.
+    pub fn of(bytes: &[u8]) -> Digest {
+        Digest(Sha256::digest(bytes).into())
+    }
+
+    /// Returns a digest made of the given bytes, or [`None`] if they are not 32 bytes long.
+    pub fn from_slice(bytes: &[u8]) -> Option<Digest> {
+        bytes.try_into().ok().map(Digest)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<[u8; 32]> for Digest {
+    fn from(bytes: [u8; 32]) -> Self {
+        Digest(bytes)
+    }
+}
+
+impl fmt::Debug for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Digest({})", self)
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+/// Computes the [`Digest`] of the given value, the SHA-256 hash of its canonical encoding,
+/// without storing anything.
+///
+/// It is the same digest which [`ContentStore::put_value`] returns. The encoding is streamed
+/// into the hasher, so the encoded bytes are not allocated.
+///
+/// ```
+/// use bencodex::{ BencodexValue, Encode };
+/// use bencodex::store::{ digest, ContentStore, Digest, MemoryContentStore };
+///
+/// let value: BencodexValue = vec![vec![1, 2].into(), BencodexValue::Null].into();
+/// let mut store = MemoryContentStore::new();
+/// assert_eq!(store.put_value(&value).unwrap(), digest(&value));
+///
+/// let mut encoded = vec![];
+/// (&value).encode(&mut encoded).unwrap();
+/// assert_eq!(digest(&value), Digest::of(&encoded));
+/// ```
+pub fn digest(value: &BencodexValue) -> Digest {
+    let mut hasher = Sha256::new();
+    value
+        .encode(&mut hasher)
+        .expect("writing to a hasher never fails");
+    Digest(hasher.finalize().into())
+}
+
+/// Computes the node digest of the given value, the SHA-256 hash of the bytes of its root node,
+/// without storing anything.
+///
+/// It is the same digest which [`ContentStore::node_digest`] returns for the value, and the
+/// root which [`verify_proof`] checks proofs against. For a value which is neither a list nor
+/// a dictionary, it is the same as its [`digest`].
+///
+/// ```
+/// use bencodex::BencodexValue;
+/// use bencodex::store::{ digest, node_digest, ContentStore, MemoryContentStore };
+///
+/// let value: BencodexValue = vec![vec![1, 2].into(), BencodexValue::Null].into();
+/// let mut store = MemoryContentStore::new();
+/// let key = store.put_value(&value).unwrap();
+/// assert_eq!(store.node_digest(&key).unwrap(), Some(node_digest(&value)));
+/// assert_ne!(key, node_digest(&value));
+///
+/// let scalar = BencodexValue::from("foo");
+/// assert_eq!(digest(&scalar), node_digest(&scalar));
+/// ```
+pub fn node_digest(value: &BencodexValue) -> Digest {
+    reference(value).node_digest
+}
+
+fn reference(value: &BencodexValue) -> node::Reference {
+    let node = node::encode_node(value, &mut |child| Ok(reference(child)))
+        .expect("writing to a Vec never fails");
+    node::Reference {
+        digest: digest(value),
+        node_digest: Digest::of(&node),
+    }
+}
+
+/// `ContentStore` is a trait for a storage which keeps nodes by their [`Digest`].
+///
+/// Implementors only provide raw node access; [`put_value`](ContentStore::put_value) and
+/// [`get_value`](ContentStore::get_value) split values into nodes and reassemble them.
+pub trait ContentStore {
+    /// Returns the bytes of the node of the given digest, or [`None`] if there is no such node.
+    fn get(&self, digest: &Digest) -> io::Result<Option<Vec<u8>>>;
+
+    /// Stores the node bytes under the given digest, which is the digest of the canonical
+    /// encoding of the value the node stands for.
+    fn put(&mut self, digest: &Digest, node: &[u8]) -> io::Result<()>;
+
+    /// Returns whether the node of the given digest is stored.
+    fn contains(&self, digest: &Digest) -> io::Result<bool> {
+        Ok(self.get(digest)?.is_some())
+    }
+
+    /// Returns the node digest of the value of the given digest, which is the root that
+    /// [`verify_proof`] checks the proofs made by [`prove`] against, or [`None`] if there is
+    /// no such node.
+    fn node_digest(&self, digest: &Digest) -> io::Result<Option<Digest>> {
+        Ok(self.get(digest)?.map(|node| Digest::of(&node)))
+    }
+
+    /// Stores the given value as a Merkle DAG, and returns its [`Digest`].
+    ///
+    /// If the value is already stored, nothing is written, as its descendants are stored
+    /// before it.
+    fn put_value(&mut self, value: &BencodexValue) -> io::Result<Digest> {
+        put_node(self, value).map(|reference| reference.digest)
+    }
+
+    /// Loads the value of the given digest, or [`None`] if there is no such node.
+    ///
+    /// It fails with [`io::ErrorKind::NotFound`] if a descendant node is missing, and with
+    /// [`io::ErrorKind::InvalidData`] if a node is malformed or does not match its digest.
+    fn get_value(&self, digest: &Digest) -> io::Result<Option<BencodexValue>> {
+        match load_encoded(self, digest, None)? {
+            Some(encoded) => encoded
+                .decode()
+                .map(Some)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Stores the node of the value and its descendants, and returns the reference to it.
+fn put_node<S: ContentStore + ?Sized>(
+    store: &mut S,
+    value: &BencodexValue,
+) -> io::Result<node::Reference> {
+    let digest = digest(value);
+    let node = match store.get(&digest)? {
+        Some(node) => node,
+        None => {
+            let node = node::encode_node(value, &mut |child| put_node(store, child))?;
+            store.put(&digest, &node)?;
+            node
+        }
+    };
+
+    Ok(node::Reference {
+        digest,
+        node_digest: Digest::of(&node),
+    })
+}
+
+/// Loads the canonical encoding of the value of the given digest by expanding its node, and
+/// checks that it matches the digest, and the node digest if it is given.
+fn load_encoded<S: ContentStore + ?Sized>(
+    store: &S,
+    digest: &Digest,
+    node_digest: Option<&Digest>,
+) -> io::Result<Option<Vec<u8>>> {
+    let node = match store.get(digest)? {
+        Some(node) => node,
+        None => return Ok(None),
+    };
+    let mismatch = || {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("the loaded node does not match {}", digest),
+        )
+    };
+    if node_digest.is_some_and(|node_digest| &Digest::of(&node) != node_digest) {
+        return Err(mismatch());
+    }
+
+    let encoded = node::expand_node(&node, &mut |child| {
+        load_encoded(store, &child.digest, Some(&child.node_digest))?.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no node for {}", child.digest),
+            )
+        })
+    })?;
+    if &Digest::of(&encoded) != digest {
+        return Err(mismatch());
+    }
+
+    Ok(Some(encoded))
+}
+
+#[cfg(test)]
+mod tests {
+    mod digest {
+        use super::super::*;
+
+        #[test]
+        fn should_hash_encoding_of_scalars() {
+            let value = BencodexValue::from("foo");
+            assert_eq!(Digest::of(b"u3:foo"), digest(&value));
+        }
+
+        #[test]
+        fn should_hash_canonical_encoding() {
+            let child: BencodexValue = vec![1].into();
+            let value: BencodexValue = vec![child.clone()].into();
+            assert_eq!(Digest::of(b"li1ee"), digest(&child));
+            assert_eq!(Digest::of(b"lli1eee"), digest(&value));
+        }
+
+        #[test]
+        fn should_format_as_hex() {
+            let digest = Digest::from([0xab; 32]);
+            assert_eq!("ab".repeat(32), digest.to_string());
+            assert_eq!(
+                format!("Digest({})", "ab".repeat(32)),
+                format!("{:?}", digest)
+            );
+        }
+    }
+
+    mod content_store {
+        use super::super::*;
+        use crate::BencodexDictionary;
+
+        #[test]
+        fn should_round_trip_nested_values() {
+            let mut dict = BencodexDictionary::new();
+            dict.insert(
+                "list".into(),
+                vec![BencodexValue::from(vec![1, 2]), "a".into()].into(),
+            );
+            dict.insert(
+                vec![0u8].into(),
+                BencodexValue::Dictionary(Default::default()),
+            );
+            dict.insert("null".into(), BencodexValue::Null);
+            let value = BencodexValue::Dictionary(dict);
+
+            let mut store = MemoryContentStore::new();
+            let digest = store.put_value(&value).unwrap();
+            assert_eq!(4, store.len());
+            assert_eq!(Some(value), store.get_value(&digest).unwrap());
+        }
+
+        #[test]
+        fn should_refer_to_children_by_digests() {
+            let child: BencodexValue = vec![1].into();
+            let value: BencodexValue = vec![child.clone(), "a".into()].into();
+
+            let mut store = MemoryContentStore::new();
+            let root = store.put_value(&value).unwrap();
+            assert_eq!(Digest::of(b"lli1eeu1:ae"), root);
+
+            let mut node = b"l*64:".to_vec();
+            node.extend_from_slice(digest(&child).as_bytes());
+            node.extend_from_slice(Digest::of(b"li1ee").as_bytes());
+            node.extend_from_slice(b"u1:ae");
+            assert_eq!(Some(node.clone()), store.get(&root).unwrap());
+            assert_eq!(Some(b"li1ee".to_vec()), store.get(&digest(&child)).unwrap());
+            assert_eq!(Some(Digest::of(&node)), store.node_digest(&root).unwrap());
+            assert_eq!(Digest::of(&node), node_digest(&value));
+        }
+
+        #[test]
+        fn should_store_shared_subtrees_once() {
+            let shared: BencodexValue = vec![1, 2, 3].into();
+            let value: BencodexValue = vec![shared.clone(), shared.clone(), shared].into();
+
+            let mut store = MemoryContentStore::new();
+            store.put_value(&value).unwrap();
+            assert_eq!(2, store.len());
+        }
+
+        #[test]
+        fn should_return_none_for_unknown_digest() {
+            let store = MemoryContentStore::new();
+            assert_eq!(None, store.get_value(&Digest::from([0; 32])).unwrap());
+        }
+
+        #[test]
+        fn should_fail_with_missing_child() {
+            let child: BencodexValue = vec![1].into();
+            let value: BencodexValue = vec![child.clone()].into();
+
+            let mut source = MemoryContentStore::new();
+            let root = source.put_value(&value).unwrap();
+
+            let mut store = MemoryContentStore::new();
+            let node = source.get(&root).unwrap().unwrap();
+            store.put(&root, &node).unwrap();
+            assert_eq!(
+                io::ErrorKind::NotFound,
+                store.get_value(&root).unwrap_err().kind()
+            );
+        }
+
+        #[test]
+        fn should_fail_with_tampered_child() {
+            let child: BencodexValue = vec![1].into();
+            let value: BencodexValue = vec![child.clone()].into();
+
+            let mut store = MemoryContentStore::new();
+            let root = store.put_value(&value).unwrap();
+            store.put(&digest(&child), b"li1ei1ee").unwrap();
+            assert_eq!(
+                io::ErrorKind::InvalidData,
+                store.get_value(&root).unwrap_err().kind()
+            );
+        }
+
+        #[test]
+        fn should_fail_with_tampered_node() {
+            let mut store = MemoryContentStore::new();
+            let digest = store.put_value(&vec![1].into()).unwrap();
+            store.put(&digest, b"li2ee").unwrap();
+            assert_eq!(
+                io::ErrorKind::InvalidData,
+                store.get_value(&digest).unwrap_err().kind()
+            );
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use std::io;
+use std::ops::Range;
+
+use super::Digest;
+use crate::codec::decode::decode_impl;
+use crate::{BencodexKey, BencodexValue, DecodeError, Encode, PathSegment};
+
+/// The byte which starts a reference to a child node in place of the child value.
+pub(crate) const REFERENCE_PREFIX: u8 = b'*';
+
+/// A reference to a child node: the [`Digest`] of the child value, which addresses the node,
+/// and the digest of the node's bytes, which chains the nodes into a Merkle DAG.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) struct Reference {
+    pub(crate) digest: Digest,
+    pub(crate) node_digest: Digest,
+}
+
+/// Writes a reference to a child node: `*` followed by its digest and its node digest as a
+/// Bencodex binary.
+fn encode_reference(reference: &Reference, node: &mut Vec<u8>) -> io::Result<()> {
+    node.push(REFERENCE_PREFIX);
+    [
+        reference.digest.as_bytes(),
+        reference.node_digest.as_bytes(),
+    ]
+    .concat()
+    .encode(node)
+}
+
+fn encode_child(
+    value: &BencodexValue,
+    node: &mut Vec<u8>,
+    child_reference: &mut dyn FnMut(&BencodexValue) -> io::Result<Reference>,
+) -> io::Result<()> {
+    match value {
+        BencodexValue::List(_) | BencodexValue::Dictionary(_) => {
+            encode_reference(&child_reference(value)?, node)
+        }
+        value => value.encode(node),
+    }
+}
+
+/// Encodes the node of a value: its canonical encoding, except that each list or dictionary
+/// directly inside it is replaced by a reference to the child node, given by
+/// `child_reference`.
+pub(crate) fn encode_node(
+    value: &BencodexValue,
+    child_reference: &mut dyn FnMut(&BencodexValue) -> io::Result<Reference>,
+) -> io::Result<Vec<u8>> {
+    let mut node = vec![];
+    match value {
+        BencodexValue::List(list) => {
+            node.push(b'l');
+            for item in list {
+                encode_child(item, &mut node, child_reference)?;
+            }
+            node.push(b'e');
+        }
+        BencodexValue::Dictionary(dict) => {
+            node.push(b'd');
+            for (key, value) in dict {
+                key.encode(&mut node)?;
+                encode_child(value, &mut node, child_reference)?;
+            }
+            node.push(b'e');
+        }
+        value => value.encode(&mut node)?,
+    }
+
+    Ok(node)
+}
+
+/// A value decoded from a node, whose children are either inlined or referenced.
+#[derive(Debug, PartialEq)]
+pub(crate) enum NodeChild {
+    Value(BencodexValue),
+    Reference(Reference),
+}
+
+fn invalid_node(error: DecodeError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+fn decode_child(node: &[u8], start: usize) -> Result<(NodeChild, usize), DecodeError> {
+    if node.get(start) != Some(&REFERENCE_PREFIX) {
+        return decode_impl(node, start).map(|(value, size)| (NodeChild::Value(value), size));
+    }
+
+    match decode_impl(node, start + 1)? {
+        (BencodexValue::Binary(digests), size) if digests.len() == Digest::LENGTH * 2 => {
+            let (digest, node_digest) = digests.split_at(Digest::LENGTH);
+            Ok((
+                NodeChild::Reference(Reference {
+                    digest: Digest::from_slice(digest).expect("the length is checked"),
+                    node_digest: Digest::from_slice(node_digest).expect("the length is checked"),
+                }),
+                size + 1,
+            ))
+        }
+        (BencodexValue::Binary(_), _) => Err(DecodeError::InvalidBencodexValueError),
+        _ => Err(DecodeError::UnexpectedTokenError {
+            token: node[start + 1],
+            point: start + 1,
+        }),
+    }
+}
+
+fn expect_end(node: &[u8], index: usize) -> Result<(), DecodeError> {
+    match node.get(index) {
+        Some(b'e') if index + 1 == node.len() => Ok(()),
+        Some(&token) => Err(DecodeError::UnexpectedTokenError {
+            token,
+            point: index,
+        }),
+        None => Err(DecodeError::InvalidBencodexValueError),
+    }
+}
+
+/// Decodes a list node into its children, with the range of each child in the node.
+pub(crate) fn decode_list_node(node: &[u8]) -> Result<Vec<(NodeChild, Range<usize>)>, DecodeError> {
+    let mut index = 1;
+    let mut children = vec![];
+    while index < node.len() && node[index] != b'e' {
+        let (child, size) = decode_child(node, index)?;
+        children.push((child, index..index + size));
+        index += size;
+    }
+    expect_end(node, index)?;
+
+    Ok(children)
+}
+
+/// Decodes a dictionary node into its entries, with the range of each child in the node.
+pub(crate) fn decode_dictionary_node(
+    node: &[u8],
+) -> Result<Vec<(BencodexKey, NodeChild, Range<usize>)>, DecodeError> {
+    let mut index = 1;
+    let mut entries: Vec<(BencodexKey, NodeChild, Range<usize>)> = vec![];
+    while index < node.len() && node[index] != b'e' {
+        let (key, size) = decode_impl(node, index)?;
+        let key = match key {
+            BencodexValue::Text(s) => BencodexKey::Text(s),
+            BencodexValue::Binary(b) => BencodexKey::Binary(b),
+            _ => return Err(DecodeError::InvalidBencodexValueError),
+        };
+        if entries.last().is_some_and(|(last, _, _)| last >= &key) {
+            return Err(DecodeError::InvalidBencodexValueError);
+        }
+        index += size;
+
+        let (child, size) = decode_child(node, index)?;
+        entries.push((key, child, index..index + size));
+        index += size;
+    }
+    expect_end(node, index)?;
+
+    Ok(entries)
+}
+
+/// Finds the child at the segment in a node, with its range in the node. Returns [`None`]
+/// if the node has no such child.
+pub(crate) fn find_child(
+    node: &[u8],
+    segment: &PathSegment,
+) -> Result<Option<(NodeChild, Range<usize>)>, DecodeError> {
+    Ok(match (node.first(), segment) {
+        (Some(b'l'), PathSegment::Index(index)) => decode_list_node(node)?.into_iter().nth(*index),
+        (Some(b'd'), PathSegment::Key(key)) => decode_dictionary_node(node)?
+            .into_iter()
+            .find(|(k, _, _)| k == key)
+            .map(|(_, child, range)| (child, range)),
+        _ => None,
+    })
+}
+
+/// Expands a node into the canonical encoding of its value, loading the canonical encoding
+/// of each referenced child through `load_child`.
+pub(crate) fn expand_node(
+    node: &[u8],
+    load_child: &mut dyn FnMut(&Reference) -> io::Result<Vec<u8>>,
+) -> io::Result<Vec<u8>> {
+    let mut expand = |child: NodeChild, encoded: &mut Vec<u8>| match child {
+        NodeChild::Value(value) => value.encode(encoded),
+        NodeChild::Reference(reference) => {
+            let child = load_child(&reference)?;
+            // Only lists and dictionaries are stored as their own nodes.
+            if !matches!(child.first(), Some(b'l' | b'd')) {
+                return Err(invalid_node(DecodeError::InvalidBencodexValueError));
+            }
+            encoded.extend_from_slice(&child);
+            Ok(())
+        }
+    };
+
+    let mut encoded = vec![];
+    match node.first() {
+        Some(b'l') => {
+            encoded.push(b'l');
+            for (child, _) in decode_list_node(node).map_err(invalid_node)? {
+                expand(child, &mut encoded)?;
+            }
+            encoded.push(b'e');
+        }
+        Some(b'd') => {
+            encoded.push(b'd');
+            for (key, child, _) in decode_dictionary_node(node).map_err(invalid_node)? {
+                key.encode(&mut encoded)?;
+                expand(child, &mut encoded)?;
+            }
+            encoded.push(b'e');
+        }
+        _ => match decode_impl(node, 0).map_err(invalid_node)? {
+            (_, size) if size == node.len() => encoded.extend_from_slice(node),
+            _ => return Err(invalid_node(DecodeError::InvalidBencodexValueError)),
+        },
+    }
+
+    Ok(encoded)
+}
+
+#[cfg(test)]
+mod tests {
+    mod encode_node {
+        use super::super::*;
+        use crate::BencodexDictionary;
+
+        #[test]
+        fn should_replace_containers_with_references() {
+            let value: BencodexValue = vec![
+                BencodexValue::from(1),
+                vec![BencodexValue::Null].into(),
+                BencodexValue::Dictionary(BencodexDictionary::new()),
+            ]
+            .into();
+            let reference = Reference {
+                digest: Digest::from([0xab; 32]),
+                node_digest: Digest::from([0xcd; 32]),
+            };
+            let node = encode_node(&value, &mut |_| Ok(reference.clone())).unwrap();
+
+            let mut expected = b"li1e".to_vec();
+            for _ in 0..2 {
+                expected.extend_from_slice(b"*64:");
+                expected.extend_from_slice(&[0xab; 32]);
+                expected.extend_from_slice(&[0xcd; 32]);
+            }
+            expected.push(b'e');
+            assert_eq!(expected, node);
+        }
+
+        #[test]
+        fn should_encode_scalars_as_usual() {
+            let node = encode_node(&"foo".into(), &mut |_| unreachable!()).unwrap();
+            assert_eq!(b"u3:foo".to_vec(), node);
+        }
+    }
+
+    mod find_child {
+        use super::super::*;
+
+        #[test]
+        fn should_return_child_with_range() {
+            let mut node = b"du1:ai1eu1:b*64:".to_vec();
+            node.extend_from_slice(&[0xab; 32]);
+            node.extend_from_slice(&[0xcd; 32]);
+            node.push(b'e');
+
+            assert_eq!(
+                Some((NodeChild::Value(1.into()), 5..8)),
+                find_child(&node, &PathSegment::Key("a".into())).unwrap()
+            );
+            let reference = Reference {
+                digest: Digest::from([0xab; 32]),
+                node_digest: Digest::from([0xcd; 32]),
+            };
+            assert_eq!(
+                Some((NodeChild::Reference(reference), 12..80)),
+                find_child(&node, &PathSegment::Key("b".into())).unwrap()
+            );
+            assert_eq!(
+                None,
+                find_child(&node, &PathSegment::Key("c".into())).unwrap()
+            );
+            assert_eq!(None, find_child(&node, &PathSegment::Index(0)).unwrap());
+            assert_eq!(None, find_child(b"i1e", &PathSegment::Index(0)).unwrap());
+        }
+    }
+
+    mod expand_node {
+        use super::super::*;
+
+        fn reference_bytes() -> Vec<u8> {
+            let mut bytes = b"*64:".to_vec();
+            bytes.extend_from_slice(&[0xab; 32]);
+            bytes.extend_from_slice(&[0xcd; 32]);
+            bytes
+        }
+
+        #[test]
+        fn should_load_references() {
+            let mut node = b"du1:a".to_vec();
+            node.extend_from_slice(&reference_bytes());
+            node.extend_from_slice(b"u1:bi1ee");
+
+            let encoded = expand_node(&node, &mut |reference| {
+                assert_eq!(Digest::from([0xab; 32]), reference.digest);
+                assert_eq!(Digest::from([0xcd; 32]), reference.node_digest);
+                Ok(b"li1ee".to_vec())
+            })
+            .unwrap();
+            assert_eq!(b"du1:ali1eeu1:bi1ee".to_vec(), encoded);
+        }
+
+        #[test]
+        fn should_return_error_with_referenced_scalar() {
+            let mut node = b"l".to_vec();
+            node.extend_from_slice(&reference_bytes());
+            node.push(b'e');
+
+            let error = expand_node(&node, &mut |_| Ok(b"i1e".to_vec())).unwrap_err();
+            assert_eq!(io::ErrorKind::InvalidData, error.kind());
+        }
+
+        #[test]
+        fn should_return_error_with_invalid_node() {
+            let mut short_reference = b"l*32:".to_vec();
+            short_reference.extend_from_slice(&[0xab; 32]);
+            short_reference.push(b'e');
+            let nodes: Vec<&[u8]> = vec![
+                b"li1e",
+                b"li1eex",
+                b"l*3:abce",
+                &short_reference,
+                b"l*nee",
+                b"du1:bnu1:ane",
+                b"du1:anu1:ane",
+                b"i1ex",
+                b"",
+            ];
+            for node in nodes {
+                let error = expand_node(node, &mut |_| unreachable!()).unwrap_err();
+                assert_eq!(io::ErrorKind::InvalidData, error.kind());
+            }
+        }
+    }
+}
@@ -0,0 +1,453 @@
+use std::io;
+
+use super::node::{self, NodeChild};
+use super::{node_digest, ContentStore, Digest};
+use crate::{BencodexPath, BencodexValue, DecodeError, Encode};
+
+/// A proof that a subvalue is at a path of a value with a known node digest, the root of
+/// the Merkle DAG the value is stored as in a [`ContentStore`].
+///
+/// It consists of a *frame* for each list or dictionary along the path, from the root: the
+/// bytes of the container's node before and after the node digest of the child on the path,
+/// or before and after the child itself if it is inlined. As a node holds the other lists and
+/// dictionaries in it as references, a frame holds the digests of the siblings rather than
+/// their encodings, and the proof grows with the depth of the path, not with the size of the
+/// value. Filling each frame with the node digest of the node below, from the innermost
+/// one, rebuilds the nodes along the path up to the root node.
+///
+/// A proof converts to and from a Bencodex list of `[prefix, suffix]` pairs of binaries, so
+/// it can be sent along with the subvalue.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct MerkleProof {
+    frames: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl MerkleProof {
+    pub fn new(frames: Vec<(Vec<u8>, Vec<u8>)>) -> MerkleProof {
+        MerkleProof { frames }
+    }
+
+    /// Returns the prefixes and suffixes of the nodes along the path, from the root.
+    pub fn frames(&self) -> &[(Vec<u8>, Vec<u8>)] {
+        &self.frames
+    }
+}
+
+impl From<MerkleProof> for BencodexValue {
+    fn from(proof: MerkleProof) -> Self {
+        BencodexValue::List(
+            proof
+                .frames
+                .into_iter()
+                .map(|(prefix, suffix)| {
+                    BencodexValue::List(vec![
+                        BencodexValue::Binary(prefix),
+                        BencodexValue::Binary(suffix),
+                    ])
+                })
+                .collect(),
+        )
+    }
+}
+
+impl TryFrom<BencodexValue> for MerkleProof {
+    type Error = DecodeError;
+
+    fn try_from(value: BencodexValue) -> Result<Self, Self::Error> {
+        match value {
+            BencodexValue::List(list) => list
+                .into_iter()
+                .map(|frame| match frame {
+                    BencodexValue::List(frame) => match <[BencodexValue; 2]>::try_from(frame) {
+                        Ok([BencodexValue::Binary(prefix), BencodexValue::Binary(suffix)]) => {
+                            Ok((prefix, suffix))
+                        }
+                        _ => Err(DecodeError::InvalidBencodexValueError),
+                    },
+                    _ => Err(DecodeError::InvalidBencodexValueError),
+                })
+                .collect::<Result<_, _>>()
+                .map(MerkleProof::new),
+            _ => Err(DecodeError::InvalidBencodexValueError),
+        }
+    }
+}
+
+fn invalid_node(error: DecodeError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+/// Produces a proof that the subvalue at `path` is part of the value of the digest `root` in
+/// the store. Returns [`None`] if there is no such value or no subvalue at the path.
+///
+/// Only the nodes along the path are loaded, and the proof is checked against the node
+/// digest of the value, which [`ContentStore::node_digest`] returns.
+///
+/// # Examples
+///
+/// ```
+/// use bencodex::{ BencodexDictionary, BencodexPath, BencodexValue };
+/// use bencodex::store::{ prove, verify_proof, ContentStore, MemoryContentStore };
+///
+/// let mut accounts = BencodexDictionary::new();
+/// accounts.insert("alice".into(), 100.into());
+/// accounts.insert("bob".into(), vec![0u8; 1024].into());
+/// let mut state = BencodexDictionary::new();
+/// state.insert("accounts".into(), BencodexValue::Dictionary(accounts));
+/// state.insert("blocks".into(), vec![vec![0u8; 1024]; 100].into());
+///
+/// let mut store = MemoryContentStore::new();
+/// let digest = store.put_value(&BencodexValue::Dictionary(state)).unwrap();
+/// let root = store.node_digest(&digest).unwrap().unwrap();
+///
+/// let path = BencodexPath::root().key("accounts").key("alice");
+/// let proof = prove(&store, &digest, &path).unwrap().unwrap();
+/// assert!(verify_proof(&root, &path, &100.into(), &proof));
+/// assert!(!verify_proof(&root, &path, &200.into(), &proof));
+///
+/// // The blocks are in the proof only as their digests.
+/// let size: usize = proof.frames().iter().map(|(p, s)| p.len() + s.len()).sum();
+/// assert!(size < 2048);
+/// ```
+pub fn prove<S: ContentStore + ?Sized>(
+    store: &S,
+    root: &Digest,
+    path: &BencodexPath,
+) -> io::Result<Option<MerkleProof>> {
+    let mut current = match store.get(root)? {
+        Some(node) => node,
+        None => return Ok(None),
+    };
+    let mut segments = path.into_iter().peekable();
+    let mut frames = vec![];
+    while let Some(segment) = segments.next() {
+        let (child, range) = match node::find_child(&current, segment).map_err(invalid_node)? {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+        match child {
+            NodeChild::Value(_) if segments.peek().is_some() => return Ok(None),
+            NodeChild::Value(_) => {
+                frames.push((
+                    current[..range.start].to_vec(),
+                    current[range.end..].to_vec(),
+                ));
+            }
+            NodeChild::Reference(reference) => {
+                frames.push((
+                    current[..range.end - Digest::LENGTH].to_vec(),
+                    current[range.end..].to_vec(),
+                ));
+                current = store.get(&reference.digest)?.ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("no node for {}", reference.digest),
+                    )
+                })?;
+            }
+        }
+    }
+
+    Ok(Some(MerkleProof::new(frames)))
+}
+
+/// Verifies that `subvalue` is at `path` of the value whose node digest is `root`, using a
+/// proof produced by [`prove`].
+///
+/// From the innermost frame, the frame is filled with the encoding of `subvalue` or the node
+/// digest of the node below, and the filled bytes must be a node whose child at the segment
+/// of the path is exactly what is filled. The node digest of the outermost node must be
+/// `root`. A malformed proof is rejected rather than making it panic.
+pub fn verify_proof(
+    root: &Digest,
+    path: &BencodexPath,
+    subvalue: &BencodexValue,
+    proof: &MerkleProof,
+) -> bool {
+    if proof.frames.len() != path.len() {
+        return false;
+    }
+
+    let segments: Vec<_> = path.into_iter().collect();
+    let mut below = node_digest(subvalue);
+    for (depth, ((prefix, suffix), segment)) in proof.frames.iter().zip(segments).enumerate().rev()
+    {
+        let mut filled = prefix.clone();
+        let inline = depth + 1 == proof.frames.len()
+            && !matches!(
+                subvalue,
+                BencodexValue::List(_) | BencodexValue::Dictionary(_)
+            );
+        if inline {
+            subvalue
+                .encode(&mut filled)
+                .expect("writing to a Vec never fails");
+        } else {
+            filled.extend_from_slice(below.as_bytes());
+        }
+        let end = filled.len();
+        filled.extend_from_slice(suffix);
+
+        let matches = match node::find_child(&filled, segment) {
+            Ok(Some((NodeChild::Value(value), range))) => {
+                inline && range == (prefix.len()..end) && &value == subvalue
+            }
+            Ok(Some((NodeChild::Reference(reference), range))) => {
+                !inline
+                    && range.end == end
+                    && reference.node_digest == below
+                    && (depth + 1 < proof.frames.len()
+                        || reference.digest == super::digest(subvalue))
+            }
+            _ => false,
+        };
+        if !matches {
+            return false;
+        }
+        below = Digest::of(&filled);
+    }
+
+    &below == root
+}
+
+#[cfg(test)]
+mod tests {
+    mod verify_proof {
+        use super::super::*;
+        use crate::store::MemoryContentStore;
+        use crate::BencodexDictionary;
+
+        fn state() -> BencodexValue {
+            let mut dict = BencodexDictionary::new();
+            dict.insert("height".into(), 1.into());
+            dict.insert(
+                "txs".into(),
+                vec![
+                    BencodexValue::from(vec![1u8, 2, 3]),
+                    vec![BencodexValue::from("nested")].into(),
+                ]
+                .into(),
+            );
+            dict.insert("version".into(), 2.into());
+            BencodexValue::Dictionary(dict)
+        }
+
+        /// Stores the value, and returns the store, its digest and its node digest.
+        fn store(value: &BencodexValue) -> (MemoryContentStore, Digest, Digest) {
+            let mut store = MemoryContentStore::new();
+            let digest = store.put_value(value).unwrap();
+            let root = store.node_digest(&digest).unwrap().unwrap();
+            (store, digest, root)
+        }
+
+        fn prove_value(value: &BencodexValue, path: &BencodexPath) -> MerkleProof {
+            let (store, digest, _) = store(value);
+            prove(&store, &digest, path).unwrap().unwrap()
+        }
+
+        #[test]
+        fn should_accept_valid_proofs() {
+            let value = state();
+            let (store, digest, root) = store(&value);
+            let paths = vec![
+                BencodexPath::root(),
+                BencodexPath::root().key("height"),
+                BencodexPath::root().key("txs"),
+                BencodexPath::root().key("txs").index(0),
+                BencodexPath::root().key("txs").index(1),
+                BencodexPath::root().key("txs").index(1).index(0),
+                BencodexPath::root().key("version"),
+            ];
+            for path in paths {
+                let proof = prove(&store, &digest, &path).unwrap().unwrap();
+                assert_eq!(path.len(), proof.frames().len());
+                let subvalue = value.get_path(&path).unwrap();
+                assert!(verify_proof(&root, &path, subvalue, &proof), "{}", path);
+            }
+        }
+
+        #[test]
+        fn should_hold_digests_of_siblings() {
+            let sibling: BencodexValue = vec![BencodexValue::from(vec![0u8; 4096]); 64].into();
+            let mut dict = BencodexDictionary::new();
+            dict.insert("a".into(), 1.into());
+            dict.insert("b".into(), sibling.clone());
+            let value = BencodexValue::Dictionary(dict);
+            let path = BencodexPath::root().key("a");
+
+            let proof = prove_value(&value, &path);
+            let mut suffix = b"u1:b*64:".to_vec();
+            suffix.extend_from_slice(crate::store::digest(&sibling).as_bytes());
+            suffix.extend_from_slice(node_digest(&sibling).as_bytes());
+            suffix.push(b'e');
+            assert_eq!(&[(b"du1:a".to_vec(), suffix)], proof.frames());
+            assert!(verify_proof(&node_digest(&value), &path, &1.into(), &proof));
+        }
+
+        #[test]
+        fn should_prove_deep_paths() {
+            let mut value = BencodexValue::from("leaf");
+            let mut path = BencodexPath::root();
+            for _ in 0..100 {
+                value = vec![BencodexValue::Null, value].into();
+                path = path.index(1);
+            }
+
+            let (store, digest, root) = store(&value);
+            let proof = prove(&store, &digest, &path).unwrap().unwrap();
+            assert!(verify_proof(&root, &path, &"leaf".into(), &proof));
+        }
+
+        #[test]
+        fn should_reject_wrong_subvalue() {
+            let value = state();
+            let root = node_digest(&value);
+            let path = BencodexPath::root().key("txs").index(1);
+            let proof = prove_value(&value, &path);
+            let wrong: BencodexValue = vec![BencodexValue::from("other")].into();
+            assert!(!verify_proof(&root, &path, &wrong, &proof));
+            assert!(!verify_proof(&root, &path, &"nested".into(), &proof));
+        }
+
+        #[test]
+        fn should_reject_wrong_root() {
+            let value = state();
+            let path = BencodexPath::root().key("height");
+            let proof = prove_value(&value, &path);
+            assert!(!verify_proof(
+                &crate::store::digest(&value),
+                &path,
+                &1.into(),
+                &proof
+            ));
+        }
+
+        #[test]
+        fn should_reject_wrong_path() {
+            let value = state();
+            let root = node_digest(&value);
+            let path = BencodexPath::root().key("txs").index(0);
+            let proof = prove_value(&value, &path);
+            let subvalue = value.get_path(&path).unwrap();
+            assert!(!verify_proof(
+                &root,
+                &BencodexPath::root().key("txs").index(1),
+                subvalue,
+                &proof
+            ));
+            assert!(!verify_proof(
+                &root,
+                &BencodexPath::root().key("txs"),
+                subvalue,
+                &proof
+            ));
+            assert!(!verify_proof(
+                &root,
+                &BencodexPath::root().key("height").index(0),
+                subvalue,
+                &proof
+            ));
+        }
+
+        #[test]
+        fn should_reject_tampered_proof() {
+            let value = state();
+            let root = node_digest(&value);
+            let path = BencodexPath::root().key("height");
+            let mut frames = prove_value(&value, &path).frames().to_vec();
+            frames[0].1 = b"e".to_vec();
+            assert!(!verify_proof(
+                &root,
+                &path,
+                &1.into(),
+                &MerkleProof::new(frames)
+            ));
+
+            // Moving a byte of the sibling digest across the frame keeps the node bytes, but
+            // the filled child is no longer the one on the path.
+            let path = BencodexPath::root().key("txs").index(1).index(0);
+            let mut frames = prove_value(&value, &path).frames().to_vec();
+            let last = frames[0].0.pop().unwrap();
+            frames[0].1.insert(0, last);
+            assert!(!verify_proof(
+                &root,
+                &path,
+                &"nested".into(),
+                &MerkleProof::new(frames)
+            ));
+        }
+
+        #[test]
+        fn should_reject_crafted_proofs_without_panicking() {
+            let path = BencodexPath::root().key("a");
+            let crafted: Vec<(&[u8], &[u8])> = vec![
+                // The filled node has a duplicate key.
+                (b"du1:anu1:a", b"e"),
+                // The filled node has a length larger than the node.
+                (b"du1:a", b"u99999999999999999999999:xe"),
+                (b"du1:a", b"99999999999999999999999:xe"),
+                // The filled node is not a node at all.
+                (b"", b""),
+                (b"d*", b"e"),
+            ];
+            for (prefix, suffix) in crafted {
+                let proof = MerkleProof::new(vec![(prefix.to_vec(), suffix.to_vec())]);
+                let node = [prefix, b"n", suffix].concat();
+                assert!(!verify_proof(
+                    &Digest::of(&node),
+                    &path,
+                    &BencodexValue::Null,
+                    &proof
+                ));
+            }
+        }
+
+        #[test]
+        fn should_verify_scalar_root_without_frames() {
+            let value = BencodexValue::from(42);
+            let proof = prove_value(&value, &BencodexPath::root());
+            assert!(proof.frames().is_empty());
+            assert!(verify_proof(
+                &node_digest(&value),
+                &BencodexPath::root(),
+                &42.into(),
+                &proof
+            ));
+            assert!(!verify_proof(
+                &node_digest(&value),
+                &BencodexPath::root(),
+                &43.into(),
+                &proof
+            ));
+        }
+
+        #[test]
+        fn should_return_none_for_missing_path() {
+            let (store, digest, _) = store(&state());
+            let missing = vec![
+                BencodexPath::root().key("none"),
+                BencodexPath::root().key("height").index(0),
+                BencodexPath::root().key("txs").key("height"),
+                BencodexPath::root().key("txs").index(2),
+            ];
+            for path in missing {
+                assert_eq!(None, prove(&store, &digest, &path).unwrap(), "{}", path);
+            }
+            assert_eq!(
+                None,
+                prove(&store, &Digest::from([0; 32]), &BencodexPath::root()).unwrap()
+            );
+        }
+
+        #[test]
+        fn should_convert_to_bencodex() {
+            let proof = prove_value(&state(), &BencodexPath::root().key("txs").index(0));
+            let converted = BencodexValue::from(proof.clone());
+            assert_eq!(Ok(proof), MerkleProof::try_from(converted));
+            assert_eq!(
+                Err(DecodeError::InvalidBencodexValueError),
+                MerkleProof::try_from(BencodexValue::from(vec![1]))
+            );
+        }
+    }
+}
@@ -0,0 +1,512 @@
+//! A Merkle Patricia Trie whose nodes are Bencodex values, laid out after Libplanet's state
+//! tries.
+//!
+//! To use it, you should enable `trie` feature.
+//!
+//! The node layout below follows Libplanet's, but root hashes have not been checked against
+//! tries produced by Libplanet, so do not rely on them to verify Libplanet state yet.
+//!
+//! Keys are split into nibbles, and the trie is made of four kinds of nodes, each encoded
+//! as a Bencodex value:
+//!
+//! - A *value node* is `[null, value]`.
+//! - A *short node* is `[nibbles, child]`, where `nibbles` is a binary of one nibble per byte.
+//! - A *full node* is a list of 17 children, one for each next nibble and the last one for
+//!   the value of the path ending there, where a missing child is `null`.
+//! - A *hash node* is the 32-byte SHA-256 digest of another node's encoding, as a binary.
+//!
+//! On [`MerkleTrie::commit`], every node whose encoding is longer than 32 bytes is put into
+//! a [`ContentStore`] under its digest and replaced by a hash node in its parent; smaller
+//! nodes are embedded in their parents. The root node is always stored, and its digest is
+//! the root hash of the trie.
+//!
+//! ```
+//! use bencodex::BencodexValue;
+//! use bencodex::store::MemoryContentStore;
+//! use bencodex::trie::MerkleTrie;
+//!
+//! let mut trie = MerkleTrie::new(MemoryContentStore::new());
+//! trie.insert(b"alice", 100.into()).unwrap();
+//! trie.insert(b"bob", "foo".into()).unwrap();
+//! let root_hash = trie.commit().unwrap();
+//!
+//! let trie = MerkleTrie::open(trie.into_store(), &root_hash);
+//! assert_eq!(trie.get(b"alice").unwrap(), Some(100.into()));
+//! assert_eq!(trie.get(b"carol").unwrap(), None);
+//! ```
+
+mod node;
+
+use std::io;
+use std::mem;
+
+use crate::store::{ContentStore, Digest};
+use crate::BencodexValue;
+use node::{to_nibbles, Node, FULL_NODE_CHILDREN, VALUE_INDEX};
+
+/// Returns the root hash of an empty trie, which is the digest of the encoding of `null`.
+pub fn empty_root_hash() -> Digest {
+    Digest::of(b"n")
+}
+
+/// A Merkle Patricia Trie over a [`ContentStore`]. See the [module documentation](self)
+/// for its node encoding.
+///
+/// Changes made by [`insert`](MerkleTrie::insert) and [`remove`](MerkleTrie::remove) are
+/// kept in memory until [`commit`](MerkleTrie::commit) writes them into the store.
+#[derive(Debug, Clone)]
+pub struct MerkleTrie<S: ContentStore> {
+    store: S,
+    root: Option<Node>,
+}
+
+impl<S: ContentStore> MerkleTrie<S> {
+    /// Creates an empty trie over the given store.
+    pub fn new(store: S) -> MerkleTrie<S> {
+        MerkleTrie { store, root: None }
+    }
+
+    /// Opens the trie of the given root hash from the store. Its nodes are loaded lazily, so
+    /// a missing or corrupted node is reported by the operation which reaches it.
+    pub fn open(store: S, root_hash: &Digest) -> MerkleTrie<S> {
+        let root = if root_hash == &empty_root_hash() {
+            None
+        } else {
+            Some(Node::Hash(root_hash.clone()))
+        };
+
+        MerkleTrie { store, root }
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    pub fn into_store(self) -> S {
+        self.store
+    }
+
+    /// Returns the value of the given key, or [`None`] if there is no such key.
+    ///
+    /// It fails with [`io::ErrorKind::NotFound`] if a node is missing in the store, and with
+    /// [`io::ErrorKind::InvalidData`] if a node is malformed or does not match its digest.
+    pub fn get(&self, key: &[u8]) -> io::Result<Option<BencodexValue>> {
+        match &self.root {
+            Some(root) => get_impl(&self.store, root, &to_nibbles(key)),
+            None => Ok(None),
+        }
+    }
+
+    /// Sets the value of the given key.
+    ///
+    /// If it fails, the trie is left as it was.
+    pub fn insert(&mut self, key: &[u8], value: BencodexValue) -> io::Result<()> {
+        let path = to_nibbles(key);
+        match &mut self.root {
+            Some(root) => insert_impl(&self.store, root, &path, value),
+            None => {
+                self.root = Some(leaf(&path, value));
+                Ok(())
+            }
+        }
+    }
+
+    /// Removes the given key, and returns its value if there was.
+    ///
+    /// If it fails, the trie is left as it was.
+    pub fn remove(&mut self, key: &[u8]) -> io::Result<Option<BencodexValue>> {
+        let root = match self.root.take() {
+            Some(root) => root,
+            None => return Ok(None),
+        };
+        let (root, removed) = match remove_impl(&self.store, root.clone(), &to_nibbles(key)) {
+            Ok(result) => result,
+            Err(e) => {
+                self.root = Some(root);
+                return Err(e);
+            }
+        };
+        self.root = root;
+        Ok(removed)
+    }
+
+    /// Writes the changes into the store, and returns the new root hash.
+    pub fn commit(&mut self) -> io::Result<Digest> {
+        let root = match &self.root {
+            Some(root) => root.clone(),
+            None => return Ok(empty_root_hash()),
+        };
+        let store = &mut self.store;
+        let mut write = |digest: &Digest, encoded: &[u8]| {
+            if !store.contains(digest)? {
+                store.put(digest, encoded)?;
+            }
+            Ok(())
+        };
+        let root_hash = match commit_node(root, &mut write)? {
+            Node::Hash(digest) => digest,
+            root => {
+                let encoded = root.encode();
+                let digest = Digest::of(&encoded);
+                write(&digest, &encoded)?;
+                digest
+            }
+        };
+
+        self.root = Some(Node::Hash(root_hash.clone()));
+        Ok(root_hash)
+    }
+
+    /// Returns the root hash which [`commit`](MerkleTrie::commit) would return, without
+    /// writing anything into the store.
+    pub fn root_hash(&self) -> io::Result<Digest> {
+        let root = match &self.root {
+            Some(root) => root.clone(),
+            None => return Ok(empty_root_hash()),
+        };
+        Ok(match commit_node(root, &mut |_, _| Ok(()))? {
+            Node::Hash(digest) => digest,
+            root => Digest::of(&root.encode()),
+        })
+    }
+}
+
+fn load<S: ContentStore>(store: &S, digest: &Digest) -> io::Result<Node> {
+    let encoded = store.get(digest)?.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, format!("no node for {}", digest))
+    })?;
+    // Trie nodes are stored as their encodings as they are, so they hash to their digests.
+    if &Digest::of(&encoded) != digest {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("the loaded node does not match {}", digest),
+        ));
+    }
+    Node::decode(encoded)
+}
+
+/// Loads the node if it is a hash node.
+fn resolve<S: ContentStore>(store: &S, node: Node) -> io::Result<Node> {
+    match node {
+        Node::Hash(digest) => load(store, &digest),
+        node => Ok(node),
+    }
+}
+
+fn get_impl<S: ContentStore>(
+    store: &S,
+    node: &Node,
+    path: &[u8],
+) -> io::Result<Option<BencodexValue>> {
+    match node {
+        Node::Value(value) => Ok(path.is_empty().then(|| value.clone())),
+        Node::Short(nibbles, child) => match path.strip_prefix(nibbles.as_slice()) {
+            Some(rest) => get_impl(store, child, rest),
+            None => Ok(None),
+        },
+        Node::Full(children) => {
+            let (index, rest) = match path.split_first() {
+                Some((nibble, rest)) => (*nibble as usize, rest),
+                None => (VALUE_INDEX, path),
+            };
+            match &children[index] {
+                Some(child) => get_impl(store, child, rest),
+                None => Ok(None),
+            }
+        }
+        Node::Hash(digest) => get_impl(store, &load(store, digest)?, path),
+    }
+}
+
+/// Returns the node holding only the value at the path.
+fn leaf(path: &[u8], value: BencodexValue) -> Node {
+    if path.is_empty() {
+        Node::Value(value)
+    } else {
+        Node::Short(path.to_vec(), Box::new(Node::Value(value)))
+    }
+}
+
+/// Sets the value of the path in place. Nodes are only loaded before anything changes, so
+/// the node is left as it was if it fails.
+fn insert_impl<S: ContentStore>(
+    store: &S,
+    node: &mut Node,
+    path: &[u8],
+    value: BencodexValue,
+) -> io::Result<()> {
+    if let Node::Hash(digest) = node {
+        *node = load(store, digest)?;
+    }
+
+    match node {
+        Node::Value(_) if path.is_empty() => *node = Node::Value(value),
+        Node::Value(_) => {
+            let old = mem::replace(node, Node::empty_full());
+            if let Node::Full(children) = node {
+                children[VALUE_INDEX] = Some(old);
+                children[path[0] as usize] = Some(leaf(&path[1..], value));
+            }
+        }
+        Node::Short(nibbles, child) => {
+            let common = nibbles.iter().zip(path).take_while(|(a, b)| a == b).count();
+            if common == nibbles.len() {
+                return insert_impl(store, child, &path[common..], value);
+            }
+
+            let (nibbles, child) = match mem::replace(node, Node::empty_full()) {
+                Node::Short(nibbles, child) => (nibbles, child),
+                _ => unreachable!("the node is a short node"),
+            };
+            let mut children: [Option<Node>; FULL_NODE_CHILDREN] = Default::default();
+            let rest = &nibbles[common + 1..];
+            children[nibbles[common] as usize] = Some(if rest.is_empty() {
+                *child
+            } else {
+                Node::Short(rest.to_vec(), child)
+            });
+            match path.get(common) {
+                Some(nibble) => {
+                    children[*nibble as usize] = Some(leaf(&path[common + 1..], value));
+                }
+                None => children[VALUE_INDEX] = Some(Node::Value(value)),
+            }
+
+            let full = Node::Full(Box::new(children));
+            *node = if common == 0 {
+                full
+            } else {
+                Node::Short(nibbles[..common].to_vec(), Box::new(full))
+            };
+        }
+        Node::Full(children) => match path.split_first() {
+            Some((nibble, rest)) => match &mut children[*nibble as usize] {
+                Some(child) => insert_impl(store, child, rest, value)?,
+                slot => *slot = Some(leaf(rest, value)),
+            },
+            None => children[VALUE_INDEX] = Some(Node::Value(value)),
+        },
+        Node::Hash(_) => unreachable!("hash nodes are resolved above"),
+    }
+
+    Ok(())
+}
+
+/// Prepends nibbles to a resolved node, merging them into it if it is a short node too.
+fn join_short(mut nibbles: Vec<u8>, node: Node) -> Node {
+    match node {
+        Node::Short(rest, child) => {
+            nibbles.extend(rest);
+            Node::Short(nibbles, child)
+        }
+        node => Node::Short(nibbles, Box::new(node)),
+    }
+}
+
+/// Removes the value of the path from the node, and returns the remaining node (if any) with
+/// the removed value. The remaining node is always resolved.
+fn remove_impl<S: ContentStore>(
+    store: &S,
+    node: Node,
+    path: &[u8],
+) -> io::Result<(Option<Node>, Option<BencodexValue>)> {
+    Ok(match resolve(store, node)? {
+        Node::Value(value) if path.is_empty() => (None, Some(value)),
+        Node::Short(nibbles, child) if path.starts_with(&nibbles) => {
+            match remove_impl(store, *child, &path[nibbles.len()..])? {
+                (Some(child), None) => (Some(Node::Short(nibbles, Box::new(child))), None),
+                (Some(child), removed) => (Some(join_short(nibbles, child)), removed),
+                (None, removed) => (None, removed),
+            }
+        }
+        Node::Full(mut children) => {
+            let (index, rest) = match path.split_first() {
+                Some((nibble, rest)) => (*nibble as usize, rest),
+                None => (VALUE_INDEX, path),
+            };
+            let removed = match children[index].take() {
+                Some(child) => {
+                    let (child, removed) = remove_impl(store, child, rest)?;
+                    children[index] = child;
+                    removed
+                }
+                None => None,
+            };
+            if removed.is_none() {
+                return Ok((Some(Node::Full(children)), None));
+            }
+
+            let mut present = children.iter().enumerate().filter(|(_, c)| c.is_some());
+            let remaining = match (present.next(), present.next()) {
+                (Some((index, _)), None) => {
+                    let child = resolve(store, children[index].take().unwrap())?;
+                    Some(if index == VALUE_INDEX {
+                        child
+                    } else {
+                        join_short(vec![index as u8], child)
+                    })
+                }
+                (None, _) => None,
+                _ => Some(Node::Full(children)),
+            };
+            (remaining, removed)
+        }
+        node => (Some(node), None),
+    })
+}
+
+/// Replaces the descendants of the node, and the node itself, whose encoding is longer than
+/// a digest with hash nodes, passing them to `write`.
+fn commit_node(
+    node: Node,
+    write: &mut dyn FnMut(&Digest, &[u8]) -> io::Result<()>,
+) -> io::Result<Node> {
+    let node = match node {
+        Node::Hash(_) | Node::Value(_) => node,
+        Node::Short(nibbles, child) => Node::Short(nibbles, Box::new(commit_node(*child, write)?)),
+        Node::Full(mut children) => {
+            for slot in children.iter_mut() {
+                if let Some(child) = slot.take() {
+                    *slot = Some(commit_node(child, write)?);
+                }
+            }
+            Node::Full(children)
+        }
+    };
+    if let Node::Hash(_) = node {
+        return Ok(node);
+    }
+
+    let encoded = node.encode();
+    if encoded.len() <= Digest::LENGTH {
+        return Ok(node);
+    }
+
+    let digest = Digest::of(&encoded);
+    write(&digest, &encoded)?;
+    Ok(Node::Hash(digest))
+}
+
+#[cfg(test)]
+mod tests {
+    mod merkle_trie {
+        use super::super::*;
+        use crate::store::{FileContentStore, MemoryContentStore};
+
+        fn keys() -> Vec<Vec<u8>> {
+            vec![
+                b"".to_vec(),
+                b"a".to_vec(),
+                b"ab".to_vec(),
+                b"abc".to_vec(),
+                b"b".to_vec(),
+                vec![0x00],
+                vec![0x00, 0x01],
+                vec![0x10],
+                vec![0xff; 20],
+            ]
+        }
+
+        fn value_of(key: &[u8]) -> BencodexValue {
+            // Some values are large enough to be stored as hash nodes.
+            BencodexValue::Binary(key.repeat(3))
+        }
+
+        #[test]
+        fn should_get_inserted_values() {
+            let mut trie = MerkleTrie::new(MemoryContentStore::new());
+            for key in keys() {
+                trie.insert(&key, value_of(&key)).unwrap();
+            }
+            for key in keys() {
+                assert_eq!(Some(value_of(&key)), trie.get(&key).unwrap());
+            }
+            assert_eq!(None, trie.get(b"abcd").unwrap());
+            assert_eq!(None, trie.get(b"c").unwrap());
+            assert_eq!(None, trie.get(&[0x00, 0x02]).unwrap());
+        }
+
+        #[test]
+        fn should_overwrite_values() {
+            let mut trie = MerkleTrie::new(MemoryContentStore::new());
+            trie.insert(b"a", 1.into()).unwrap();
+            trie.insert(b"a", 2.into()).unwrap();
+            assert_eq!(Some(2.into()), trie.get(b"a").unwrap());
+        }
+
+        #[test]
+        fn should_have_empty_root_hash_when_empty() {
+            let mut trie = MerkleTrie::new(MemoryContentStore::new());
+            assert_eq!(empty_root_hash(), trie.root_hash().unwrap());
+            assert_eq!(empty_root_hash(), trie.commit().unwrap());
+            assert!(trie.store().is_empty());
+
+            trie.insert(b"a", 1.into()).unwrap();
+            assert_eq!(Some(1.into()), trie.remove(b"a").unwrap());
+            assert_eq!(empty_root_hash(), trie.root_hash().unwrap());
+        }
+
+        #[test]
+        fn should_hash_root_node() {
+            let mut trie = MerkleTrie::new(MemoryContentStore::new());
+            trie.insert(&[0xbe, 0xef], "foo".into()).unwrap();
+            let root_hash = trie.commit().unwrap();
+
+            // The root is a short node whose value node is embedded.
+            let root = b"l4:\x0b\x0e\x0e\x0flnu3:fooee";
+            assert_eq!(Digest::of(root), root_hash);
+            assert_eq!(Some(root.to_vec()), trie.store().get(&root_hash).unwrap());
+        }
+
+        #[test]
+        fn should_not_depend_on_insertion_order() {
+            let mut forward = MerkleTrie::new(MemoryContentStore::new());
+            for key in keys() {
+                forward.insert(&key, value_of(&key)).unwrap();
+            }
+            let mut backward = MerkleTrie::new(MemoryContentStore::new());
+            for key in keys().iter().rev() {
+                backward.insert(key, value_of(key)).unwrap();
+            }
+            assert_eq!(forward.root_hash().unwrap(), backward.root_hash().unwrap());
+            assert_eq!(forward.commit().unwrap(), backward.commit().unwrap());
+        }
+
+        #[test]
+        fn should_restore_root_hash_after_remove() {
+            let mut trie = MerkleTrie::new(MemoryContentStore::new());
+            let mut hashes = vec![trie.root_hash().unwrap()];
+            for key in keys() {
+                trie.insert(&key, value_of(&key)).unwrap();
+                hashes.push(trie.commit().unwrap());
+            }
+            hashes.pop();
+            for key in keys().iter().rev() {
+                assert_eq!(Some(value_of(key)), trie.remove(key).unwrap());
+                assert_eq!(None, trie.remove(key).unwrap());
+                assert_eq!(hashes.pop(), Some(trie.root_hash().unwrap()));
+            }
+        }
+
+        #[test]
+        fn should_round_trip_through_file_store() {
+            let dir = tempfile::tempdir().unwrap();
+            let mut trie = MerkleTrie::new(FileContentStore::open(dir.path()).unwrap());
+            for key in keys() {
+                trie.insert(&key, value_of(&key)).unwrap();
+            }
+            let root_hash = trie.commit().unwrap();
+
+            let trie = MerkleTrie::open(FileContentStore::open(dir.path()).unwrap(), &root_hash);
+            for key in keys() {
+                assert_eq!(Some(value_of(&key)), trie.get(&key).unwrap());
+            }
+        }
+
+        #[test]
+        fn should_fail_with_missing_node() {
+            let trie = MerkleTrie::open(MemoryContentStore::new(), &Digest::from([0; 32]));
+            assert_eq!(io::ErrorKind::NotFound, trie.get(b"a").unwrap_err().kind());
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use std::io;
+
+use crate::store::Digest;
+use crate::{BencodexValue, Decode, DecodeError, Encode, BENCODEX_NULL};
+
+/// The number of children of a full node: one for each nibble, and one for the value.
+pub(crate) const FULL_NODE_CHILDREN: usize = 17;
+
+/// The index of the value among the children of a full node.
+pub(crate) const VALUE_INDEX: usize = FULL_NODE_CHILDREN - 1;
+
+/// A node of a Merkle Patricia Trie, laid out after Libplanet's trie nodes.
+#[derive(PartialEq, Debug, Clone)]
+pub(crate) enum Node {
+    /// A value, encoded as `[null, value]`.
+    Value(BencodexValue),
+    /// A node with a shared path of nibbles, encoded as `[nibbles, child]`.
+    Short(Vec<u8>, Box<Node>),
+    /// A node branching on the next nibble, encoded as a list of 17 children, whose last
+    /// one is the value of the path ending here. A missing child is encoded as `null`.
+    Full(Box<[Option<Node>; FULL_NODE_CHILDREN]>),
+    /// A node stored elsewhere, encoded as the SHA-256 digest of its encoding.
+    Hash(Digest),
+}
+
+impl Node {
+    /// Returns a full node without any children.
+    pub(crate) fn empty_full() -> Node {
+        Node::Full(Box::default())
+    }
+
+    pub(crate) fn to_bencodex(&self) -> BencodexValue {
+        match self {
+            Node::Value(value) => BencodexValue::List(vec![BENCODEX_NULL, value.clone()]),
+            Node::Short(nibbles, child) => BencodexValue::List(vec![
+                BencodexValue::Binary(nibbles.clone()),
+                child.to_bencodex(),
+            ]),
+            Node::Full(children) => BencodexValue::List(
+                children
+                    .iter()
+                    .map(|child| child.as_ref().map_or(BENCODEX_NULL, Node::to_bencodex))
+                    .collect(),
+            ),
+            Node::Hash(digest) => BencodexValue::Binary(digest.as_bytes().to_vec()),
+        }
+    }
+
+    pub(crate) fn from_bencodex(value: BencodexValue) -> Result<Node, DecodeError> {
+        match value {
+            BencodexValue::Binary(digest) => Digest::from_slice(&digest)
+                .map(Node::Hash)
+                .ok_or(DecodeError::InvalidBencodexValueError),
+            BencodexValue::List(list) if list.len() == 2 => {
+                let mut list = list.into_iter();
+                match (list.next(), list.next()) {
+                    (Some(BencodexValue::Null), Some(value)) => Ok(Node::Value(value)),
+                    (Some(BencodexValue::Binary(nibbles)), Some(child))
+                        if nibbles.iter().all(|nibble| *nibble < 16) =>
+                    {
+                        Ok(Node::Short(nibbles, Box::new(Node::from_bencodex(child)?)))
+                    }
+                    _ => Err(DecodeError::InvalidBencodexValueError),
+                }
+            }
+            BencodexValue::List(list) if list.len() == FULL_NODE_CHILDREN => {
+                let mut children: [Option<Node>; FULL_NODE_CHILDREN] = Default::default();
+                for (slot, child) in children.iter_mut().zip(list) {
+                    *slot = match child {
+                        BencodexValue::Null => None,
+                        child => Some(Node::from_bencodex(child)?),
+                    };
+                }
+                Ok(Node::Full(Box::new(children)))
+            }
+            _ => Err(DecodeError::InvalidBencodexValueError),
+        }
+    }
+
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut buf = vec![];
+        self.to_bencodex()
+            .encode(&mut buf)
+            .expect("writing to a Vec never fails");
+        buf
+    }
+
+    pub(crate) fn decode(encoded: Vec<u8>) -> io::Result<Node> {
+        encoded
+            .decode()
+            .and_then(Node::from_bencodex)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// Splits each byte of the key into two nibbles, the high one first.
+pub(crate) fn to_nibbles(key: &[u8]) -> Vec<u8> {
+    key.iter()
+        .flat_map(|byte| [byte >> 4, byte & 0x0f])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    mod node {
+        use super::super::*;
+
+        #[test]
+        fn should_encode_as_lists() {
+            assert_eq!(b"lnu3:fooe".to_vec(), Node::Value("foo".into()).encode());
+            assert_eq!(
+                b"l2:\x01\x02lni1eee".to_vec(),
+                Node::Short(vec![1, 2], Box::new(Node::Value(1.into()))).encode()
+            );
+
+            let mut full = Node::empty_full();
+            if let Node::Full(children) = &mut full {
+                children[3] = Some(Node::Hash(Digest::from([0xab; 32])));
+                children[VALUE_INDEX] = Some(Node::Value(BencodexValue::Null));
+            }
+            let mut expected = b"lnnn32:".to_vec();
+            expected.extend_from_slice(&[0xab; 32]);
+            expected.extend_from_slice(&[b'n'; 12]);
+            expected.extend_from_slice(b"lnnee");
+            assert_eq!(expected, full.encode());
+        }
+
+        #[test]
+        fn should_round_trip() {
+            let mut full = Node::empty_full();
+            if let Node::Full(children) = &mut full {
+                children[0] = Some(Node::Short(vec![15], Box::new(Node::Value(1.into()))));
+                children[VALUE_INDEX] = Some(Node::Value("a".into()));
+            }
+            let nodes = vec![
+                Node::Value(vec![1, 2].into()),
+                Node::Short(vec![0, 1], Box::new(full.clone())),
+                full,
+                Node::Hash(Digest::from([1; 32])),
+            ];
+            for node in nodes {
+                assert_eq!(node, Node::decode(node.encode()).unwrap());
+            }
+        }
+
+        #[test]
+        fn should_return_error_with_invalid_node() {
+            let nodes: Vec<&[u8]> = vec![b"i1e", b"le", b"l2:\x10\x01lni1eee", b"3:abc", b"lni1"];
+            for node in nodes {
+                assert_eq!(
+                    io::ErrorKind::InvalidData,
+                    Node::decode(node.to_vec()).unwrap_err().kind()
+                );
+            }
+        }
+    }
+
+    mod nibbles {
+        use super::super::*;
+
+        #[test]
+        fn should_split_bytes() {
+            assert_eq!(vec![0xa, 0xb, 0x0, 0x1], to_nibbles(&[0xab, 0x01]));
+            assert!(to_nibbles(&[]).is_empty());
+        }
+    }
+}
@@ -194,71 +194,55 @@ impl SpecImportError {
 
 impl Error for SpecImportError {}
 
+/// Converts a binary in the spec JSON, which is either in hex or in base64, to the encoding.
+/// Other strings are returned as they are.
 #[cfg(feature = "json")]
-fn hex_to_base64(s: &String) -> String {
-    if s.starts_with("0x") {
-        format!(
-            "b64:{}",
-            base64::engine::general_purpose::STANDARD
-                .encode(hex::decode(s[2..].to_owned()).unwrap())
-        )
+fn convert_binary(s: &String, encoding: &BinaryEncoding) -> String {
+    let bytes = if let Some(hex) = s.strip_prefix("0x") {
+        hex::decode(hex).unwrap()
+    } else if let Some(base64) = s.strip_prefix("b64:") {
+        base64::engine::general_purpose::STANDARD
+            .decode(base64)
+            .unwrap()
     } else {
-        s.to_owned()
-    }
-}
+        return s.to_owned();
+    };
 
-#[cfg(feature = "json")]
-fn base64_to_hex(s: &String) -> String {
-    if s.starts_with("b64:") {
-        format!(
-            "0x{}",
-            hex::encode(
-                base64::engine::general_purpose::STANDARD
-                    .decode(s[4..].to_owned())
-                    .unwrap()
-            )
-        )
-    } else {
-        s.to_owned()
+    match encoding {
+        BinaryEncoding::Hex => format!("0x{}", hex::encode(bytes)),
+        BinaryEncoding::Base64 => format!(
+            "b64:{}",
+            base64::engine::general_purpose::STANDARD.encode(bytes)
+        ),
+        BinaryEncoding::Base64Url => format!(
+            "b64url:{}",
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+        ),
+        BinaryEncoding::Base58 => format!("b58:{}", bs58::encode(bytes).into_string()),
     }
 }
 
+/// Converts the binaries in the spec JSON, including the keys and the nested values, to the
+/// encoding.
 #[cfg(feature = "json")]
 fn traverse(value: &Value, bytes_encode_method: &BinaryEncoding) -> Value {
-    let converter = match bytes_encode_method {
-        BinaryEncoding::Hex => base64_to_hex,
-        BinaryEncoding::Base64 => hex_to_base64,
-    };
     match value {
-        Value::Object(obj) => {
-            let mut ret = obj.clone();
-            for (key, value) in obj.iter() {
-                let new_key = converter(key);
-                let new_value = match value {
-                    Value::String(s) if s.starts_with("0x") => Value::String(hex_to_base64(s)),
-                    _ => value.to_owned(),
-                };
-
-                if !new_key.eq(key) {
-                    ret.remove(key);
-
-                    ret.insert(new_key, new_value);
-                } else {
-                    ret[key] = new_value;
-                }
-            }
-
-            Value::Object(ret)
-        }
+        Value::Object(obj) => Value::Object(
+            obj.iter()
+                .map(|(key, value)| {
+                    (
+                        convert_binary(key, bytes_encode_method),
+                        traverse(value, bytes_encode_method),
+                    )
+                })
+                .collect(),
+        ),
         Value::Array(list) => Value::Array(
             list.iter()
-                .map(|x| match x {
-                    Value::String(s) => Value::String(converter(s)),
-                    _ => x.to_owned(),
-                })
-                .collect::<Vec<Value>>(),
+                .map(|x| traverse(x, bytes_encode_method))
+                .collect(),
         ),
-        Value::String(s) => Value::String(converter(s)),
+        Value::String(s) => Value::String(convert_binary(s, bytes_encode_method)),
         _ => value.to_owned(),
     }
 }
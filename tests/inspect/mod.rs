@@ -0,0 +1,21 @@
+use super::codec::utils;
+#[cfg(test)]
+use bencodex::inspect::from_inspect;
+
+#[test]
+fn spec_test() {
+    let specs = utils::iter_spec().unwrap();
+    for spec in specs {
+        println!("---- SPEC [{}] ----", spec.name);
+        println!("BVALUE: {:?}", spec.bvalue);
+
+        let inspected = spec.bvalue.to_string();
+        println!("INSPECT: {}", inspected);
+        assert_eq!(from_inspect(&inspected).unwrap(), spec.bvalue);
+
+        let pretty = format!("{:#}", spec.bvalue);
+        assert_eq!(from_inspect(&pretty).unwrap(), spec.bvalue);
+
+        println!("---- PASSED ----");
+    }
+}
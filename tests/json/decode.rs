@@ -1,6 +1,11 @@
 use super::super::codec::utils;
 #[cfg(test)]
-use bencodex::json::{from_json_string, BinaryEncoding};
+use bencodex::json::{
+    from_json, from_json_string, from_json_string_with_options, from_json_with_options,
+    transcode_from_json, transcode_from_json_with_options, BinaryEncoding, JsonDecodeError,
+    JsonDecodeErrorReason, JsonDecodeOptions,
+};
+use bencodex::{BencodexDictionary, BencodexValue, Decode, Encode};
 
 #[test]
 fn spec_test_base64() {
@@ -27,3 +32,466 @@ fn spec_test_hex() {
         println!("---- PASSED ----");
     }
 }
+
+#[test]
+fn spec_test_base64url_and_base58() {
+    for binary_encoding in [BinaryEncoding::Base64Url, BinaryEncoding::Base58] {
+        let specs = utils::iter_spec_with_json(binary_encoding).unwrap();
+        for spec in specs {
+            println!("---- SPEC [{}] ({:?}) ----", spec.name, binary_encoding);
+
+            println!("JSON: {:?}", spec.json);
+            assert_eq!(from_json_string(&spec.json).unwrap(), spec.bvalue);
+
+            println!("---- PASSED ----");
+        }
+    }
+}
+
+#[test]
+fn spec_test_transcode() {
+    let specs = utils::iter_spec_with_json(BinaryEncoding::Hex).unwrap();
+    for spec in specs {
+        println!("---- SPEC [{}] ----", spec.name);
+
+        let mut encoded = vec![];
+        transcode_from_json(spec.json.as_bytes(), &mut encoded).unwrap();
+        assert_eq!(encoded, spec.encoded);
+
+        println!("---- PASSED ----");
+    }
+}
+
+fn transcode(json: &str) -> std::io::Result<Vec<u8>> {
+    let mut encoded = vec![];
+    transcode_from_json(json.as_bytes(), &mut encoded).map(|_| encoded)
+}
+
+fn transcode_error(json: &str) -> JsonDecodeError {
+    let err = transcode(json).unwrap_err();
+    assert_eq!(std::io::ErrorKind::InvalidData, err.kind());
+    *err.into_inner()
+        .unwrap()
+        .downcast::<JsonDecodeError>()
+        .unwrap()
+}
+
+#[test]
+fn transcode_should_equal_from_json_string() {
+    let json = r#"
+        {
+            "\ufeffz": [null, true, false, "-12", "0x", "b64:AQI="],
+            "0x02": {"\ufeff\n": "\ufeff\"", "0x01": []},
+            "b64:AQ==": {},
+            "\ufeff": "\ufeff"
+        }
+    "#;
+    let expected: BencodexValue = from_json_string(json).unwrap();
+    let mut expected_encoded = vec![];
+    (&expected).encode(&mut expected_encoded).unwrap();
+
+    let encoded = transcode(json).unwrap();
+    assert_eq!(encoded, expected_encoded);
+    assert_eq!(encoded.decode().unwrap(), expected);
+}
+
+#[test]
+fn transcode_should_keep_last_duplicate_key() {
+    assert_eq!(
+        transcode(r#"{"0x61": "1", "b64:YQ==": "2"}"#).unwrap(),
+        b"d1:ai2ee"
+    );
+}
+
+#[test]
+fn transcode_should_stream_ordered_dictionary() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct SharedWriter(Rc<RefCell<Vec<u8>>>);
+
+    impl std::io::Write for SharedWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Records how many bytes are written when the whole JSON is read.
+    struct WatchedReader<'a> {
+        json: &'a [u8],
+        written: Rc<RefCell<Vec<u8>>>,
+        written_at_end: Option<usize>,
+    }
+
+    impl std::io::Read for WatchedReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let size = self.json.read(buf)?;
+            if size == 0 && self.written_at_end.is_none() {
+                self.written_at_end = Some(self.written.borrow().len());
+            }
+            Ok(size)
+        }
+    }
+
+    let large = "ab".repeat(3 << 20);
+    let json = format!(r#"{{"0x61": "0x{}", "0x62": {{"0x63": []}}}}"#, large);
+    let written = Rc::new(RefCell::new(vec![]));
+    let mut reader = WatchedReader {
+        json: json.as_bytes(),
+        written: written.clone(),
+        written_at_end: None,
+    };
+    transcode_from_json(&mut reader, &mut SharedWriter(written.clone())).unwrap();
+    assert!(reader.written_at_end.unwrap() >= 2 << 20);
+
+    let mut expected = BencodexDictionary::new();
+    expected.insert(vec![b'a'].into(), vec![0xabu8; 3 << 20].into());
+    let mut inner = BencodexDictionary::new();
+    inner.insert(vec![b'c'].into(), BencodexValue::List(vec![]));
+    expected.insert(vec![b'b'].into(), inner.into());
+    assert_eq!(
+        written.borrow().clone().decode().unwrap(),
+        BencodexValue::Dictionary(expected)
+    );
+}
+
+#[test]
+fn transcode_should_reorder_keys_after_streaming() {
+    let large = "ab".repeat(1 << 10);
+    let json = format!(
+        r#"{{"0x61": "0x{}", "0x63": {{"0x02": "1", "0x01": "2"}}, "0x62": "3"}}"#,
+        large
+    );
+    let expected: BencodexValue = from_json_string(&json).unwrap();
+    assert_eq!(transcode(&json).unwrap().decode().unwrap(), expected);
+
+    let options = JsonDecodeOptions {
+        reject_duplicate_keys: true,
+        ..Default::default()
+    };
+    let mut encoded = vec![];
+    let err = transcode_from_json_with_options(
+        r#"{"0x61": "1", "0x62": "2", "b64:YQ==": "3"}"#.as_bytes(),
+        &mut encoded,
+        options,
+    )
+    .unwrap_err();
+    assert_eq!(
+        invalid_json("/b64:YQ==", JsonDecodeErrorReason::DuplicateKey),
+        without_position(*err.into_inner().unwrap().downcast().unwrap())
+    );
+}
+
+#[test]
+fn transcode_should_reject_unordered_keys_beyond_window() {
+    let large = "ab".repeat(3 << 20);
+    let json = format!(r#"{{"0x62": "0x{}", "0x61": "1"}}"#, large);
+    assert_eq!(
+        invalid_json("/0x61", JsonDecodeErrorReason::UnorderedKeys),
+        without_position(transcode_error(&json))
+    );
+    assert!(from_json_string(&json).is_ok());
+}
+
+fn invalid_json(pointer: &str, reason: JsonDecodeErrorReason) -> JsonDecodeError {
+    JsonDecodeError::InvalidJson {
+        pointer: pointer.to_string(),
+        line: None,
+        column: None,
+        reason,
+    }
+}
+
+fn without_position(err: JsonDecodeError) -> JsonDecodeError {
+    match err {
+        JsonDecodeError::InvalidJson {
+            pointer, reason, ..
+        } => invalid_json(&pointer, reason),
+        err => err,
+    }
+}
+
+#[test]
+fn transcode_should_return_error() {
+    assert!(matches!(
+        transcode_error("nulll"),
+        JsonDecodeError::InvalidJsonString { .. }
+    ));
+    assert!(matches!(
+        transcode_error("[null"),
+        JsonDecodeError::InvalidJsonString { .. }
+    ));
+    assert!(matches!(
+        transcode_error("null null"),
+        JsonDecodeError::InvalidJsonString { .. }
+    ));
+    assert_eq!(
+        invalid_json("/0", JsonDecodeErrorReason::UnexpectedNumber),
+        without_position(transcode_error("[1]"))
+    );
+    assert_eq!(
+        invalid_json("/0", JsonDecodeErrorReason::InvalidHex),
+        without_position(transcode_error(r#"["0xZZ"]"#))
+    );
+    assert_eq!(
+        invalid_json("/a", JsonDecodeErrorReason::MissingTextPrefix),
+        without_position(transcode_error(r#"{"a": null}"#))
+    );
+    assert_eq!(
+        invalid_json("/0x", JsonDecodeErrorReason::InvalidInteger),
+        without_position(transcode_error(r#"{"0x": "1-2"}"#))
+    );
+}
+
+#[test]
+fn error_should_have_pointer_and_reason() {
+    let cases = [
+        (r#""b64:!""#, "", JsonDecodeErrorReason::InvalidBase64),
+        (r#""b64url:+/""#, "", JsonDecodeErrorReason::InvalidBase64),
+        (
+            r#"["b58:0OIl"]"#,
+            "/0",
+            JsonDecodeErrorReason::InvalidBase58,
+        ),
+        (r#"[null, "0x0"]"#, "/1", JsonDecodeErrorReason::InvalidHex),
+        (
+            r#"{"0x": 1.5}"#,
+            "/0x",
+            JsonDecodeErrorReason::UnexpectedNumber,
+        ),
+        (
+            r#"{"0x": ["--1"]}"#,
+            "/0x/0",
+            JsonDecodeErrorReason::InvalidInteger,
+        ),
+        (
+            r#"{"0x": [{"text": null}]}"#,
+            "/0x/0/text",
+            JsonDecodeErrorReason::MissingTextPrefix,
+        ),
+        (
+            r#"{"0x": {"a/b~c": null}}"#,
+            "/0x/a~1b~0c",
+            JsonDecodeErrorReason::MissingTextPrefix,
+        ),
+        (
+            r#"["text"]"#,
+            "/0",
+            JsonDecodeErrorReason::MissingTextPrefix,
+        ),
+    ];
+    for (json, pointer, reason) in cases {
+        println!("JSON: {}", json);
+        let expected = invalid_json(pointer, reason);
+        let value: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(expected, from_json(&value).unwrap_err());
+        assert_eq!(
+            expected,
+            without_position(from_json_string(json).unwrap_err())
+        );
+        assert_eq!(expected, without_position(transcode_error(json)));
+    }
+}
+
+#[test]
+fn error_should_have_line_and_column() {
+    let json = "{\n  \"\u{FEFF}a\": [\n    null,\n    \"12x\"\n  ]\n}";
+    let expected = JsonDecodeError::InvalidJson {
+        pointer: "/\u{FEFF}a/1".to_string(),
+        line: Some(4),
+        column: Some(9),
+        reason: JsonDecodeErrorReason::MissingTextPrefix,
+    };
+    assert_eq!(expected, from_json_string(json).unwrap_err());
+    assert_eq!(expected, transcode_error(json));
+
+    assert_eq!(
+        JsonDecodeError::InvalidJsonString {
+            line: 2,
+            column: 3,
+            message: "expected value".to_string(),
+        },
+        from_json_string("[\n  ,]").unwrap_err()
+    );
+}
+
+#[test]
+fn transcode_should_pass_writer_error() {
+    struct FailWriter;
+
+    impl std::io::Write for FailWriter {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::BrokenPipe,
+                "closed",
+            ))
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let err = transcode_from_json("[null]".as_bytes(), &mut FailWriter).unwrap_err();
+    assert_eq!(std::io::ErrorKind::BrokenPipe, err.kind());
+}
+
+/// Decodes the JSON in both ways with the options, asserting that they agree except the
+/// positions of errors, which differ between strings and readers.
+fn decode_with_options(
+    json: &str,
+    options: JsonDecodeOptions,
+) -> Result<BencodexValue, JsonDecodeError> {
+    let mut encoded = vec![];
+    let transcoded = transcode_from_json_with_options(json.as_bytes(), &mut encoded, options)
+        .map(|_| encoded.decode().unwrap())
+        .map_err(|e| {
+            *e.into_inner()
+                .unwrap()
+                .downcast::<JsonDecodeError>()
+                .unwrap()
+        });
+    assert_eq!(
+        from_json_string_with_options(json, options).map_err(without_position),
+        transcoded.map_err(without_position)
+    );
+
+    from_json_string_with_options(json, options)
+}
+
+#[test]
+fn default_options_should_be_strict() {
+    let json = r#"{"\ufeffa": ["0x01", "1"]}"#;
+    let value: serde_json::Value = serde_json::from_str(json).unwrap();
+    assert_eq!(
+        from_json(&value),
+        from_json_with_options(&value, JsonDecodeOptions::default())
+    );
+    assert_eq!(
+        from_json_string(json),
+        decode_with_options(json, JsonDecodeOptions::default())
+    );
+
+    let reason = |json: &str| match decode_with_options(json, JsonDecodeOptions::default()) {
+        Err(JsonDecodeError::InvalidJson { reason, .. }) => Some(reason),
+        _ => None,
+    };
+    // Texts need the prefix, and integers need to be strings.
+    assert_eq!(
+        reason(r#""a""#),
+        Some(JsonDecodeErrorReason::MissingTextPrefix)
+    );
+    assert_eq!(
+        reason(r#"{"a": null}"#),
+        Some(JsonDecodeErrorReason::MissingTextPrefix)
+    );
+    assert_eq!(reason("1"), Some(JsonDecodeErrorReason::UnexpectedNumber));
+    // Strings starting with 0x are binaries, not texts.
+    assert_eq!(
+        decode_with_options(r#""0x01""#, JsonDecodeOptions::default()),
+        Ok(BencodexValue::Binary(vec![1]))
+    );
+    assert_eq!(reason(r#""0xZZ""#), Some(JsonDecodeErrorReason::InvalidHex));
+}
+
+#[test]
+fn allow_numbers() {
+    let options = JsonDecodeOptions {
+        allow_numbers: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        Ok(BencodexValue::from(vec![
+            BencodexValue::Number(i64::MIN.into()),
+            BencodexValue::Number(u64::MAX.into()),
+            BencodexValue::from(1),
+        ])),
+        decode_with_options(&format!(r#"[{}, {}, "1"]"#, i64::MIN, u64::MAX), options)
+    );
+    for json in ["1.5", "1e3", "18446744073709551616"] {
+        assert_eq!(
+            invalid_json("", JsonDecodeErrorReason::InvalidInteger),
+            without_position(decode_with_options(json, options).unwrap_err())
+        );
+    }
+    assert_eq!(
+        Err(invalid_json("/0", JsonDecodeErrorReason::InvalidInteger)),
+        from_json_with_options(&serde_json::json!([0.5]), options)
+    );
+}
+
+#[test]
+fn allow_unprefixed_texts() {
+    let options = JsonDecodeOptions {
+        allow_unprefixed_texts: true,
+        ..Default::default()
+    };
+    let mut expected = BencodexDictionary::new();
+    expected.insert("key".into(), "value".into());
+    expected.insert("\u{FEFF}".into(), "".into());
+    expected.insert(
+        "n".into(),
+        vec![BencodexValue::from(-1), "1-2".into()].into(),
+    );
+    expected.insert("bin".into(), vec![1u8].into());
+    assert_eq!(
+        Ok(BencodexValue::Dictionary(expected)),
+        decode_with_options(
+            r#"{"key": "value", "\ufeff\ufeff": "", "n": ["-1", "1-2"], "bin": "0x01"}"#,
+            options
+        )
+    );
+    assert_eq!(
+        invalid_json("/0", JsonDecodeErrorReason::InvalidHex),
+        without_position(decode_with_options(r#"["0xZZ"]"#, options).unwrap_err())
+    );
+}
+
+#[test]
+fn hex_as_text() {
+    let options = JsonDecodeOptions {
+        hex_as_text: true,
+        ..Default::default()
+    };
+    let mut expected = BencodexDictionary::new();
+    expected.insert("0x01".into(), "0xZZ".into());
+    expected.insert(vec![1u8].into(), BencodexValue::Null);
+    assert_eq!(
+        Ok(BencodexValue::Dictionary(expected)),
+        decode_with_options(r#"{"0x01": "0xZZ", "b64:AQ==": null}"#, options)
+    );
+}
+
+#[test]
+fn reject_duplicate_keys() {
+    let json = r#"{"0x61": "1", "\ufeffb": null, "b64:YQ==": "2"}"#;
+    assert!(decode_with_options(json, JsonDecodeOptions::default()).is_ok());
+
+    let options = JsonDecodeOptions {
+        reject_duplicate_keys: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        invalid_json("/b64:YQ==", JsonDecodeErrorReason::DuplicateKey),
+        without_position(decode_with_options(json, options).unwrap_err())
+    );
+    assert_eq!(
+        invalid_json("/0/\u{FEFF}a", JsonDecodeErrorReason::DuplicateKey),
+        without_position(
+            decode_with_options(r#"[{"\ufeffa": null, "\ufeffa": null}]"#, options).unwrap_err()
+        )
+    );
+    let value: serde_json::Value = serde_json::from_str(json).unwrap();
+    assert_eq!(
+        Err(invalid_json(
+            "/b64:YQ==",
+            JsonDecodeErrorReason::DuplicateKey
+        )),
+        from_json_with_options(&value, options)
+    );
+}
@@ -1,14 +1,17 @@
 use super::super::codec::utils;
 #[cfg(test)]
-use bencodex::json::{to_json_with_options, BinaryEncoding, JsonEncodeOptions};
-
-const SPEC_TEST_BASE64_OPTIONS: JsonEncodeOptions = JsonEncodeOptions {
-    binary_encoding: BinaryEncoding::Base64,
+use bencodex::json::{
+    from_json_string, to_json, to_json_with_options, to_json_writer, transcode_to_json,
+    BinaryEncoding, JsonEncodeOptions, JsonMode, BENCODEX_JSON_SCHEMA,
 };
 
-const SPEC_TEST_HEX_OPTIONS: JsonEncodeOptions = JsonEncodeOptions {
-    binary_encoding: BinaryEncoding::Hex,
-};
+use bencodex::{BencodexDictionary, BencodexPath, BencodexValue, Decode, Encode};
+
+const SPEC_TEST_BASE64_OPTIONS: JsonEncodeOptions =
+    JsonEncodeOptions::new().with_binary_encoding(BinaryEncoding::Base64);
+
+const SPEC_TEST_HEX_OPTIONS: JsonEncodeOptions =
+    JsonEncodeOptions::new().with_binary_encoding(BinaryEncoding::Hex);
 
 #[test]
 fn spec_test_base64() {
@@ -18,7 +21,7 @@ fn spec_test_base64() {
 
         println!("JSON: {:?}", spec.json);
         assert_eq!(
-            to_json_with_options(&spec.bvalue, SPEC_TEST_BASE64_OPTIONS),
+            to_json_with_options(&spec.bvalue, SPEC_TEST_BASE64_OPTIONS).unwrap(),
             spec.json
         );
 
@@ -34,10 +37,504 @@ fn spec_test_hex() {
 
         println!("JSON: {:?}", spec.json);
         assert_eq!(
-            to_json_with_options(&spec.bvalue, SPEC_TEST_HEX_OPTIONS),
+            to_json_with_options(&spec.bvalue, SPEC_TEST_HEX_OPTIONS).unwrap(),
             spec.json
         );
 
         println!("---- PASSED ----");
     }
 }
+
+#[test]
+fn spec_test_base64url_and_base58() {
+    for binary_encoding in [BinaryEncoding::Base64Url, BinaryEncoding::Base58] {
+        let specs = utils::iter_spec_with_json(binary_encoding).unwrap();
+        for spec in specs {
+            println!("---- SPEC [{}] ({:?}) ----", spec.name, binary_encoding);
+
+            println!("JSON: {:?}", spec.json);
+            let json = to_json_with_options(
+                &spec.bvalue,
+                JsonEncodeOptions::new().with_binary_encoding(binary_encoding),
+            )
+            .unwrap();
+            // Compared as JSON values, as the spec JSON is rewritten with its keys sorted
+            // as strings, which is not the order of binary keys in these encodings.
+            assert_eq!(
+                serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+                serde_json::from_str::<serde_json::Value>(&spec.json).unwrap()
+            );
+
+            println!("---- PASSED ----");
+        }
+    }
+}
+
+#[test]
+fn spec_test_transcode() {
+    let specs = utils::iter_spec_with_json(BinaryEncoding::Base64).unwrap();
+    for spec in specs {
+        println!("---- SPEC [{}] ----", spec.name);
+
+        let mut json = vec![];
+        transcode_to_json(spec.encoded.as_slice(), &mut json, SPEC_TEST_BASE64_OPTIONS).unwrap();
+        assert_eq!(String::from_utf8(json).unwrap(), spec.json);
+
+        println!("---- PASSED ----");
+    }
+}
+
+#[test]
+fn transcode_should_equal_to_json() {
+    let mut inner = BencodexDictionary::new();
+    inner.insert(vec![0u8, 1].into(), BencodexValue::Null);
+    inner.insert(
+        "\"quoted\"".into(),
+        vec![BencodexValue::from(true), false.into()].into(),
+    );
+    let mut dict = BencodexDictionary::new();
+    dict.insert("inner".into(), inner.into());
+    dict.insert("empty".into(), BencodexValue::List(vec![]));
+    dict.insert("n".into(), (-42).into());
+    let value = BencodexValue::List(vec![dict.into(), BencodexDictionary::new().into()]);
+
+    let mut encoded = vec![];
+    (&value).encode(&mut encoded).unwrap();
+    let mut json = vec![];
+    transcode_to_json(encoded.as_slice(), &mut json, JsonEncodeOptions::default()).unwrap();
+    assert_eq!(String::from_utf8(json).unwrap(), to_json(&value).unwrap());
+}
+
+#[test]
+fn roundtrip_control_characters() {
+    for c in (0u32..0x20)
+        .chain([0x22, 0x5c, 0x7f])
+        .filter_map(char::from_u32)
+    {
+        let text = format!("a{}b{}", c, c);
+        let mut dict = BencodexDictionary::new();
+        dict.insert(text.clone().into(), text.clone().into());
+        let value = BencodexValue::List(vec![text.into(), dict.into()]);
+
+        let json = to_json(&value).unwrap();
+        println!("JSON: {:?}", json);
+        assert!(serde_json::from_str::<serde_json::Value>(&json).is_ok());
+        assert!(!json.chars().any(|c| c < ' '));
+        assert_eq!(from_json_string(&json), Ok(value));
+    }
+}
+
+#[test]
+fn roundtrip_all_control_characters_in_one_text() {
+    let text: String = (0u8..0x20)
+        .map(char::from)
+        .chain("\"\\/\u{7f}".chars())
+        .collect();
+    let value = BencodexValue::Text(text);
+
+    let json = to_json(&value).unwrap();
+    assert_eq!(
+        json,
+        "\"\u{FEFF}\\u0000\\u0001\\u0002\\u0003\\u0004\\u0005\\u0006\\u0007\\b\\t\\n\\u000b\\f\\r\\u000e\\u000f\\u0010\\u0011\\u0012\\u0013\\u0014\\u0015\\u0016\\u0017\\u0018\\u0019\\u001a\\u001b\\u001c\\u001d\\u001e\\u001f\\\"\\\\/\u{7f}\""
+    );
+    assert_eq!(from_json_string(&json), Ok(value));
+}
+
+fn sample_value() -> BencodexValue {
+    let mut inner = BencodexDictionary::new();
+    inner.insert(vec![0u8, 1].into(), BencodexValue::Null);
+    inner.insert("\u{AC00}\u{1F600}".into(), BencodexValue::List(vec![]));
+    let mut dict = BencodexDictionary::new();
+    dict.insert("inner".into(), inner.into());
+    dict.insert("empty".into(), BencodexDictionary::new().into());
+    dict.insert(
+        "list".into(),
+        vec![BencodexValue::from(1), true.into()].into(),
+    );
+    dict.into()
+}
+
+#[test]
+fn pretty_print_with_indent() {
+    let json = to_json_with_options(
+        &sample_value(),
+        JsonEncodeOptions::new()
+            .with_indent(Some(2))
+            .with_trailing_newline(true),
+    )
+    .unwrap();
+    assert_eq!(
+        json,
+        "{\n  \"\u{FEFF}empty\": {},\n  \"\u{FEFF}inner\": {\n    \"b64:AAE=\": null,\n    \"\u{FEFF}\u{AC00}\u{1F600}\": []\n  },\n  \"\u{FEFF}list\": [\n    \"1\",\n    true\n  ]\n}\n"
+    );
+    assert_eq!(from_json_string(&json), Ok(sample_value()));
+}
+
+#[test]
+fn escape_non_ascii_with_ascii_only() {
+    let json = to_json_with_options(
+        &BencodexValue::from("\u{AC00}\u{1F600}"),
+        JsonEncodeOptions::new().with_ascii_only(true),
+    )
+    .unwrap();
+    assert_eq!(json, "\"\\ufeff\\uac00\\ud83d\\ude00\"");
+    assert!(json.is_ascii());
+    assert_eq!(
+        from_json_string(&json),
+        Ok(BencodexValue::from("\u{AC00}\u{1F600}"))
+    );
+}
+
+#[test]
+fn transcode_should_equal_to_json_with_options() {
+    let mut encoded = vec![];
+    (&sample_value()).encode(&mut encoded).unwrap();
+    for indent in [None, Some(0), Some(4)] {
+        let options = || {
+            JsonEncodeOptions::new()
+                .with_binary_encoding(BinaryEncoding::Hex)
+                .with_mode(JsonMode::Bencodex)
+                .with_indent(indent)
+                .with_trailing_newline(true)
+                .with_ascii_only(true)
+        };
+        let mut json = vec![];
+        transcode_to_json(encoded.as_slice(), &mut json, options()).unwrap();
+        assert_eq!(
+            String::from_utf8(json).unwrap(),
+            to_json_with_options(&sample_value(), options()).unwrap()
+        );
+    }
+}
+
+#[test]
+fn transcode_should_reject_unordered_keys() {
+    for encoded in [
+        &b"du1:anu1:ane"[..],
+        b"du1:bnu1:ane",
+        b"du1:an1:ane",
+        b"ldu1:adu1:bnu1:bneu1:anee",
+    ] {
+        let err = transcode_to_json(encoded, &mut vec![], JsonEncodeOptions::new()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "{:?}", encoded);
+    }
+
+    // The keys are compared only with the keys of the same dictionary.
+    let mut json = vec![];
+    let encoded = b"d1:adu1:znee1:bdu1:aneu1:bi1eee";
+    transcode_to_json(&encoded[..], &mut json, JsonEncodeOptions::new()).unwrap();
+    assert_eq!(
+        String::from_utf8(json).unwrap(),
+        to_json(&encoded.decode().unwrap()).unwrap()
+    );
+}
+
+/// A writer which accepts at most one byte on each call.
+struct ShortWriter(Vec<u8>);
+
+impl std::io::Write for ShortWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.extend(buf.first());
+        Ok(buf.len().min(1))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn write_whole_json_to_short_writer() {
+    let mut encoded = vec![];
+    (&sample_value()).encode(&mut encoded).unwrap();
+    for value in [sample_value(), BencodexValue::Null] {
+        let mut writer = ShortWriter(vec![]);
+        to_json_writer(&value, &mut writer, JsonEncodeOptions::new()).unwrap();
+        assert_eq!(
+            String::from_utf8(writer.0).unwrap(),
+            to_json(&value).unwrap()
+        );
+    }
+
+    let mut writer = ShortWriter(vec![]);
+    transcode_to_json(encoded.as_slice(), &mut writer, JsonEncodeOptions::new()).unwrap();
+    assert_eq!(
+        String::from_utf8(writer.0).unwrap(),
+        to_json(&sample_value()).unwrap()
+    );
+}
+
+#[test]
+fn roundtrip_binary_encodings() {
+    let value = BencodexValue::from(vec![
+        BencodexValue::from(vec![0u8, 0, 0xfb, 0xff]),
+        BencodexValue::from(Vec::<u8>::new()),
+    ]);
+    let cases = [
+        (BinaryEncoding::Base64, r#"["b64:AAD7/w==","b64:"]"#),
+        (BinaryEncoding::Hex, r#"["0x0000fbff","0x"]"#),
+        (BinaryEncoding::Base64Url, r#"["b64url:AAD7_w","b64url:"]"#),
+        (BinaryEncoding::Base58, r#"["b58:11LBG","b58:"]"#),
+    ];
+    for (binary_encoding, expected) in cases {
+        let json = to_json_with_options(
+            &value,
+            JsonEncodeOptions::new().with_binary_encoding(binary_encoding),
+        )
+        .unwrap();
+        assert_eq!(json, expected);
+        assert_eq!(from_json_string(&json), Ok(value.clone()));
+    }
+}
+
+#[test]
+fn plain_mode_should_reject_colliding_keys() {
+    let mut dict = BencodexDictionary::new();
+    dict.insert(vec![0x00u8].into(), 1.into());
+    dict.insert("0x00".into(), 2.into());
+    let value = BencodexValue::from(vec![BencodexValue::Dictionary(dict)]);
+    let mut encoded = vec![];
+    (&value).encode(&mut encoded).unwrap();
+
+    let options = || {
+        JsonEncodeOptions::new()
+            .with_mode(JsonMode::Plain)
+            .with_binary_encoding(BinaryEncoding::Hex)
+    };
+    let err = to_json_with_options(&value, options()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    let err = transcode_to_json(encoded.as_slice(), &mut vec![], options()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+    // The keys do not collide in the other encodings, nor with the prefix of texts.
+    let options = || {
+        JsonEncodeOptions::new()
+            .with_mode(JsonMode::Plain)
+            .with_binary_encoding(BinaryEncoding::Base64)
+    };
+    let json = to_json_with_options(&value, options()).unwrap();
+    assert_eq!(json, r#"[{"b64:AA==":1,"0x00":2}]"#);
+    let mut transcoded = vec![];
+    transcode_to_json(encoded.as_slice(), &mut transcoded, options()).unwrap();
+    assert_eq!(String::from_utf8(transcoded).unwrap(), json);
+    let json = to_json_with_options(
+        &value,
+        JsonEncodeOptions::new().with_binary_encoding(BinaryEncoding::Hex),
+    )
+    .unwrap();
+    assert_eq!(json, "[{\"0x00\":\"1\",\"\u{FEFF}0x00\":\"2\"}]");
+}
+
+fn signed_value() -> BencodexValue {
+    let mut tx = BencodexDictionary::new();
+    tx.insert("signature".into(), vec![0xffu8, 0xfe].into());
+    tx.insert("payload".into(), vec![0xffu8, 0xfe].into());
+    tx.insert(vec![0xffu8].into(), vec![0xffu8].into());
+    let mut dict = BencodexDictionary::new();
+    dict.insert("signature".into(), vec![0xffu8, 0xfe].into());
+    dict.insert(
+        "txs".into(),
+        vec![BencodexValue::from(tx.clone()), tx.into()].into(),
+    );
+    dict.into()
+}
+
+fn override_options() -> JsonEncodeOptions {
+    JsonEncodeOptions::new()
+        .with_binary_encoding_override(BencodexPath::root().key("signature"), BinaryEncoding::Hex)
+        .with_binary_encoding_override(
+            BencodexPath::root().key("txs").index(1).key("payload"),
+            BinaryEncoding::Base58,
+        )
+        .with_binary_encoding_override(
+            BencodexPath::root().key("txs").index(1).key(vec![0xffu8]),
+            BinaryEncoding::Base64Url,
+        )
+}
+
+#[test]
+fn override_binary_encoding_by_path() {
+    let json = to_json_with_options(&signed_value(), override_options()).unwrap();
+    assert_eq!(
+        json,
+        concat!(
+            "{\"\u{FEFF}signature\":\"0xfffe\",\"\u{FEFF}txs\":[",
+            "{\"b64:/w==\":\"b64:/w==\",\"\u{FEFF}payload\":\"b64://4=\",",
+            "\"\u{FEFF}signature\":\"b64://4=\"},",
+            "{\"b64:/w==\":\"b64url:_w\",\"\u{FEFF}payload\":\"b58:LUu\",",
+            "\"\u{FEFF}signature\":\"b64://4=\"}]}",
+        )
+    );
+    assert_eq!(from_json_string(&json), Ok(signed_value()));
+}
+
+#[test]
+fn transcode_should_equal_to_json_with_overrides() {
+    let mut encoded = vec![];
+    (&signed_value()).encode(&mut encoded).unwrap();
+    let mut json = vec![];
+    transcode_to_json(encoded.as_slice(), &mut json, override_options()).unwrap();
+    assert_eq!(
+        String::from_utf8(json).unwrap(),
+        to_json_with_options(&signed_value(), override_options()).unwrap()
+    );
+}
+
+/// The patterns of the string types in [`BENCODEX_JSON_SCHEMA`], each with the alternatives.
+fn schema_patterns() -> Vec<(&'static str, Vec<regex::Regex>)> {
+    let schema: serde_json::Value = serde_json::from_str(BENCODEX_JSON_SCHEMA).unwrap();
+    ["integer", "binary", "text"]
+        .into_iter()
+        .map(|name| {
+            let def = &schema["$defs"][name];
+            let alternatives = match def["anyOf"].as_array() {
+                Some(alternatives) => alternatives.iter().collect(),
+                None => vec![def],
+            };
+            let patterns = alternatives
+                .into_iter()
+                .map(|alternative| regex::Regex::new(alternative["pattern"].as_str().unwrap()))
+                .collect::<Result<_, _>>()
+                .unwrap();
+            (name, patterns)
+        })
+        .collect()
+}
+
+/// Returns the name of the only string type the string is valid against.
+fn string_type(patterns: &[(&'static str, Vec<regex::Regex>)], s: &str) -> &'static str {
+    let matched: Vec<_> = patterns
+        .iter()
+        .filter(|(_, alternatives)| alternatives.iter().any(|p| p.is_match(s)))
+        .map(|(name, _)| *name)
+        .collect();
+    assert_eq!(matched.len(), 1, "{:?} matches {:?}", s, matched);
+    matched[0]
+}
+
+/// Asserts that each string in the JSON is of the type in the schema which it is decoded to.
+fn assert_valid_against_schema(
+    patterns: &[(&'static str, Vec<regex::Regex>)],
+    json: &serde_json::Value,
+) {
+    let assert_string = |s: &String| {
+        let decoded = from_json_string(&serde_json::to_string(s).unwrap()).unwrap();
+        let expected = match decoded {
+            BencodexValue::Number(_) => "integer",
+            BencodexValue::Binary(_) => "binary",
+            BencodexValue::Text(_) => "text",
+            _ => unreachable!(),
+        };
+        assert_eq!(expected, string_type(patterns, s));
+    };
+    match json {
+        serde_json::Value::String(s) => assert_string(s),
+        serde_json::Value::Array(array) => {
+            for json in array {
+                assert_valid_against_schema(patterns, json);
+            }
+        }
+        serde_json::Value::Object(object) => {
+            for (key, json) in object {
+                assert_string(key);
+                assert_valid_against_schema(patterns, json);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Compiles [`BENCODEX_JSON_SCHEMA`] with a JSON Schema 2020-12 validator.
+fn schema_validator() -> jsonschema::Validator {
+    let schema: serde_json::Value = serde_json::from_str(BENCODEX_JSON_SCHEMA).unwrap();
+    jsonschema::draft202012::new(&schema).unwrap()
+}
+
+fn assert_valid_json(validator: &jsonschema::Validator, json: &str) {
+    let instance: serde_json::Value = serde_json::from_str(json).unwrap();
+    let errors: Vec<_> = validator
+        .iter_errors(&instance)
+        .map(|e| e.to_string())
+        .collect();
+    assert!(errors.is_empty(), "{} is invalid: {:?}", json, errors);
+}
+
+const BINARY_ENCODINGS: [BinaryEncoding; 4] = [
+    BinaryEncoding::Base64,
+    BinaryEncoding::Hex,
+    BinaryEncoding::Base64Url,
+    BinaryEncoding::Base58,
+];
+
+#[test]
+fn spec_json_should_be_valid_against_schema() {
+    let validator = schema_validator();
+    let specs = utils::iter_spec().unwrap();
+    for spec in specs {
+        println!("---- SPEC [{}] ----", spec.name);
+        for binary_encoding in BINARY_ENCODINGS {
+            let json = to_json_with_options(
+                &spec.bvalue,
+                JsonEncodeOptions::new().with_binary_encoding(binary_encoding),
+            )
+            .unwrap();
+            assert_valid_json(&validator, &json);
+        }
+        println!("---- PASSED ----");
+    }
+}
+
+#[test]
+fn invalid_json_should_be_rejected_by_schema() {
+    let validator = schema_validator();
+    for json in [
+        r#""a""#,
+        "1",
+        "1.5",
+        r#""0xZZ""#,
+        r#""0x123""#,
+        r#""b64:AAA""#,
+        r#""b58:0OIl""#,
+        r#""12a""#,
+        r#"["\ufeffa", 1]"#,
+        r#"{"a": null}"#,
+        r#"{"-1": null}"#,
+        r#"{"\ufeffa": "b"}"#,
+    ] {
+        let instance: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert!(!validator.is_valid(&instance), "{} should be invalid", json);
+    }
+}
+
+#[test]
+fn json_should_be_valid_against_schema() {
+    let validator = schema_validator();
+    let patterns = schema_patterns();
+    let mut dict = BencodexDictionary::new();
+    dict.insert(vec![0xfbu8, 0xff, 0x00].into(), vec![0xffu8].into());
+    dict.insert("0x00".into(), "-123".into());
+    dict.insert("".into(), BencodexValue::Number((-123).into()));
+    dict.insert(
+        "list".into(),
+        vec![
+            BencodexValue::Null,
+            true.into(),
+            Vec::<u8>::new().into(),
+            vec![0u8, 1, 2, 3, 4].into(),
+        ]
+        .into(),
+    );
+    let value = BencodexValue::Dictionary(dict);
+
+    for binary_encoding in BINARY_ENCODINGS {
+        let json = to_json_with_options(
+            &value,
+            JsonEncodeOptions::new().with_binary_encoding(binary_encoding),
+        )
+        .unwrap();
+        println!("JSON: {}", json);
+        assert_eq!(from_json_string(&json), Ok(value.clone()));
+        assert_valid_json(&validator, &json);
+        assert_valid_against_schema(&patterns, &serde_json::from_str(&json).unwrap());
+    }
+}
@@ -1,3 +1,6 @@
 pub mod codec;
+pub mod inspect;
 #[cfg(feature = "json")]
 pub mod json;
+#[cfg(feature = "schema")]
+pub mod schema;
@@ -0,0 +1,158 @@
+// Generated by bencodex::schema::generate_rust. Do not edit.
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Transfer {
+    /// The value of `b"\x61"`.
+    pub a: i64,
+    /// The value of `"a"`.
+    pub a2: String,
+    /// The value of `"type"`.
+    pub r#type: TransferType,
+    /// The value of `"recipientList"`, if any.
+    pub recipient_list: Option<Vec<TransferRecipientListItem>>,
+}
+
+impl ::bencodex::ToBencodex for Transfer {
+    fn to_bencodex(&self) -> ::bencodex::BencodexValue {
+        let mut dict = ::bencodex::BencodexDictionary::new();
+        dict.insert(
+            ::bencodex::BencodexKey::Binary(b"a".to_vec()),
+            ::bencodex::ToBencodex::to_bencodex(&self.a),
+        );
+        dict.insert(
+            ::bencodex::BencodexKey::Text("a".to_string()),
+            ::bencodex::ToBencodex::to_bencodex(&self.a2),
+        );
+        dict.insert(
+            ::bencodex::BencodexKey::Text("type".to_string()),
+            ::bencodex::ToBencodex::to_bencodex(&self.r#type),
+        );
+        if let Some(value) = &self.recipient_list {
+            dict.insert(
+                ::bencodex::BencodexKey::Text("recipientList".to_string()),
+                ::bencodex::ToBencodex::to_bencodex(value),
+            );
+        }
+        ::bencodex::BencodexValue::Dictionary(dict)
+    }
+}
+
+impl ::bencodex::FromBencodex for Transfer {
+    fn from_bencodex(
+        value: &::bencodex::BencodexValue,
+    ) -> Result<Self, ::bencodex::FromBencodexError> {
+        let dict = ::bencodex::convert::as_dictionary(value)?;
+        let keys: [::bencodex::BencodexKey; 4] = [
+            ::bencodex::BencodexKey::Binary(b"a".to_vec()),
+            ::bencodex::BencodexKey::Text("a".to_string()),
+            ::bencodex::BencodexKey::Text("type".to_string()),
+            ::bencodex::BencodexKey::Text("recipientList".to_string()),
+        ];
+        ::bencodex::convert::reject_unexpected_keys(dict, &keys)?;
+        Ok(Transfer {
+            a: ::bencodex::convert::required(dict, &keys[0])?,
+            a2: ::bencodex::convert::required(dict, &keys[1])?,
+            r#type: ::bencodex::convert::required(dict, &keys[2])?,
+            recipient_list: ::bencodex::convert::optional(dict, &keys[3])?,
+        })
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum TransferType {
+    Null,
+    Binary(Vec<u8>),
+    Tuple(TransferTypeTuple),
+}
+
+impl ::bencodex::ToBencodex for TransferType {
+    fn to_bencodex(&self) -> ::bencodex::BencodexValue {
+        match self {
+            TransferType::Null => ::bencodex::BencodexValue::Null,
+            TransferType::Binary(value) => ::bencodex::ToBencodex::to_bencodex(value),
+            TransferType::Tuple(value) => ::bencodex::ToBencodex::to_bencodex(value),
+        }
+    }
+}
+
+impl ::bencodex::FromBencodex for TransferType {
+    fn from_bencodex(
+        value: &::bencodex::BencodexValue,
+    ) -> Result<Self, ::bencodex::FromBencodexError> {
+        let mut errors = Vec::new();
+        match <() as ::bencodex::FromBencodex>::from_bencodex(value) {
+            Ok(()) => return Ok(TransferType::Null),
+            Err(e) => errors.push(e),
+        }
+        match ::bencodex::FromBencodex::from_bencodex(value) {
+            Ok(value) => return Ok(TransferType::Binary(value)),
+            Err(e) => errors.push(e),
+        }
+        match ::bencodex::FromBencodex::from_bencodex(value) {
+            Ok(value) => return Ok(TransferType::Tuple(value)),
+            Err(e) => errors.push(e),
+        }
+        Err(::bencodex::convert::no_matching_variant(errors))
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct TransferTypeTuple(pub bool, pub ::bencodex::num_bigint::BigInt);
+
+impl ::bencodex::ToBencodex for TransferTypeTuple {
+    fn to_bencodex(&self) -> ::bencodex::BencodexValue {
+        ::bencodex::BencodexValue::List(vec![
+            ::bencodex::ToBencodex::to_bencodex(&self.0),
+            ::bencodex::ToBencodex::to_bencodex(&self.1),
+        ])
+    }
+}
+
+impl ::bencodex::FromBencodex for TransferTypeTuple {
+    fn from_bencodex(
+        value: &::bencodex::BencodexValue,
+    ) -> Result<Self, ::bencodex::FromBencodexError> {
+        let list = ::bencodex::convert::as_tuple(value, 2)?;
+        Ok(TransferTypeTuple(
+            ::bencodex::convert::item(list, 0)?,
+            ::bencodex::convert::item(list, 1)?,
+        ))
+    }
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct TransferRecipientListItem {
+    /// The value of `b"\x00\xff"`.
+    pub key_00ff: ::bencodex::BencodexValue,
+    /// The values of the other keys.
+    pub additional: ::std::collections::BTreeMap<::bencodex::BencodexKey, bool>,
+}
+
+impl ::bencodex::ToBencodex for TransferRecipientListItem {
+    fn to_bencodex(&self) -> ::bencodex::BencodexValue {
+        let mut dict = ::bencodex::BencodexDictionary::new();
+        for (key, value) in &self.additional {
+            dict.insert(key.clone(), ::bencodex::ToBencodex::to_bencodex(value));
+        }
+        dict.insert(
+            ::bencodex::BencodexKey::Binary(b"\x00\xff".to_vec()),
+            ::bencodex::ToBencodex::to_bencodex(&self.key_00ff),
+        );
+        ::bencodex::BencodexValue::Dictionary(dict)
+    }
+}
+
+impl ::bencodex::FromBencodex for TransferRecipientListItem {
+    fn from_bencodex(
+        value: &::bencodex::BencodexValue,
+    ) -> Result<Self, ::bencodex::FromBencodexError> {
+        let dict = ::bencodex::convert::as_dictionary(value)?;
+        let keys: [::bencodex::BencodexKey; 1] = [
+            ::bencodex::BencodexKey::Binary(b"\x00\xff".to_vec()),
+        ];
+        Ok(TransferRecipientListItem {
+            key_00ff: ::bencodex::convert::required(dict, &keys[0])?,
+            additional: ::bencodex::convert::additional(dict, &keys)?,
+        })
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+
+use bencodex::schema::{generate_rust, validate, Schema};
+use bencodex::{
+    BencodexDictionary, BencodexKey, BencodexPath, BencodexValue, FromBencodex, FromBencodexError,
+    ToBencodex,
+};
+
+mod generated {
+    include!("generated/transfer.rs");
+}
+
+use generated::{Transfer, TransferRecipientListItem, TransferType, TransferTypeTuple};
+
+fn schema() -> Schema {
+    Schema::Dictionary {
+        required: BTreeMap::from([
+            (
+                b"a".to_vec().into(),
+                Schema::Integer {
+                    min: Some(0.into()),
+                    max: Some(100.into()),
+                },
+            ),
+            ("a".into(), Schema::Text { pattern: None }),
+            (
+                "type".into(),
+                Schema::Union(vec![
+                    Schema::Null,
+                    Schema::Binary {
+                        min_length: Some(20),
+                        max_length: Some(20),
+                    },
+                    Schema::Tuple(vec![
+                        Schema::Boolean,
+                        Schema::Integer {
+                            min: None,
+                            max: None,
+                        },
+                    ]),
+                ]),
+            ),
+        ]),
+        optional: BTreeMap::from([(
+            "recipientList".into(),
+            Schema::List(Box::new(Schema::Dictionary {
+                required: BTreeMap::from([(vec![0x00u8, 0xff].into(), Schema::Any)]),
+                optional: BTreeMap::new(),
+                additional: Some(Box::new(Schema::Boolean)),
+            })),
+        )]),
+        additional: None,
+    }
+}
+
+#[test]
+fn generated_code_should_be_up_to_date() {
+    assert_eq!(
+        generate_rust(&schema(), "Transfer"),
+        include_str!("generated/transfer.rs")
+    );
+}
+
+fn transfer() -> Transfer {
+    Transfer {
+        a: 42,
+        a2: "text".to_string(),
+        r#type: TransferType::Tuple(TransferTypeTuple(true, (-1).into())),
+        recipient_list: Some(vec![TransferRecipientListItem {
+            key_00ff: BencodexValue::Null,
+            additional: BTreeMap::from([(BencodexKey::from("extra"), false)]),
+        }]),
+    }
+}
+
+fn transfer_value() -> BencodexValue {
+    let mut item = BencodexDictionary::new();
+    item.insert(vec![0x00u8, 0xff].into(), BencodexValue::Null);
+    item.insert("extra".into(), false.into());
+    let mut dict = BencodexDictionary::new();
+    dict.insert(b"a".to_vec().into(), 42.into());
+    dict.insert("a".into(), "text".into());
+    dict.insert(
+        "type".into(),
+        vec![BencodexValue::from(true), (-1).into()].into(),
+    );
+    dict.insert(
+        "recipientList".into(),
+        vec![BencodexValue::Dictionary(item)].into(),
+    );
+    BencodexValue::Dictionary(dict)
+}
+
+#[test]
+fn generated_code_should_roundtrip() {
+    let value = transfer_value();
+    assert!(validate(&value, &schema()).is_empty());
+    assert_eq!(transfer().to_bencodex(), value);
+    assert_eq!(Transfer::from_bencodex(&value), Ok(transfer()));
+
+    let mut without_optional = transfer();
+    without_optional.recipient_list = None;
+    without_optional.r#type = TransferType::Null;
+    assert_eq!(
+        Transfer::from_bencodex(&without_optional.to_bencodex()),
+        Ok(without_optional)
+    );
+}
+
+#[test]
+fn generated_code_should_keep_binary_and_text_keys_apart() {
+    let mut dict = match transfer_value() {
+        BencodexValue::Dictionary(dict) => dict,
+        _ => unreachable!(),
+    };
+    let value = dict.remove(&BencodexKey::from("type")).unwrap();
+    dict.insert(b"type".to_vec().into(), value);
+
+    assert_eq!(
+        Transfer::from_bencodex(&BencodexValue::Dictionary(dict)),
+        Err(FromBencodexError::UnexpectedKeyError {
+            path: BencodexPath::root().key(b"type".to_vec())
+        })
+    );
+}
+
+#[test]
+fn generated_code_should_report_error_paths() {
+    let mut dict = match transfer_value() {
+        BencodexValue::Dictionary(dict) => dict,
+        _ => unreachable!(),
+    };
+    dict.insert("type".into(), vec![BencodexValue::from(true)].into());
+    assert_eq!(
+        Transfer::from_bencodex(&BencodexValue::Dictionary(dict.clone())),
+        Err(FromBencodexError::TupleLengthMismatchError {
+            path: BencodexPath::root().key("type"),
+            expected: 2,
+            actual: 1,
+        })
+    );
+
+    dict.remove(&BencodexKey::from(b"a".to_vec()));
+    assert_eq!(
+        Transfer::from_bencodex(&BencodexValue::Dictionary(dict)),
+        Err(FromBencodexError::MissingKeyError {
+            path: BencodexPath::root().key(b"a".to_vec())
+        })
+    );
+}